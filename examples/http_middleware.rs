@@ -0,0 +1,32 @@
+use wstd::http::middleware::{DefaultHeadersLayer, LoggingLayer, RequestIdLayer};
+use wstd::http::router::Routed;
+use wstd::http::server::{Finished, Handler, IncomingRequest, Responder};
+use wstd::http::{HeaderMap, HeaderValue, Response, Router, StatusCode};
+
+async fn hello(_routed: Routed, responder: Responder) -> Finished {
+    let response = Response::new(StatusCode::Ok).set_body("Hello, middleware!\n");
+    responder.respond(response).await
+}
+
+async fn custom_server(_routed: Routed, responder: Responder) -> Finished {
+    let mut response = Response::new(StatusCode::Ok);
+    response
+        .headers_mut()
+        .insert("server", HeaderValue::from_static("custom"));
+    responder.respond(response).await
+}
+
+#[wstd::http_server]
+async fn main(request: IncomingRequest, responder: Responder) -> Finished {
+    let mut headers = HeaderMap::new();
+    headers.insert("server", HeaderValue::from_static("wstd"));
+
+    Router::new()
+        .get("/", hello)
+        .get("/custom", custom_server)
+        .layer(DefaultHeadersLayer::new(headers))
+        .layer(RequestIdLayer::new())
+        .layer(LoggingLayer::new())
+        .handle(request, responder)
+        .await
+}
@@ -0,0 +1,41 @@
+use wstd::http::router::Routed;
+use wstd::http::server::{Finished, IncomingRequest, Responder};
+use wstd::http::{Response, Router, StatusCode};
+
+async fn greet(routed: Routed<&'static str>, responder: Responder) -> Finished {
+    let greeting = routed.state;
+    let name = routed.params.get("name").unwrap_or("world");
+    let response = Response::new(StatusCode::Ok).set_body(format!("{greeting}, {name}!\n"));
+    responder.respond(response).await
+}
+
+async fn file(routed: Routed, responder: Responder) -> Finished {
+    let path = routed.params.get("path").unwrap_or_default();
+    let response = Response::new(StatusCode::Ok).set_body(format!("file: {path}\n"));
+    responder.respond(response).await
+}
+
+async fn not_in_api(_: Routed<&'static str>, responder: Responder) -> Finished {
+    let response = Response::new(StatusCode::NotFound).set_body("no such api\n");
+    responder.respond(response).await
+}
+
+async fn version(_: Routed, responder: Responder) -> Finished {
+    let response = Response::new(StatusCode::Ok).set_body("1\n");
+    responder.respond(response).await
+}
+
+#[wstd::http_server]
+async fn main(request: IncomingRequest, responder: Responder) -> Finished {
+    let api = Router::with_state("Hello")
+        .get("/greet", greet)
+        .get("/greet/:name", greet)
+        .fallback(not_in_api);
+
+    Router::new()
+        .nest("/api", api)
+        .get("/api/version", version)
+        .get("/static/*path", file)
+        .handle(request, responder)
+        .await
+}
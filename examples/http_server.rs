@@ -0,0 +1,44 @@
+use wstd::http::server::{Finished, IncomingRequest, Responder};
+use wstd::http::{Response, StatusCode};
+
+#[wstd::http_server]
+async fn main(request: IncomingRequest, responder: Responder) -> Finished {
+    match request.uri().path() {
+        "/" => {
+            let response =
+                Response::new(StatusCode::Ok).set_body("Hello, wasi:http/proxy world!\n");
+            responder.respond(response).await
+        }
+        "/echo" => {
+            // Stream the request body straight back into the response body.
+            let response = Response::new(StatusCode::Ok).set_body(request.into_body());
+            responder.respond(response).await
+        }
+        "/upload" => {
+            // List the fields of a multipart form, and the size of each.
+            let mut form = match request.multipart() {
+                Ok(form) => form,
+                Err(_) => {
+                    let response = Response::new(StatusCode::BadRequest);
+                    return responder.respond(response).await;
+                }
+            };
+            let mut summary = String::new();
+            loop {
+                let mut field = match form.next_field().await {
+                    Ok(Some(field)) => field,
+                    Ok(None) => break,
+                    Err(err) => return responder.fail(err),
+                };
+                let name = field.name().unwrap_or_default().to_owned();
+                match field.bytes().await {
+                    Ok(bytes) => summary.push_str(&format!("{name}: {} bytes\n", bytes.len())),
+                    Err(err) => return responder.fail(err),
+                }
+            }
+            let response = Response::new(StatusCode::Ok).set_body(summary);
+            responder.respond(response).await
+        }
+        _ => responder.respond(Response::new(StatusCode::NotFound)).await,
+    }
+}
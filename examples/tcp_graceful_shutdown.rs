@@ -0,0 +1,77 @@
+use std::future::{self, Future};
+use std::pin::{pin, Pin};
+use wstd::io::{self, AsyncRead, AsyncWrite};
+use wstd::iter::AsyncIterator;
+use wstd::net::{ConnectionGuard, ConnectionTracker, ShutdownToken, TcpListener, TcpStream};
+use wstd::time::Duration;
+
+#[wstd::main]
+async fn main() -> io::Result<()> {
+    let listener = TcpListener::bind("127.0.0.1:8081").await?;
+    println!("Listening on {}", listener.local_addr()?);
+    println!("type `echo shutdown | nc localhost 8081` to stop the server");
+
+    let token = ShutdownToken::new();
+    let tracker = ConnectionTracker::new();
+    let mut connections = Connections::default();
+
+    let mut incoming = listener.incoming_with_shutdown(token.clone());
+    while let Some(stream) = connections.run_until(incoming.next()).await {
+        let stream = stream?;
+        println!("Accepted from: {}", stream.peer_addr()?);
+        let (token, guard) = (token.clone(), tracker.track());
+        connections.push(async move {
+            if let Err(err) = echo(stream, token, guard).await {
+                eprintln!("Connection failed: {err}");
+            }
+        });
+    }
+
+    println!("Draining {} connections", tracker.active());
+    connections
+        .run_until(tracker.drain(Duration::from_secs(5)))
+        .await?;
+    println!("Shut down");
+    Ok(())
+}
+
+/// Echo everything read from `stream` back to it, and request a shutdown
+/// when reading `shutdown`.
+async fn echo(
+    mut stream: TcpStream,
+    token: ShutdownToken,
+    _guard: ConnectionGuard,
+) -> io::Result<()> {
+    let mut buf = [0; 1024];
+    loop {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        if buf[..n].trim_ascii() == b"shutdown" {
+            token.shutdown();
+        }
+        stream.write_all(&buf[..n]).await?;
+    }
+}
+
+/// Connections which are handled concurrently with each other.
+#[derive(Default)]
+struct Connections(Vec<Pin<Box<dyn Future<Output = ()>>>>);
+
+impl Connections {
+    fn push(&mut self, connection: impl Future<Output = ()> + 'static) {
+        self.0.push(Box::pin(connection));
+    }
+
+    /// Handle the connections until `fut` completes.
+    async fn run_until<F: Future>(&mut self, fut: F) -> F::Output {
+        let mut fut = pin!(fut);
+        future::poll_fn(|cx| {
+            self.0
+                .retain_mut(|connection| connection.as_mut().poll(cx).is_pending());
+            fut.as_mut().poll(cx)
+        })
+        .await
+    }
+}
@@ -83,3 +83,62 @@ pub fn attr_macro_test(_attr: TokenStream, item: TokenStream) -> TokenStream {
     }
     .into()
 }
+
+#[proc_macro_attribute]
+pub fn attr_macro_http_server(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as ItemFn);
+
+    if input.sig.asyncness.is_none() {
+        return quote_spanned! { input.sig.fn_token.span()=>
+            compile_error!("fn must be `async fn`");
+        }
+        .into();
+    }
+
+    if input.sig.ident != "main" {
+        return quote_spanned! { input.sig.ident.span()=>
+            compile_error!("only `async fn main` can be used for #[wstd::http_server]");
+        }
+        .into();
+    }
+
+    if input.sig.inputs.len() != 2 {
+        return quote_spanned! { input.sig.inputs.span()=>
+            compile_error!("#[wstd::http_server] main must take an `IncomingRequest` and a `Responder`");
+        }
+        .into();
+    }
+    let attrs = input.attrs;
+    let inputs = input.sig.inputs;
+    let output = input.sig.output;
+    let block = input.block;
+    quote! {
+        struct TheServer;
+
+        impl ::wstd::__internal::wasi::exports::http::incoming_handler::Guest for TheServer {
+            fn handle(
+                request: ::wstd::__internal::wasi::http::types::IncomingRequest,
+                response_out: ::wstd::__internal::wasi::http::types::ResponseOutparam,
+            ) {
+                #(#attrs)*
+                async fn __run(#inputs) #output {
+                    #block
+                }
+
+                ::wstd::http::server::handle(request, response_out, __run)
+            }
+        }
+
+        ::wstd::__internal::wasi::http::proxy::export!(
+            TheServer with_types_in ::wstd::__internal::wasi
+        );
+
+        // HTTP server components are driven through the exported `handle`
+        // function rather than `main`, but a binary still needs a `main`.
+        // It is never called.
+        fn main() {
+            unreachable!("HTTP server components are run through `wasi:http/incoming-handler`");
+        }
+    }
+    .into()
+}
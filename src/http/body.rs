@@ -1,7 +1,17 @@
 //! HTTP body types
 
-use crate::io::{AsyncRead, Cursor, Empty};
+use super::{fields::header_map_to_wasi, urlencoded, Error, HeaderMap, HeaderValue, Result};
+use crate::io::{self, AsyncRead, AsyncWrite, Cursor, Empty};
+use crate::iter::AsyncIterator;
+use crate::runtime::Reactor;
+use std::future::Future;
+use std::pin::Pin;
+use wasi::http::types::{http_error_code, Fields, OutgoingBody as WasiOutgoingBody};
+use wasi::io::streams::StreamError;
 
+#[cfg(any(feature = "gzip", feature = "deflate"))]
+pub use super::encoder::{CompressedBody, ContentEncoding};
+pub use super::multipart::{Multipart, MultipartBody, MultipartField, MultipartReader, Part};
 pub use super::response::IncomingBody;
 
 /// A trait representing an HTTP body.
@@ -14,6 +24,13 @@ pub trait Body: AsyncRead {
     fn is_empty(&self) -> bool {
         matches!(self.len(), Some(0))
     }
+
+    /// Returns the trailers to send after the body.
+    ///
+    /// This is called once the body has been read to the end.
+    async fn trailers(&mut self) -> Result<Option<HeaderMap>> {
+        Ok(None)
+    }
 }
 
 /// Conversion into a `Body`.
@@ -77,8 +94,564 @@ impl<T: AsRef<[u8]>> Body for BoundedBody<T> {
     }
 }
 
+/// A body which is followed by trailers.
+///
+/// ```
+/// use wstd::http::body::WithTrailers;
+/// use wstd::http::{HeaderMap, HeaderValue};
+///
+/// let mut trailers = HeaderMap::new();
+/// trailers.insert("grpc-status", HeaderValue::from_static("0"));
+/// let body = WithTrailers::new("hello", trailers);
+/// ```
+#[derive(Debug)]
+pub struct WithTrailers<B> {
+    body: B,
+    trailers: Option<HeaderMap>,
+}
+
+impl<B: Body> WithTrailers<B> {
+    /// Create a new body which sends `trailers` after `body`.
+    pub fn new<T: IntoBody<IntoBody = B>>(body: T, trailers: HeaderMap) -> Self {
+        Self {
+            body: body.into_body(),
+            trailers: Some(trailers),
+        }
+    }
+}
+
+impl<B: Body> AsyncRead for WithTrailers<B> {
+    async fn read(&mut self, buf: &mut [u8]) -> crate::io::Result<usize> {
+        self.body.read(buf).await
+    }
+}
+
+impl<B: Body> Body for WithTrailers<B> {
+    fn len(&self) -> Option<usize> {
+        self.body.len()
+    }
+
+    async fn trailers(&mut self) -> Result<Option<HeaderMap>> {
+        Ok(self.trailers.take())
+    }
+}
+
+/// An `application/x-www-form-urlencoded` body, as sent by HTML forms.
+///
+/// [`Request::form`](super::Request::form) sets the body of a request to a
+/// form, along with its `Content-Type`.
+///
+/// ```
+/// use wstd::http::body::Form;
+///
+/// let form = Form::new().append("name", "Ferris").append("likes", "rust & wasm");
+/// assert_eq!(form.as_str(), "name=Ferris&likes=rust+%26+wasm");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Form {
+    encoded: String,
+}
+
+impl Form {
+    /// Create an empty form.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a `name=value` pair to the form.
+    pub fn append(mut self, name: impl AsRef<str>, value: impl AsRef<str>) -> Self {
+        urlencoded::append_pair(&mut self.encoded, name.as_ref(), value.as_ref());
+        self
+    }
+
+    /// Get the encoded form.
+    pub fn as_str(&self) -> &str {
+        &self.encoded
+    }
+
+    /// Get the `Content-Type` of a form body.
+    pub fn content_type() -> HeaderValue {
+        HeaderValue::from_static("application/x-www-form-urlencoded")
+    }
+}
+
+impl<K: AsRef<str>, V: AsRef<str>> FromIterator<(K, V)> for Form {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(pairs: I) -> Self {
+        pairs
+            .into_iter()
+            .fold(Self::new(), |form, (name, value)| form.append(name, value))
+    }
+}
+
+impl IntoBody for Form {
+    type IntoBody = BoundedBody<Vec<u8>>;
+    fn into_body(self) -> Self::IntoBody {
+        self.encoded.into_bytes().into_body()
+    }
+}
+
+/// A body which is streamed from an [`AsyncRead`] or an [`AsyncIterator`] of
+/// chunks, without buffering it in memory.
+///
+/// ```no_run
+/// use wstd::http::body::StreamingBody;
+/// use wstd::http::{Method, Request};
+/// use wstd::io::Cursor;
+///
+/// let data = Cursor::new(vec![0; 1024]);
+/// let body = StreamingBody::from_reader(data, Some(1024));
+/// let request = Request::new(Method::PUT, "https://example.com/upload".parse().unwrap())
+///     .set_body(body);
+/// ```
+#[derive(Debug)]
+pub struct StreamingBody<S> {
+    source: S,
+    len: Option<u64>,
+}
+
+impl<R: AsyncRead> StreamingBody<R> {
+    /// Create a body which reads from `reader`.
+    ///
+    /// If `len` is known, it is sent as the `Content-Length` of the body, and
+    /// `reader` must produce exactly that many bytes.
+    pub fn from_reader(reader: R, len: Option<u64>) -> Self {
+        Self {
+            source: reader,
+            len,
+        }
+    }
+}
+
+impl<I, E> StreamingBody<Chunks<I>>
+where
+    I: AsyncIterator<Item = std::result::Result<Vec<u8>, E>>,
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    /// Create a body which sends each chunk produced by `iter`.
+    ///
+    /// The length of the body isn't known, so it is sent with chunked
+    /// encoding. If `iter` produces an error, sending the body fails.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_iter(iter: I) -> Self {
+        Self {
+            source: Chunks {
+                iter,
+                chunk: Vec::new(),
+                offset: 0,
+            },
+            len: None,
+        }
+    }
+}
+
+impl<S: AsyncRead> AsyncRead for StreamingBody<S> {
+    async fn read(&mut self, buf: &mut [u8]) -> crate::io::Result<usize> {
+        self.source.read(buf).await
+    }
+}
+
+impl<S: AsyncRead> Body for StreamingBody<S> {
+    fn len(&self) -> Option<usize> {
+        self.len.and_then(|len| usize::try_from(len).ok())
+    }
+}
+
+/// An [`AsyncRead`] over the chunks of an [`AsyncIterator`], as created by
+/// [`StreamingBody::from_iter`].
+#[derive(Debug)]
+pub struct Chunks<I> {
+    iter: I,
+    chunk: Vec<u8>,
+    // How many bytes have we already read from the chunk?
+    offset: usize,
+}
+
+impl<I, E> AsyncRead for Chunks<I>
+where
+    I: AsyncIterator<Item = std::result::Result<Vec<u8>, E>>,
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    async fn read(&mut self, buf: &mut [u8]) -> crate::io::Result<usize> {
+        while self.offset == self.chunk.len() {
+            match self.iter.next().await {
+                Some(Ok(chunk)) => {
+                    self.chunk = chunk;
+                    self.offset = 0;
+                }
+                Some(Err(err)) => return Err(io::Error::other(err)),
+                None => return Ok(0),
+            }
+        }
+        let len = (self.chunk.len() - self.offset).min(buf.len());
+        buf[..len].copy_from_slice(&self.chunk[self.offset..self.offset + len]);
+        self.offset += len;
+        Ok(len)
+    }
+}
+
+/// An [`AsyncRead`] which can be used as a trait object.
+pub(crate) trait DynRead {
+    fn read_dyn<'a>(
+        &'a mut self,
+        buf: &'a mut [u8],
+    ) -> Pin<Box<dyn Future<Output = io::Result<usize>> + 'a>>;
+}
+
+impl<R: AsyncRead> DynRead for R {
+    fn read_dyn<'a>(
+        &'a mut self,
+        buf: &'a mut [u8],
+    ) -> Pin<Box<dyn Future<Output = io::Result<usize>> + 'a>> {
+        Box::pin(AsyncRead::read(self, buf))
+    }
+}
+
+/// A [`Body`] of any type, as sent by a
+/// [`Transport`](super::transport::Transport).
+pub struct BoxBody<'a> {
+    inner: Box<dyn DynBody + 'a>,
+}
+
+impl<'a> BoxBody<'a> {
+    /// Erase the type of `body`.
+    pub fn new(body: impl Body + 'a) -> Self {
+        Self {
+            inner: Box::new(body),
+        }
+    }
+}
+
+impl AsyncRead for BoxBody<'_> {
+    async fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read_dyn(buf).await
+    }
+}
+
+impl Body for BoxBody<'_> {
+    fn len(&self) -> Option<usize> {
+        self.inner.len_dyn()
+    }
+
+    async fn trailers(&mut self) -> Result<Option<HeaderMap>> {
+        self.inner.trailers_dyn().await
+    }
+}
+
+impl std::fmt::Debug for BoxBody<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BoxBody")
+            .field("len", &self.inner.len_dyn())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A [`Body`] which can be used as a trait object.
+trait DynBody: DynRead {
+    fn len_dyn(&self) -> Option<usize>;
+
+    fn trailers_dyn<'a>(
+        &'a mut self,
+    ) -> Pin<Box<dyn Future<Output = Result<Option<HeaderMap>>> + 'a>>;
+}
+
+impl<B: Body> DynBody for B {
+    fn len_dyn(&self) -> Option<usize> {
+        self.len()
+    }
+
+    fn trailers_dyn<'a>(
+        &'a mut self,
+    ) -> Pin<Box<dyn Future<Output = Result<Option<HeaderMap>>> + 'a>> {
+        Box::pin(self.trailers())
+    }
+}
+
+impl<B: Body + ?Sized> Body for &mut B {
+    fn len(&self) -> Option<usize> {
+        (**self).len()
+    }
+
+    async fn trailers(&mut self) -> Result<Option<HeaderMap>> {
+        (**self).trailers().await
+    }
+}
+
+/// A body which keeps a copy of what was read from it, so it can be sent
+/// again, for example to follow a redirect.
+#[derive(Debug)]
+pub(crate) struct Replay<B> {
+    body: B,
+    // What was read from `body`, or `None` once it got larger than the limit.
+    copy: Option<Vec<u8>>,
+    limit: usize,
+    // Whether anything was read from `body` yet.
+    started: bool,
+    // The trailers of `body`, once it was read to the end.
+    trailers: Option<Option<HeaderMap>>,
+    // Where we are in `copy`, once it is being replayed.
+    replay_offset: Option<usize>,
+}
+
+impl<B: Body> Replay<B> {
+    /// Wrap `body`, keeping a copy of up to `limit` bytes of it.
+    pub(crate) fn new(body: B, limit: usize) -> Self {
+        let copy = match body.len() {
+            Some(len) if len > limit => None,
+            _ => Some(Vec::new()),
+        };
+        Self {
+            body,
+            copy,
+            limit,
+            started: false,
+            trailers: None,
+            replay_offset: None,
+        }
+    }
+
+    /// Start sending the body again from the beginning.
+    ///
+    /// If the body was only partially sent, for example because the server
+    /// responded before receiving all of it, the rest of it is read into the
+    /// copy first.
+    ///
+    /// Returns `false` if this isn't possible, because the body was too large
+    /// to keep a copy of, or reading the rest of it failed.
+    pub(crate) async fn rewind(&mut self) -> bool {
+        if !self.started {
+            return true;
+        }
+        let mut buf = [0; 4096];
+        while self.copy.is_some() && self.trailers.is_none() {
+            let read = match self.read(&mut buf).await {
+                Ok(0) => self.trailers().await.is_ok(),
+                Ok(_) => true,
+                Err(_) => false,
+            };
+            if !read {
+                return false;
+            }
+        }
+        if self.copy.is_none() {
+            return false;
+        }
+        self.replay_offset = Some(0);
+        true
+    }
+
+    /// Replace the body with an empty one.
+    pub(crate) fn clear(&mut self) {
+        self.copy = Some(Vec::new());
+        self.trailers = Some(None);
+        self.replay_offset = Some(0);
+    }
+}
+
+impl<B: Body> AsyncRead for Replay<B> {
+    async fn read(&mut self, buf: &mut [u8]) -> crate::io::Result<usize> {
+        if let (Some(offset), Some(copy)) = (&mut self.replay_offset, &self.copy) {
+            let len = (copy.len() - *offset).min(buf.len());
+            buf[..len].copy_from_slice(&copy[*offset..*offset + len]);
+            *offset += len;
+            return Ok(len);
+        }
+        let n = self.body.read(buf).await?;
+        self.started |= n > 0;
+        if let Some(copy) = &mut self.copy {
+            if copy.len() + n > self.limit {
+                self.copy = None;
+            } else {
+                copy.extend_from_slice(&buf[..n]);
+            }
+        }
+        Ok(n)
+    }
+}
+
+impl<B: Body> Body for Replay<B> {
+    fn len(&self) -> Option<usize> {
+        match (self.replay_offset, &self.copy) {
+            (Some(offset), Some(copy)) => Some(copy.len() - offset),
+            _ => self.body.len(),
+        }
+    }
+
+    async fn trailers(&mut self) -> Result<Option<HeaderMap>> {
+        if let Some(trailers) = &self.trailers {
+            return Ok(trailers.clone());
+        }
+        let trailers = self.body.trailers().await?;
+        self.trailers = Some(trailers.clone());
+        Ok(trailers)
+    }
+}
+
 impl Body for Empty {
     fn len(&self) -> Option<usize> {
         Some(0)
     }
 }
+
+/// An outgoing HTTP body, which is sent to the peer as it is written.
+#[derive(Debug)]
+pub struct OutgoingBody {
+    // IMPORTANT: the order of these fields here matters. `stream` must
+    // be dropped before `body`.
+    stream: OutputStream,
+    body: WasiOutgoingBody,
+    // The `Content-Length` the body was declared with, if any.
+    content_length: Option<u64>,
+}
+
+impl OutgoingBody {
+    /// Create a body for a request or response sent with `headers`.
+    pub(crate) fn new(body: WasiOutgoingBody, headers: &Fields) -> Result<Self> {
+        let content_length = headers
+            .get(&"content-length".to_owned())
+            .first()
+            .map(|value| {
+                std::str::from_utf8(value)
+                    .ok()
+                    .and_then(|value| value.trim().parse::<u64>().ok())
+                    .ok_or_else(|| Error::other("outgoing content-length should be a u64"))
+            })
+            .transpose()?;
+        let stream = body
+            .write()
+            .map_err(|()| Error::other("outgoing body stream was already taken"))?;
+        Ok(Self {
+            stream: OutputStream::new(stream),
+            body,
+            content_length,
+        })
+    }
+
+    /// Finish sending the body.
+    ///
+    /// If an `OutgoingBody` is dropped without calling `finish`, the body is
+    /// considered incomplete and the peer will see an error.
+    pub fn finish(self) -> Result<()> {
+        self.finish_with(None)
+    }
+
+    /// Finish sending the body, followed by `trailers`.
+    pub fn finish_with_trailers(self, trailers: HeaderMap) -> Result<()> {
+        self.finish_with(Some(trailers))
+    }
+
+    fn finish_with(self, trailers: Option<HeaderMap>) -> Result<()> {
+        let Self {
+            stream,
+            body,
+            content_length,
+        } = self;
+        let written = stream.written();
+        drop(stream);
+        if let Some(content_length) = content_length.filter(|len| written < *len) {
+            // Dropping the body without finishing it makes the peer see an
+            // error, rather than a body which is silently cut short.
+            return Err(Error::other(format!(
+                "body is shorter than its content-length: wrote {written} of {content_length} bytes"
+            )));
+        }
+        let trailers = trailers.as_ref().map(header_map_to_wasi).transpose()?;
+        WasiOutgoingBody::finish(body, trailers)
+            .map_err(|e| Error::from(e).context(format!("finishing body after {written} bytes")))
+    }
+
+    /// Send all of `body` and its trailers, and finish sending the body.
+    pub(crate) async fn send<B: Body>(mut self, mut body: B) -> Result<()> {
+        if let Err(err) = io::copy(&mut body, &mut self).await {
+            let written = self.written();
+            return Err(
+                Error::from_io(err).context(format!("writing body failed after {written} bytes"))
+            );
+        }
+        let trailers = body.trailers().await?;
+        self.finish_with(trailers)
+    }
+
+    /// The number of bytes which were written to the body so far.
+    pub(crate) fn written(&self) -> u64 {
+        self.stream.written()
+    }
+}
+
+impl AsyncWrite for OutgoingBody {
+    async fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(content_length) = self.content_length {
+            if self.written() + buf.len() as u64 > content_length {
+                return Err(io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    Error::other(format!(
+                        "body is longer than its content-length of {content_length} bytes"
+                    )),
+                ));
+            }
+        }
+        self.stream.write(buf).await
+    }
+
+    async fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush().await
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct OutputStream {
+    stream: wasi::http::types::OutputStream,
+    written: u64,
+}
+
+impl OutputStream {
+    pub(crate) fn new(stream: wasi::http::types::OutputStream) -> Self {
+        Self { stream, written: 0 }
+    }
+
+    /// The number of bytes which were written to the stream.
+    pub(crate) fn written(&self) -> u64 {
+        self.written
+    }
+}
+
+impl AsyncWrite for OutputStream {
+    async fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let max = self.stream.check_write().map_err(stream_error)? as usize;
+            if max == 0 {
+                Reactor::current().wait_for(self.stream.subscribe()).await;
+                continue;
+            }
+            let max = max.min(buf.len());
+            self.stream.write(&buf[0..max]).map_err(stream_error)?;
+            self.written += max as u64;
+            return Ok(max);
+        }
+    }
+
+    async fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush().map_err(stream_error)?;
+        Reactor::current().wait_for(self.stream.subscribe()).await;
+        // Surface any error from the flush itself.
+        self.stream.check_write().map_err(stream_error)?;
+        Ok(())
+    }
+}
+
+/// Convert a wasi stream error into an `io::Error`, preserving the
+/// `wasi:http` error code behind it, if any, as an [`Error`].
+pub(crate) fn stream_error(err: StreamError) -> io::Error {
+    match err {
+        StreamError::Closed => io::Error::new(
+            std::io::ErrorKind::BrokenPipe,
+            Error::other("stream closed"),
+        ),
+        StreamError::LastOperationFailed(err) => match http_error_code(&err) {
+            Some(code) => io::Error::other(Error::from(code)),
+            None => io::Error::other(Error::other(err.to_debug_string())),
+        },
+    }
+}
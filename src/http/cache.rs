@@ -0,0 +1,577 @@
+//! Caching responses across requests
+//!
+//! A [`Cache`] attached to a [`Client`] stores responses to `GET` requests,
+//! and answers later requests for the same URL from memory while the stored
+//! response is fresh, following [RFC 9111] for a private cache:
+//!
+//! ```no_run
+//! use wstd::http::{Cache, Client};
+//!
+//! let cache = Cache::new(4 * 1024 * 1024);
+//! let mut client = Client::new();
+//! client.set_cache(cache.clone());
+//! ```
+//!
+//! A response is fresh for its `Cache-Control: max-age`, until its `Expires`
+//! date, or otherwise for a tenth of the time since its `Last-Modified` date,
+//! up to a day. A stale response with an `ETag` or `Last-Modified` header is
+//! revalidated with a conditional request, and served again if the server
+//! responds with `304 Not Modified`. Responses with a `Vary` header are only
+//! served for requests with the same values of the headers it lists.
+//!
+//! The `no-cache`, `no-store`, `max-age`, `max-stale`, `min-fresh` and
+//! `only-if-cached` directives of a request's `Cache-Control` header are
+//! honoured, as are the `no-cache`, `no-store`, `must-revalidate` and
+//! `max-age` directives of a response. Requests with their own conditional
+//! or `Range` headers bypass the cache, and a successful request with an
+//! unsafe method, such as `POST`, removes the responses stored for its URL.
+//!
+//! A response which is stored is read into memory before [`Client::send`]
+//! returns it. Responses which don't fit in the cache aren't stored, and the
+//! least recently used responses are removed to make room for new ones.
+//!
+//! [`Client`]: super::Client
+//! [`Client::send`]: super::Client::send
+//! [RFC 9111]: https://www.rfc-editor.org/rfc/rfc9111
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use http::header::{
+    AGE, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, DATE, ETAG,
+    EXPIRES, IF_MATCH, IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_RANGE, IF_UNMODIFIED_SINCE,
+    LAST_MODIFIED, RANGE, TRANSFER_ENCODING, VARY,
+};
+
+use super::response::{IncomingBody, Parts};
+use super::{
+    date, Body, Client, Error, HeaderMap, HeaderName, HeaderValue, Method, Request, Response,
+    Result, StatusCode, Uri,
+};
+use crate::io::AsyncRead;
+use crate::time::SystemTime;
+
+/// The longest a response is considered fresh for based on its
+/// `Last-Modified` date, in seconds.
+const MAX_HEURISTIC_FRESHNESS: u64 = 24 * 60 * 60;
+
+/// An in-memory cache of responses.
+///
+/// Clones of a `Cache` share the same stored responses.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    inner: Rc<RefCell<Inner>>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    max_size: usize,
+    size: usize,
+    // The stored responses for each URL, one for each variant.
+    entries: HashMap<String, Vec<Entry>>,
+    // Incremented every time a response is used, to find the least recently
+    // used one.
+    clock: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Vec<u8>,
+    trailers: Option<HeaderMap>,
+    // The values of the request headers listed by `Vary`.
+    vary: Vec<(HeaderName, Vec<HeaderValue>)>,
+    // The age of the response when it was received, in seconds.
+    initial_age: u64,
+    // How long the response is fresh for, in seconds.
+    freshness: u64,
+    response_time: SystemTime,
+    last_used: u64,
+}
+
+/// The result of looking up a request in the cache.
+enum Lookup {
+    /// A response which can be served as it is.
+    Fresh(Entry),
+    /// A response which must be revalidated before it is served.
+    Stale(Entry),
+    Miss,
+}
+
+impl Cache {
+    /// The default maximum size of the cache: 16 MiB.
+    pub const DEFAULT_MAX_SIZE: usize = 16 * 1024 * 1024;
+
+    /// Create an empty cache which stores at most `max_size` bytes of
+    /// responses, counting their headers and bodies.
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            inner: Rc::new(RefCell::new(Inner {
+                max_size,
+                size: 0,
+                entries: HashMap::new(),
+                clock: 0,
+            })),
+        }
+    }
+
+    /// Get the maximum size of the cache, in bytes.
+    pub fn max_size(&self) -> usize {
+        self.inner.borrow().max_size
+    }
+
+    /// Get how many bytes of responses are stored.
+    pub fn size(&self) -> usize {
+        self.inner.borrow().size
+    }
+
+    /// Get how many responses are stored.
+    pub fn len(&self) -> usize {
+        self.inner.borrow().entries.values().map(Vec::len).sum()
+    }
+
+    /// Returns `true` if no responses are stored.
+    pub fn is_empty(&self) -> bool {
+        self.inner.borrow().entries.is_empty()
+    }
+
+    /// Remove the responses stored for `url`.
+    pub fn remove(&self, url: &Uri) {
+        let mut inner = self.inner.borrow_mut();
+        if let Some(entries) = inner.entries.remove(&url.to_string()) {
+            inner.size -= entries.iter().map(Entry::size).sum::<usize>();
+        }
+    }
+
+    /// Remove all stored responses.
+    pub fn clear(&self) {
+        let mut inner = self.inner.borrow_mut();
+        inner.entries.clear();
+        inner.size = 0;
+    }
+
+    /// Send a request with `client`, unless it can be answered from the
+    /// cache, and store the response if possible.
+    pub(crate) async fn send<B: Body>(
+        &self,
+        client: &Client,
+        mut request: Request<B>,
+    ) -> Result<Response<IncomingBody>> {
+        let url = request.uri().clone();
+        let method = request.method().clone();
+        if method != Method::GET {
+            let response = client.send_uncached(request).await?;
+            let status = u16::from(response.status_code());
+            if !is_safe(&method) && (200..400).contains(&status) {
+                self.remove(&url);
+            }
+            return Ok(response);
+        }
+        let conditional = [
+            IF_MATCH,
+            IF_NONE_MATCH,
+            IF_MODIFIED_SINCE,
+            IF_UNMODIFIED_SINCE,
+            IF_RANGE,
+            RANGE,
+        ];
+        if conditional
+            .iter()
+            .any(|name| request.headers().contains_key(name))
+        {
+            return client.send_uncached(request).await;
+        }
+
+        let request_cc = CacheControl::from_headers(request.headers());
+        let request_headers = request.headers().clone();
+        let stale = match self.lookup(&url, &request_headers, &request_cc) {
+            Lookup::Fresh(entry) => return Ok(entry.into_response(&url)),
+            _ if request_cc.only_if_cached => {
+                let mut response = Response::from_parts(
+                    Parts::new(StatusCode::GatewayTimeout),
+                    IncomingBody::from_bytes(Vec::new(), None, None),
+                );
+                response.set_url(url);
+                return Ok(response);
+            }
+            Lookup::Stale(entry) => {
+                let headers = request.headers_mut();
+                if let Some(etag) = entry.headers.get(ETAG) {
+                    headers.insert(IF_NONE_MATCH, etag.clone());
+                }
+                if let Some(last_modified) = entry.headers.get(LAST_MODIFIED) {
+                    headers.insert(IF_MODIFIED_SINCE, last_modified.clone());
+                }
+                Some(entry)
+            }
+            Lookup::Miss => None,
+        };
+
+        let request_time = SystemTime::now();
+        let response = client.send_uncached(request).await?;
+        let response_time = SystemTime::now();
+        match stale {
+            Some(entry) if response.status_code() == StatusCode::NotModified => {
+                let entry = entry.refresh(response.headers(), request_time, response_time);
+                self.insert(&url, entry.clone());
+                Ok(entry.into_response(&url))
+            }
+            _ => {
+                self.store(
+                    &url,
+                    request_headers,
+                    &request_cc,
+                    response,
+                    request_time,
+                    response_time,
+                )
+                .await
+            }
+        }
+    }
+
+    /// Find the stored response for a request.
+    fn lookup(&self, url: &Uri, request_headers: &HeaderMap, request_cc: &CacheControl) -> Lookup {
+        let mut inner = self.inner.borrow_mut();
+        inner.clock += 1;
+        let clock = inner.clock;
+        let Some(entry) = inner
+            .entries
+            .get_mut(&url.to_string())
+            .and_then(|entries| entries.iter_mut().find(|e| e.matches(request_headers)))
+        else {
+            return Lookup::Miss;
+        };
+        entry.last_used = clock;
+
+        let response_cc = CacheControl::from_headers(&entry.headers);
+        let age = entry.current_age(SystemTime::now());
+        let mut usable = !request_cc.no_cache && !response_cc.no_cache;
+        if let Some(max_age) = request_cc.max_age {
+            usable &= age <= max_age;
+        }
+        let freshness = entry.freshness;
+        let min_fresh = request_cc.min_fresh.unwrap_or(0);
+        let max_stale = match request_cc.max_stale {
+            Some(max_stale) if !response_cc.must_revalidate => max_stale,
+            _ => 0,
+        };
+        usable &= age.saturating_add(min_fresh) < freshness.saturating_add(max_stale);
+
+        if usable {
+            Lookup::Fresh(entry.clone())
+        } else if entry.headers.contains_key(ETAG) || entry.headers.contains_key(LAST_MODIFIED) {
+            Lookup::Stale(entry.clone())
+        } else {
+            Lookup::Miss
+        }
+    }
+
+    /// Store `response` if it can be, and return it.
+    async fn store(
+        &self,
+        url: &Uri,
+        request_headers: HeaderMap,
+        request_cc: &CacheControl,
+        mut response: Response<IncomingBody>,
+        request_time: SystemTime,
+        response_time: SystemTime,
+    ) -> Result<Response<IncomingBody>> {
+        let response_cc = CacheControl::from_headers(response.headers());
+        let status = response.status_code();
+        let explicit = response_cc.max_age.is_some() || response.headers().contains_key(EXPIRES);
+        let has_validator =
+            response.headers().contains_key(ETAG) || response.headers().contains_key(LAST_MODIFIED);
+        let mut vary = Vec::new();
+        for value in response.headers().get_all(VARY) {
+            let Ok(value) = value.to_str() else {
+                return Ok(response);
+            };
+            for name in value.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+                // `*` is a valid header name, but matches no other request.
+                if name == "*" {
+                    return Ok(response);
+                }
+                let Ok(name) = HeaderName::try_from(name) else {
+                    return Ok(response);
+                };
+                let values = request_headers.get_all(&name).iter().cloned().collect();
+                vary.push((name, values));
+            }
+        }
+        let mut entry = Entry {
+            status,
+            headers: response.headers().clone(),
+            body: Vec::new(),
+            trailers: None,
+            vary,
+            initial_age: 0,
+            freshness: 0,
+            response_time,
+            last_used: 0,
+        };
+        entry.update_age(request_time, response_time);
+
+        let storable = !request_cc.no_store
+            && !response_cc.no_store
+            && (explicit || is_heuristically_cacheable(status))
+            && (entry.freshness > 0 || has_validator)
+            // Responses to redirected requests belong to another URL.
+            && response.url() == Some(url);
+        let max_size = self.max_size();
+        if !storable || response.body().len().is_some_and(|len| len > max_size) {
+            return Ok(response);
+        }
+
+        // Read the body, and return what was read if it turns out to be too
+        // large to store.
+        let body = response.body();
+        let mut bytes = Vec::new();
+        let mut buf = [0; 4096];
+        loop {
+            let n = body
+                .read(&mut buf)
+                .await
+                .map_err(|e| Error::from_io(e).context("reading body"))?;
+            if n == 0 {
+                break;
+            }
+            bytes.extend_from_slice(&buf[..n]);
+            if entry.size() + bytes.len() > max_size {
+                body.unread(bytes);
+                return Ok(response);
+            }
+        }
+        entry.trailers = body.trailers().await?;
+        entry.body = bytes;
+        self.insert(url, entry.clone());
+        Ok(entry.into_response(url))
+    }
+
+    /// Store `entry`, replacing a stored response for the same variant, and
+    /// removing the least recently used responses to make room for it.
+    fn insert(&self, url: &Uri, mut entry: Entry) {
+        let mut inner = self.inner.borrow_mut();
+        inner.clock += 1;
+        entry.last_used = inner.clock;
+        let entries = inner.entries.entry(url.to_string()).or_default();
+        let mut removed = 0;
+        entries.retain(|e| {
+            let same = e.vary == entry.vary;
+            if same {
+                removed += e.size();
+            }
+            !same
+        });
+        let size = entry.size();
+        entries.push(entry);
+        inner.size = inner.size - removed + size;
+
+        while inner.size > inner.max_size {
+            let Some((key, index)) = inner.least_recently_used() else {
+                break;
+            };
+            let entries = inner.entries.get_mut(&key).expect("key was just found");
+            let removed = entries.remove(index);
+            if entries.is_empty() {
+                inner.entries.remove(&key);
+            }
+            inner.size -= removed.size();
+        }
+    }
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_MAX_SIZE)
+    }
+}
+
+impl Inner {
+    /// Find the URL and index of the least recently used response.
+    fn least_recently_used(&self) -> Option<(String, usize)> {
+        self.entries
+            .iter()
+            .flat_map(|(key, entries)| {
+                entries
+                    .iter()
+                    .enumerate()
+                    .map(move |(index, e)| (e.last_used, key, index))
+            })
+            .min_by_key(|(last_used, _, _)| *last_used)
+            .map(|(_, key, index)| (key.clone(), index))
+    }
+}
+
+impl Entry {
+    /// The number of bytes the response is counted as in the cache.
+    fn size(&self) -> usize {
+        let headers = |map: &HeaderMap| {
+            map.iter()
+                .map(|(name, value)| name.as_str().len() + value.len())
+                .sum::<usize>()
+        };
+        self.body.len() + headers(&self.headers) + self.trailers.as_ref().map_or(0, headers)
+    }
+
+    /// Whether a request has the same values of the headers listed by `Vary`
+    /// as the request this response was stored for.
+    fn matches(&self, request_headers: &HeaderMap) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, values)| request_headers.get_all(name).iter().eq(values.iter()))
+    }
+
+    /// Calculate the age and freshness of the response, following RFC 9111
+    /// section 4.2.
+    fn update_age(&mut self, request_time: SystemTime, response_time: SystemTime) {
+        let date = self
+            .headers
+            .get(DATE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(date::parse);
+        let apparent_age = date.map_or(0, |date| secs_between(response_time, date));
+        let age_value = self
+            .headers
+            .get(AGE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .unwrap_or(0);
+        let response_delay = secs_between(response_time, request_time);
+        self.initial_age = apparent_age.max(age_value.saturating_add(response_delay));
+        self.response_time = response_time;
+
+        let cc = CacheControl::from_headers(&self.headers);
+        let date = date.unwrap_or(response_time);
+        let header_date = |name| {
+            self.headers
+                .get(name)
+                .and_then(|v: &HeaderValue| v.to_str().ok())
+                .and_then(date::parse)
+        };
+        self.freshness = if let Some(max_age) = cc.max_age {
+            max_age
+        } else if self.headers.contains_key(EXPIRES) {
+            // An invalid date means that the response has already expired.
+            header_date(EXPIRES).map_or(0, |expires| secs_between(expires, date))
+        } else if let Some(last_modified) = header_date(LAST_MODIFIED) {
+            (secs_between(date, last_modified) / 10).min(MAX_HEURISTIC_FRESHNESS)
+        } else {
+            0
+        };
+    }
+
+    /// The age of the response at `now`, in seconds.
+    fn current_age(&self, now: SystemTime) -> u64 {
+        self.initial_age
+            .saturating_add(secs_between(now, self.response_time))
+    }
+
+    /// Update the response with the headers of a `304 Not Modified`
+    /// response which revalidated it.
+    fn refresh(
+        mut self,
+        headers: &HeaderMap,
+        request_time: SystemTime,
+        response_time: SystemTime,
+    ) -> Self {
+        let unchanged = [
+            CONTENT_LENGTH,
+            CONTENT_ENCODING,
+            CONTENT_RANGE,
+            TRANSFER_ENCODING,
+        ];
+        for name in headers.keys() {
+            if unchanged.contains(name) {
+                continue;
+            }
+            self.headers.remove(name);
+            for value in headers.get_all(name) {
+                self.headers.append(name.clone(), value.clone());
+            }
+        }
+        self.update_age(request_time, response_time);
+        self
+    }
+
+    /// Create a response from the stored one.
+    fn into_response(self, url: &Uri) -> Response<IncomingBody> {
+        let age = self.current_age(SystemTime::now());
+        let mut parts = Parts::new(self.status);
+        parts.headers = self.headers;
+        parts.headers.insert(AGE, HeaderValue::from(age));
+        let content_type = parts.headers.get(CONTENT_TYPE).cloned();
+        let body = IncomingBody::from_bytes(self.body, content_type, self.trailers);
+        let mut response = Response::from_parts(parts, body);
+        response.set_url(url.clone());
+        response
+    }
+}
+
+/// The directives of a `Cache-Control` header which are understood.
+#[derive(Debug, Default)]
+struct CacheControl {
+    no_cache: bool,
+    no_store: bool,
+    must_revalidate: bool,
+    only_if_cached: bool,
+    max_age: Option<u64>,
+    // `max-stale` without a value accepts a response however stale it is.
+    max_stale: Option<u64>,
+    min_fresh: Option<u64>,
+}
+
+impl CacheControl {
+    fn from_headers(headers: &HeaderMap) -> Self {
+        let mut cc = Self::default();
+        for value in headers.get_all(CACHE_CONTROL) {
+            let Ok(value) = value.to_str() else {
+                continue;
+            };
+            for directive in value.split(',') {
+                let (name, argument) = match directive.split_once('=') {
+                    Some((name, argument)) => (name, Some(argument.trim().trim_matches('"'))),
+                    None => (directive, None),
+                };
+                let seconds = || argument.and_then(|a| a.parse::<u64>().ok());
+                match name.trim().to_ascii_lowercase().as_str() {
+                    "no-cache" => cc.no_cache = true,
+                    "no-store" => cc.no_store = true,
+                    "must-revalidate" => cc.must_revalidate = true,
+                    "only-if-cached" => cc.only_if_cached = true,
+                    // An invalid `max-age` means that the response is stale.
+                    "max-age" => cc.max_age = cc.max_age.or(Some(seconds().unwrap_or(0))),
+                    "max-stale" => cc.max_stale = Some(seconds().unwrap_or(u64::MAX)),
+                    "min-fresh" => cc.min_fresh = seconds(),
+                    _ => {}
+                }
+            }
+        }
+        cc
+    }
+}
+
+/// Whether a method is safe, which means that it doesn't change anything
+/// on the server.
+fn is_safe(method: &Method) -> bool {
+    [Method::GET, Method::HEAD, Method::OPTIONS, Method::TRACE].contains(method)
+}
+
+/// Whether a response with `status` may be stored without explicit
+/// freshness information, from RFC 9110 section 15.1.
+fn is_heuristically_cacheable(status: StatusCode) -> bool {
+    matches!(
+        u16::from(status),
+        200 | 203 | 204 | 300 | 301 | 308 | 404 | 405 | 410 | 414 | 501
+    )
+}
+
+/// The number of whole seconds from `earlier` to `later`, or 0 if `earlier`
+/// is later.
+fn secs_between(later: SystemTime, earlier: SystemTime) -> u64 {
+    later
+        .checked_duration_since(earlier)
+        .map_or(0, |d| std::time::Duration::from(d).as_secs())
+}
@@ -0,0 +1,51 @@
+//! Decoding text bodies according to the `charset` of their `Content-Type`.
+
+use super::{Error, HeaderValue, Result};
+
+/// Get the `charset` parameter of a `Content-Type` header value, if any.
+pub(crate) fn from_content_type(content_type: &HeaderValue) -> Option<&str> {
+    let content_type = content_type.to_str().ok()?;
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches('"'))
+    })
+}
+
+/// Decode `bytes` as text in `charset`, which defaults to UTF-8.
+///
+/// Invalid sequences are replaced with `U+FFFD`, as a browser would.
+pub(crate) fn decode(bytes: Vec<u8>, charset: Option<&str>) -> Result<String> {
+    let charset = charset.unwrap_or("utf-8").to_ascii_lowercase();
+    match charset.as_str() {
+        "utf-8" | "utf8" | "us-ascii" | "ascii" => match String::from_utf8(bytes) {
+            Ok(text) => Ok(text),
+            Err(err) => Ok(String::from_utf8_lossy(err.as_bytes()).into_owned()),
+        },
+        "iso-8859-1" | "iso8859-1" | "latin1" | "l1" => {
+            Ok(bytes.into_iter().map(char::from).collect())
+        }
+        "utf-16le" => Ok(decode_utf16(&bytes, u16::from_le_bytes)),
+        "utf-16be" => Ok(decode_utf16(&bytes, u16::from_be_bytes)),
+        "utf-16" => match bytes.as_slice() {
+            [0xff, 0xfe, rest @ ..] => Ok(decode_utf16(rest, u16::from_le_bytes)),
+            [0xfe, 0xff, rest @ ..] => Ok(decode_utf16(rest, u16::from_be_bytes)),
+            _ => Ok(decode_utf16(&bytes, u16::from_be_bytes)),
+        },
+        _ => Err(Error::other(format!("unsupported charset: {charset}"))),
+    }
+}
+
+fn decode_utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16) -> String {
+    let chunks = bytes.chunks_exact(2);
+    let trailing = !chunks.remainder().is_empty();
+    let units = chunks.map(|pair| unit([pair[0], pair[1]]));
+    let mut text: String = char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect();
+    if trailing {
+        text.push(char::REPLACEMENT_CHARACTER);
+    }
+    text
+}
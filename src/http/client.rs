@@ -1,115 +1,411 @@
-use super::{response::IncomingBody, Body, Error, Request, Response, Result};
-use crate::io::{self, AsyncWrite};
+use super::{
+    body::{BoxBody, OutgoingBody, Replay},
+    cache::Cache,
+    cookie::CookieJar,
+    decoder,
+    error::ErrorVariant,
+    redirect::{self, RedirectPolicy},
+    request::Parts,
+    response::IncomingBody,
+    retry::RetryPolicy,
+    transport::{self, Transport, WasiTransport},
+    Body, Error, Method, Request, Response, Result, Service,
+};
+use crate::future::FutureExt;
+use crate::io::Empty;
 use crate::runtime::Reactor;
-use crate::time::Duration;
-use wasi::http::types::{OutgoingBody, RequestOptions as WasiRequestOptions};
+use crate::time::{Duration, Instant};
+use http::header::{
+    ACCEPT_ENCODING, AUTHORIZATION, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, COOKIE,
+    PROXY_AUTHORIZATION, TRANSFER_ENCODING,
+};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use wasi::http::types::{FutureIncomingResponse, RequestOptions as WasiRequestOptions};
+
+/// The largest request body which is kept around to send it again when
+/// following a redirect or retrying a request.
+const REPLAY_LIMIT: usize = 64 * 1024;
 
 /// An HTTP client.
-// Empty for now, but permits adding support for RequestOptions soon:
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Client {
-    options: Option<RequestOptions>,
+    options: RequestOptions,
+    redirect_policy: RedirectPolicy,
+    retry_policy: RetryPolicy,
+    cookie_jar: Option<CookieJar>,
+    cache: Option<Cache>,
+    transport: Option<BoxTransport>,
 }
 
 impl Client {
     /// Create a new instance of `Client`
     pub fn new() -> Self {
-        Self { options: None }
+        Self {
+            options: RequestOptions::default(),
+            redirect_policy: RedirectPolicy::none(),
+            retry_policy: RetryPolicy::none(),
+            cookie_jar: None,
+            cache: None,
+            transport: None,
+        }
     }
 
     /// Send an HTTP request.
+    ///
+    /// The request body is sent while waiting for the response. If the
+    /// server responds with an error status before the whole body was sent,
+    /// the rest of the body is not sent, and the response is returned right
+    /// away.
+    ///
+    /// Failed requests are retried according to the client's [`RetryPolicy`],
+    /// and redirects are followed according to its [`RedirectPolicy`]. If
+    /// the client has a [`CookieJar`], cookies are sent and stored along the
+    /// way.
+    /// The URL of the final request is available from [`Response::url`].
+    ///
+    /// With the `gzip`, `deflate` or `brotli` features, compressed responses
+    /// are decoded as they are read; see [`Request::set_decompress`].
+    ///
+    /// If the client has a [`Cache`], requests are answered from it when
+    /// possible, and responses are stored in it.
+    ///
+    /// If the request or the client has a total timeout, see
+    /// [`Client::set_timeout`], sending the request and reading the response
+    /// body fail with [`ErrorVariant::Timeout`] once it expires.
     pub async fn send<B: Body>(&self, req: Request<B>) -> Result<Response<IncomingBody>> {
-        let (wasi_req, body) = req.into_outgoing()?;
-        let wasi_body = wasi_req.body().unwrap();
-        let body_stream = wasi_body.write().unwrap();
+        let Some(timeout) = self.options_for(&req).timeout else {
+            return self.send_cached(req).await;
+        };
+        let deadline = Instant::now() + timeout;
+        let mut response = self
+            .send_cached(req)
+            .timeout(deadline)
+            .await
+            .map_err(|_| Error::from(ErrorVariant::Timeout))??;
+        response.body().set_deadline(deadline);
+        Ok(response)
+    }
 
-        // 1. Start sending the request head
-        let res = wasi::http::outgoing_handler::handle(wasi_req, self.wasi_options()?).unwrap();
+    /// Send a request, answering it from the cache if possible.
+    async fn send_cached<B: Body>(&self, req: Request<B>) -> Result<Response<IncomingBody>> {
+        match &self.cache {
+            Some(cache) => cache.send(self, req).await,
+            None => self.send_uncached(req).await,
+        }
+    }
 
-        // 2. Start sending the request body
-        io::copy(body, OutputStream::new(body_stream))
-            .await
-            .expect("io::copy broke oh no");
+    /// Send a request, without looking it up in the cache.
+    pub(crate) async fn send_uncached<B: Body>(
+        &self,
+        req: Request<B>,
+    ) -> Result<Response<IncomingBody>> {
+        if self.redirect_policy.is_none()
+            && self.retry_policy.is_none()
+            && self.cookie_jar.is_none()
+        {
+            let url = req.uri().clone();
+            let mut response = self.send_once(req).await?;
+            response.set_url(url);
+            return Ok(response);
+        }
+
+        let (mut parts, body) = req.into_parts();
+        let mut body = Replay::new(body, REPLAY_LIMIT);
+        let mut previous = Vec::new();
+        loop {
+            let mut response = self.send_retrying(&parts, &mut body).await?;
+            response.set_url(parts.uri.clone());
+
+            previous.push(parts.uri.clone());
+            let redirect = redirect::check(
+                &self.redirect_policy,
+                &parts.method,
+                &parts.uri,
+                response.status_code(),
+                response.headers(),
+                &previous,
+            )?;
+            let Some(redirect) = redirect else {
+                return Ok(response);
+            };
+
+            if redirect.keep_body {
+                if !body.rewind().await {
+                    // The body can't be sent again, so leave it to the caller.
+                    return Ok(response);
+                }
+            } else {
+                if parts.method != Method::HEAD {
+                    parts.method = Method::GET;
+                }
+                for name in [
+                    CONTENT_LENGTH,
+                    CONTENT_TYPE,
+                    CONTENT_ENCODING,
+                    TRANSFER_ENCODING,
+                ] {
+                    parts.headers.remove(name);
+                }
+                body.clear();
+            }
+            if !redirect::same_origin(&parts.uri, &redirect.url) {
+                for name in [AUTHORIZATION, PROXY_AUTHORIZATION, COOKIE] {
+                    parts.headers.remove(name);
+                }
+            }
+            parts.uri = redirect.url;
+        }
+    }
 
-        // 3. Finish sending the request body
-        let trailers = None;
-        OutgoingBody::finish(wasi_body, trailers).unwrap();
+    /// Send a request, retrying it according to the client's [`RetryPolicy`].
+    async fn send_retrying<B: Body>(
+        &self,
+        parts: &Parts,
+        body: &mut Replay<B>,
+    ) -> Result<Response<IncomingBody>> {
+        let mut attempt = 1;
+        loop {
+            let mut request = Request::from_parts(parts.clone(), &mut *body);
+            if let Some(jar) = &self.cookie_jar {
+                jar.add_cookie_header(&parts.uri, request.headers_mut());
+            }
+            let result = self.send_once(request).await;
+            if let (Some(jar), Ok(response)) = (&self.cookie_jar, &result) {
+                jar.set_cookies(&parts.uri, response.headers());
+            }
+            let delay = self.retry_policy.delay(attempt, &parts.method, &result);
+            match delay {
+                Some(delay) if body.rewind().await => {
+                    drop(result);
+                    crate::task::sleep(delay).await;
+                    attempt += 1;
+                }
+                _ => return result,
+            }
+        }
+    }
 
-        // 4. Receive the response
-        Reactor::current().wait_for(res.subscribe()).await;
-        // NOTE: the first `unwrap` is to ensure readiness, the second `unwrap`
-        // is to trap if we try and get the response more than once. The final
-        // `?` is to raise the actual error if there is one.
-        let res = res.get().unwrap().unwrap()?;
-        Ok(Response::try_from_incoming_response(res)?)
+    /// Send a single request, without following redirects.
+    async fn send_once<B: Body>(&self, mut req: Request<B>) -> Result<Response<IncomingBody>> {
+        let accept_encoding = decoder::accept_encoding()
+            .filter(|_| req.decompress() && !req.headers().contains_key(ACCEPT_ENCODING));
+        let decompress = accept_encoding.is_some();
+        if let Some(value) = accept_encoding {
+            req.headers_mut().insert(ACCEPT_ENCODING, value);
+        }
+        let mut response = self.send_uncompressed(req).await?;
+        if decompress {
+            response.decompress();
+        }
+        Ok(response)
+    }
+
+    /// Send a single request with the client's [`Transport`], returning the
+    /// response as it was received.
+    async fn send_uncompressed<B: Body>(
+        &self,
+        mut req: Request<B>,
+    ) -> Result<Response<IncomingBody>> {
+        let options = self.options_for(&req);
+        req.extensions_mut().insert(options);
+        let (parts, body) = req.into_parts();
+        let req = Request::from_parts(parts, BoxBody::new(body));
+        match &self.transport {
+            Some(BoxTransport(transport)) => transport.send(req).await,
+            None => WasiTransport.send(req).await,
+        }
+    }
+
+    /// Send the head of a request, returning an [`OutgoingBody`] to write the
+    /// request body to, and a [`ResponseFuture`] for its response.
+    ///
+    /// The response can be awaited while the body is still being written, and
+    /// the body can be written to after the response was received. Once the
+    /// body has been written, it must be completed with
+    /// [`OutgoingBody::finish`], or the request will fail.
+    ///
+    /// The connect, first byte and between bytes timeouts of the request and
+    /// the client apply, but the total timeout doesn't.
+    ///
+    /// This bypasses the [`Transport`] set with [`Client::set_transport`]:
+    /// the request is always sent with wasi-http, since an [`OutgoingBody`]
+    /// writes to a wasi-http body stream. The client's redirect and retry
+    /// policies, cookie jar and cache don't apply either.
+    ///
+    /// ```no_run
+    /// # use wstd::http::{Client, Method, Request};
+    /// # use wstd::io::AsyncWrite;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let request = Request::new(Method::POST, "https://example.com/upload".parse()?);
+    /// let (mut body, response) = Client::new().start_request(request)?;
+    /// body.write_all(b"hello").await?;
+    /// let response = response.await?;
+    /// body.write_all(b", world").await?;
+    /// body.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn start_request(&self, req: Request<Empty>) -> Result<(OutgoingBody, ResponseFuture)> {
+        let options = self.options_for(&req);
+        let (wasi_req, _) = req.into_outgoing()?;
+        transport::start(wasi_req, options)
+    }
+
+    /// Set which redirects are followed by [`Client::send`].
+    ///
+    /// By default, no redirects are followed.
+    pub fn set_redirect_policy(&mut self, policy: RedirectPolicy) {
+        self.redirect_policy = policy;
+    }
+
+    /// Set which requests are retried by [`Client::send`].
+    ///
+    /// By default, no requests are retried.
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.retry_policy = policy;
+    }
+
+    /// Set the [`CookieJar`] which stores the cookies set by responses, and
+    /// adds them to requests sent by [`Client::send`].
+    ///
+    /// By default, cookies aren't stored.
+    pub fn set_cookie_jar(&mut self, jar: CookieJar) {
+        self.cookie_jar = Some(jar);
+    }
+
+    /// Set the [`Cache`] which stores responses, and answers requests sent
+    /// by [`Client::send`] while they are fresh.
+    ///
+    /// By default, responses aren't cached.
+    pub fn set_cache(&mut self, cache: Cache) {
+        self.cache = Some(cache);
+    }
+
+    /// Set the [`Transport`] which sends requests for [`Client::send`].
+    ///
+    /// By default, requests are sent with [`WasiTransport`].
+    pub fn set_transport(&mut self, transport: impl Transport + 'static) {
+        self.transport = Some(BoxTransport(Box::new(transport)));
+    }
+
+    /// Set a total timeout for requests sent by [`Client::send`], which
+    /// covers following redirects, retrying, and reading the response body.
+    ///
+    /// Requests can override this with [`Request::set_timeout`].
+    pub fn set_timeout(&mut self, d: impl Into<Duration>) {
+        self.options.timeout = Some(d.into());
     }
 
     /// Set timeout on connecting to HTTP server
+    ///
+    /// Requests can override this with [`Request::set_connect_timeout`].
     pub fn set_connect_timeout(&mut self, d: impl Into<Duration>) {
-        self.options_mut().connect_timeout = Some(d.into());
+        self.options.connect_timeout = Some(d.into());
     }
 
     /// Set timeout on recieving first byte of the Response body
+    ///
+    /// Requests can override this with [`Request::set_first_byte_timeout`].
     pub fn set_first_byte_timeout(&mut self, d: impl Into<Duration>) {
-        self.options_mut().first_byte_timeout = Some(d.into());
+        self.options.first_byte_timeout = Some(d.into());
     }
 
     /// Set timeout on recieving subsequent chunks of bytes in the Response body stream
+    ///
+    /// Requests can override this with [`Request::set_between_bytes_timeout`].
     pub fn set_between_bytes_timeout(&mut self, d: impl Into<Duration>) {
-        self.options_mut().between_bytes_timeout = Some(d.into());
+        self.options.between_bytes_timeout = Some(d.into());
     }
 
-    fn options_mut(&mut self) -> &mut RequestOptions {
-        match &mut self.options {
-            Some(o) => o,
-            uninit => {
-                *uninit = Some(Default::default());
-                uninit.as_mut().unwrap()
-            }
+    /// Get the options for `req`, which override the client's options.
+    fn options_for<B: Body>(&self, req: &Request<B>) -> RequestOptions {
+        match req.extensions().get::<RequestOptions>() {
+            Some(options) => options.or(&self.options),
+            None => self.options,
         }
     }
+}
+
+impl Service for Client {
+    async fn call<B: Body>(&self, request: Request<B>) -> Result<Response<IncomingBody>> {
+        self.send(request).await
+    }
+}
+
+/// The [`Transport`] of a [`Client`].
+struct BoxTransport(Box<dyn Transport>);
 
-    fn wasi_options(&self) -> Result<Option<WasiRequestOptions>> {
-        self.options.as_ref().map(|o| o.to_wasi()).transpose()
+impl fmt::Debug for BoxTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Transport").finish_non_exhaustive()
     }
 }
 
-struct OutputStream {
-    stream: wasi::http::types::OutputStream,
+/// The response to a request started with [`Client::start_request`].
+#[must_use = "futures do nothing unless polled or .awaited"]
+pub struct ResponseFuture {
+    inner: Pin<Box<dyn Future<Output = Result<Response<IncomingBody>>>>>,
 }
 
-impl OutputStream {
-    fn new(stream: wasi::http::types::OutputStream) -> Self {
-        Self { stream }
+impl ResponseFuture {
+    pub(crate) fn new(res: FutureIncomingResponse, method: Method) -> Self {
+        let inner = Box::pin(async move {
+            Reactor::current().wait_for(res.subscribe()).await;
+            let res = res
+                .get()
+                .ok_or_else(|| Error::other("response was not ready"))?
+                .map_err(|()| Error::other("response was already taken"))?
+                .map_err(|e| Error::from(e).context("receiving response"))?;
+            Response::try_from_incoming_response(res, &method)
+        });
+        Self { inner }
     }
 }
 
-impl AsyncWrite for OutputStream {
-    async fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        let max = self.stream.check_write().unwrap() as usize;
-        let max = max.min(buf.len());
-        let buf = &buf[0..max];
-        self.stream.write(buf).unwrap();
-        Reactor::current().wait_for(self.stream.subscribe()).await;
-        Ok(max)
+impl Future for ResponseFuture {
+    type Output = Result<Response<IncomingBody>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.inner.as_mut().poll(cx)
     }
+}
 
-    async fn flush(&mut self) -> io::Result<()> {
-        self.stream.flush().unwrap();
-        Reactor::current().wait_for(self.stream.subscribe()).await;
-        Ok(())
+impl fmt::Debug for ResponseFuture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseFuture").finish_non_exhaustive()
     }
 }
 
-#[derive(Default, Debug)]
-struct RequestOptions {
-    connect_timeout: Option<Duration>,
-    first_byte_timeout: Option<Duration>,
-    between_bytes_timeout: Option<Duration>,
+/// Timeouts of a [`Client`], or of a [`Request`], kept in its extensions.
+#[derive(Default, Debug, Clone, Copy)]
+pub(crate) struct RequestOptions {
+    pub(crate) connect_timeout: Option<Duration>,
+    pub(crate) first_byte_timeout: Option<Duration>,
+    pub(crate) between_bytes_timeout: Option<Duration>,
+    pub(crate) timeout: Option<Duration>,
 }
 
 impl RequestOptions {
-    fn to_wasi(&self) -> Result<WasiRequestOptions> {
+    /// Use the timeouts which are set, or else the ones of `other`.
+    fn or(self, other: &RequestOptions) -> RequestOptions {
+        RequestOptions {
+            connect_timeout: self.connect_timeout.or(other.connect_timeout),
+            first_byte_timeout: self.first_byte_timeout.or(other.first_byte_timeout),
+            between_bytes_timeout: self.between_bytes_timeout.or(other.between_bytes_timeout),
+            timeout: self.timeout.or(other.timeout),
+        }
+    }
+
+    pub(crate) fn to_wasi(self) -> Result<Option<WasiRequestOptions>> {
+        if self.connect_timeout.is_none()
+            && self.first_byte_timeout.is_none()
+            && self.between_bytes_timeout.is_none()
+        {
+            return Ok(None);
+        }
         let wasi = WasiRequestOptions::new();
         if let Some(timeout) = self.connect_timeout {
             wasi.set_connect_timeout(Some(*timeout)).map_err(|()| {
@@ -129,6 +425,6 @@ impl RequestOptions {
                     )
                 })?;
         }
-        Ok(wasi)
+        Ok(Some(wasi))
     }
 }
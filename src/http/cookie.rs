@@ -0,0 +1,309 @@
+//! Storing cookies across requests
+//!
+//! A [`CookieJar`] attached to a [`Client`] stores the cookies set by the
+//! `Set-Cookie` headers of responses, and sends them back in the `Cookie`
+//! header of matching requests, following [RFC 6265]:
+//!
+//! ```no_run
+//! use wstd::http::{Client, CookieJar};
+//!
+//! let jar = CookieJar::new();
+//! let mut client = Client::new();
+//! client.set_cookie_jar(jar.clone());
+//! ```
+//!
+//! Cookies are matched by domain and path, and expire according to their
+//! `Expires` or `Max-Age` attributes. `Secure` cookies are only accepted from
+//! and sent to `https` URLs. `HttpOnly` cookies are sent with requests, but
+//! aren't returned by [`CookieJar::get`]. As a client isn't a browser, every
+//! request is considered same-site, so cookies are sent regardless of their
+//! `SameSite` attribute.
+//!
+//! [`Client`]: super::Client
+//! [RFC 6265]: https://www.rfc-editor.org/rfc/rfc6265
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::{date, HeaderMap, HeaderValue, Uri};
+use crate::time::{Duration, SystemTime};
+
+/// An in-memory store of cookies.
+///
+/// Clones of a `CookieJar` share the same cookies.
+#[derive(Debug, Clone, Default)]
+pub struct CookieJar {
+    inner: Rc<RefCell<Inner>>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    cookies: Vec<Cookie>,
+    // Incremented for every cookie, to send older cookies first.
+    next_id: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Cookie {
+    name: String,
+    value: String,
+    domain: String,
+    host_only: bool,
+    path: String,
+    expires: Option<SystemTime>,
+    secure: bool,
+    http_only: bool,
+    created: u64,
+}
+
+impl CookieJar {
+    /// Create a new, empty `CookieJar`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store the cookie set by a `Set-Cookie` header received from `url`.
+    ///
+    /// Cookies which `url` isn't allowed to set are ignored.
+    pub fn set_cookie(&self, url: &Uri, set_cookie: &str) {
+        let Some(host) = url.host().map(|h| h.to_ascii_lowercase()) else {
+            return;
+        };
+        let secure_url = url.scheme_str() == Some("https");
+        let Some(cookie) = parse(set_cookie, &host, url.path(), secure_url) else {
+            return;
+        };
+
+        let mut inner = self.inner.borrow_mut();
+        let existing = inner.cookies.iter().position(|c| {
+            c.name == cookie.name
+                && c.domain == cookie.domain
+                && c.host_only == cookie.host_only
+                && c.path == cookie.path
+        });
+        let created = match existing {
+            Some(index) => inner.cookies.remove(index).created,
+            None => {
+                inner.next_id += 1;
+                inner.next_id
+            }
+        };
+        if cookie
+            .expires
+            .is_some_and(|expires| expires <= SystemTime::now())
+        {
+            // Setting an expired cookie removes it.
+            return;
+        }
+        inner.cookies.push(Cookie { created, ..cookie });
+    }
+
+    /// Store the cookies set by the `Set-Cookie` headers of a response
+    /// received from `url`.
+    pub fn set_cookies(&self, url: &Uri, headers: &HeaderMap) {
+        for value in headers.get_all("set-cookie") {
+            if let Ok(value) = value.to_str() {
+                self.set_cookie(url, value);
+            }
+        }
+    }
+
+    /// Get the `Cookie` header to send with a request to `url`, if any cookies
+    /// match it.
+    pub fn cookie_header(&self, url: &Uri) -> Option<HeaderValue> {
+        let cookies = self.matching(url, true);
+        if cookies.is_empty() {
+            return None;
+        }
+        let header = cookies
+            .iter()
+            .map(|c| format!("{}={}", c.name, c.value))
+            .collect::<Vec<_>>()
+            .join("; ");
+        HeaderValue::from_str(&header).ok()
+    }
+
+    /// Get the value of the cookie called `name` which would be sent to
+    /// `url`, unless it is `HttpOnly`.
+    pub fn get(&self, url: &Uri, name: &str) -> Option<String> {
+        self.matching(url, false)
+            .into_iter()
+            .find(|c| c.name == name)
+            .map(|c| c.value)
+    }
+
+    /// Remove all cookies.
+    pub fn clear(&self) {
+        self.inner.borrow_mut().cookies.clear();
+    }
+
+    /// Add the cookies matching `url` to the `Cookie` header in `headers`.
+    pub(crate) fn add_cookie_header(&self, url: &Uri, headers: &mut HeaderMap) {
+        let Some(cookies) = self.cookie_header(url) else {
+            return;
+        };
+        let value = match headers.get(http::header::COOKIE) {
+            Some(existing) => {
+                let mut value = existing.as_bytes().to_vec();
+                value.extend_from_slice(b"; ");
+                value.extend_from_slice(cookies.as_bytes());
+                match HeaderValue::from_bytes(&value) {
+                    Ok(value) => value,
+                    Err(_) => return,
+                }
+            }
+            None => cookies,
+        };
+        headers.insert(http::header::COOKIE, value);
+    }
+
+    /// The cookies which match `url`, in the order they should be sent.
+    fn matching(&self, url: &Uri, include_http_only: bool) -> Vec<Cookie> {
+        let Some(host) = url.host().map(|h| h.to_ascii_lowercase()) else {
+            return Vec::new();
+        };
+        let secure_url = url.scheme_str() == Some("https");
+        let path = url.path();
+
+        let mut inner = self.inner.borrow_mut();
+        let now = SystemTime::now();
+        inner
+            .cookies
+            .retain(|c| c.expires.map_or(true, |expires| expires > now));
+
+        let mut cookies: Vec<Cookie> = inner
+            .cookies
+            .iter()
+            .filter(|c| {
+                (c.host_only && c.domain == host || !c.host_only && domain_match(&host, &c.domain))
+                    && path_match(path, &c.path)
+                    && (secure_url || !c.secure)
+                    && (include_http_only || !c.http_only)
+            })
+            .cloned()
+            .collect();
+        // Cookies with longer paths are sent first, then older cookies.
+        cookies.sort_by(|a, b| {
+            b.path
+                .len()
+                .cmp(&a.path.len())
+                .then(a.created.cmp(&b.created))
+        });
+        cookies
+    }
+}
+
+/// Parse a `Set-Cookie` header received from `host` and `path`, returning
+/// `None` if the cookie should be ignored.
+fn parse(set_cookie: &str, host: &str, path: &str, secure_url: bool) -> Option<Cookie> {
+    let mut attributes = set_cookie.split(';');
+    let (name, value) = attributes.next()?.split_once('=')?;
+    let (name, value) = (name.trim(), value.trim());
+    if name.is_empty() {
+        return None;
+    }
+
+    let mut cookie = Cookie {
+        name: name.to_owned(),
+        value: value.to_owned(),
+        domain: host.to_owned(),
+        host_only: true,
+        path: default_path(path),
+        expires: None,
+        secure: false,
+        http_only: false,
+        created: 0,
+    };
+    let mut max_age = None;
+    let mut expires = None;
+    let mut same_site_none = false;
+    for attribute in attributes {
+        let (name, value) = match attribute.split_once('=') {
+            Some((name, value)) => (name.trim(), value.trim()),
+            None => (attribute.trim(), ""),
+        };
+        match name.to_ascii_lowercase().as_str() {
+            "expires" => expires = date::parse(value),
+            "max-age" => {
+                if let Ok(secs) = value.parse::<i64>() {
+                    max_age = Some(secs);
+                }
+            }
+            "domain" => {
+                let domain = value.trim_start_matches('.').to_ascii_lowercase();
+                if !domain.is_empty() {
+                    cookie.domain = domain;
+                    cookie.host_only = false;
+                }
+            }
+            "path" if value.starts_with('/') => cookie.path = value.to_owned(),
+            "path" => cookie.path = default_path(path),
+            "secure" => cookie.secure = true,
+            "httponly" => cookie.http_only = true,
+            "samesite" => same_site_none = value.eq_ignore_ascii_case("none"),
+            _ => {}
+        }
+    }
+
+    // Max-Age takes precedence over Expires.
+    cookie.expires = match max_age {
+        Some(secs) if secs <= 0 => Some(SystemTime::UNIX_EPOCH),
+        Some(secs) => SystemTime::now().checked_add(Duration::from_secs(secs as u64)),
+        None => expires,
+    };
+
+    if !cookie.host_only {
+        // A host may only set cookies for itself and its parent domains, and
+        // never for a bare top-level domain.
+        if !domain_match(host, &cookie.domain) {
+            return None;
+        }
+        if !cookie.domain.contains('.') && cookie.domain != host {
+            return None;
+        }
+    }
+    if cookie.secure && !secure_url {
+        return None;
+    }
+    if same_site_none && !cookie.secure {
+        return None;
+    }
+    if cookie.name.starts_with("__Secure-") && !cookie.secure {
+        return None;
+    }
+    if cookie.name.starts_with("__Host-")
+        && !(cookie.secure && cookie.host_only && cookie.path == "/")
+    {
+        return None;
+    }
+    Some(cookie)
+}
+
+/// The default path of a cookie set by a response to `path`.
+fn default_path(path: &str) -> String {
+    match path.rfind('/') {
+        Some(0) | None => "/".to_owned(),
+        Some(i) => path[..i].to_owned(),
+    }
+}
+
+/// Whether `host` is `domain` or a subdomain of it.
+fn domain_match(host: &str, domain: &str) -> bool {
+    if host == domain {
+        return true;
+    }
+    let is_ip = host.parse::<std::net::IpAddr>().is_ok() || host.starts_with('[');
+    !is_ip
+        && host
+            .strip_suffix(domain)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+/// Whether a request for `path` should carry a cookie with `cookie_path`.
+fn path_match(path: &str, cookie_path: &str) -> bool {
+    let path = if path.is_empty() { "/" } else { path };
+    path == cookie_path
+        || path
+            .strip_prefix(cookie_path)
+            .is_some_and(|rest| cookie_path.ends_with('/') || rest.starts_with('/'))
+}
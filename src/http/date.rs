@@ -0,0 +1,152 @@
+//! Parsing dates in HTTP headers.
+
+use crate::time::{Duration, SystemTime};
+
+/// Parse a date such as `Sun, 06 Nov 1994 08:49:37 GMT`.
+///
+/// This follows the lenient algorithm for cookie dates in RFC 6265, so it
+/// also accepts the obsolete formats allowed for HTTP dates, such as
+/// `Sunday, 06-Nov-94 08:49:37 GMT`.
+pub(crate) fn parse(date: &str) -> Option<SystemTime> {
+    let mut time = None;
+    let mut day = None;
+    let mut month = None;
+    let mut year = None;
+
+    let is_delimiter = |c: char| matches!(c, '\t' | ' '..='/' | ';'..='@' | '['..='`' | '{'..='~');
+    for token in date.split(is_delimiter).filter(|t| !t.is_empty()) {
+        if time.is_none() {
+            if let Some(t) = parse_time(token) {
+                time = Some(t);
+                continue;
+            }
+        }
+        if day.is_none() {
+            if let Some(d) = leading_digits(token, 1, 2) {
+                day = Some(d);
+                continue;
+            }
+        }
+        if month.is_none() {
+            if let Some(m) = parse_month(token) {
+                month = Some(m);
+                continue;
+            }
+        }
+        if year.is_none() {
+            if let Some(y) = leading_digits(token, 2, 4) {
+                year = Some(y);
+                continue;
+            }
+        }
+    }
+
+    let (hour, minute, second) = time?;
+    let (day, month, mut year) = (day?, month?, year?);
+    match year {
+        70..=99 => year += 1900,
+        0..=69 => year += 2000,
+        _ => {}
+    }
+    if !(1..=days_in_month(year, month)).contains(&day)
+        || year < 1970
+        || hour > 23
+        || minute > 59
+        || second > 59
+    {
+        return None;
+    }
+
+    // Days since the epoch, from the civil calendar.
+    let (y, m) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era_days = (y / 400) * 146_097;
+    let yoe = y % 400;
+    let doy = (153 * m + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = (era_days + doe).checked_sub(719_468)?;
+
+    let secs = days * 86_400 + hour * 3_600 + minute * 60 + second;
+    SystemTime::UNIX_EPOCH.checked_add(Duration::from_secs(secs))
+}
+
+/// Format a date as an IMF-fixdate, such as `Sun, 06 Nov 1994 08:49:37 GMT`.
+pub(crate) fn format(date: SystemTime) -> String {
+    const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    let secs = date
+        .checked_duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| std::time::Duration::from(d).as_secs());
+    let (days, secs) = (secs / 86_400, secs % 86_400);
+    // The epoch was a Thursday.
+    let weekday = WEEKDAYS[((days + 4) % 7) as usize];
+
+    // The civil calendar date, from days since the epoch.
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = era * 400 + yoe + u64::from(month <= 2);
+
+    format!(
+        "{weekday}, {day:02} {} {year:04} {:02}:{:02}:{:02} GMT",
+        MONTHS[(month - 1) as usize],
+        secs / 3_600,
+        secs / 60 % 60,
+        secs % 60,
+    )
+}
+
+/// Parse `hh:mm:ss`, where each part is one or two digits.
+fn parse_time(token: &str) -> Option<(u64, u64, u64)> {
+    let mut parts = token.splitn(3, ':');
+    let hour = exact_digits(parts.next()?, 1, 2)?;
+    let minute = exact_digits(parts.next()?, 1, 2)?;
+    let second = leading_digits(parts.next()?, 1, 2)?;
+    Some((hour, minute, second))
+}
+
+/// The number of days in `month` of `year`.
+fn days_in_month(year: u64, month: u64) -> u64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Parse the first three letters of a month name.
+fn parse_month(token: &str) -> Option<u64> {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    let prefix = token.get(..3)?.to_ascii_lowercase();
+    let index = MONTHS.iter().position(|m| *m == prefix)?;
+    Some(index as u64 + 1)
+}
+
+/// Parse `min..=max` digits at the start of `token`, which may be followed by
+/// anything other than a digit.
+fn leading_digits(token: &str, min: usize, max: usize) -> Option<u64> {
+    let len = token.bytes().take_while(u8::is_ascii_digit).count();
+    (min..=max)
+        .contains(&len)
+        .then(|| token[..len].parse().ok())?
+}
+
+/// Parse `token`, which must be `min..=max` digits.
+fn exact_digits(token: &str, min: usize, max: usize) -> Option<u64> {
+    let value = leading_digits(token, min, max)?;
+    (token.bytes().all(|b| b.is_ascii_digit())).then_some(value)
+}
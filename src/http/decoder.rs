@@ -0,0 +1,333 @@
+//! Decoding compressed response bodies.
+//!
+//! Each supported `Content-Encoding` is behind a cargo feature: `gzip`,
+//! `deflate` and `brotli`. Without any of them, nothing is decoded.
+#![cfg_attr(
+    not(any(feature = "gzip", feature = "deflate", feature = "brotli")),
+    allow(dead_code, unreachable_code, unused_variables)
+)]
+
+use std::fmt;
+use std::io;
+
+use super::HeaderValue;
+
+/// The `Accept-Encoding` header listing the encodings which can be decoded,
+/// or `None` if no encodings are enabled.
+pub(crate) fn accept_encoding() -> Option<HeaderValue> {
+    let encodings: &[&str] = &[
+        #[cfg(feature = "gzip")]
+        "gzip",
+        #[cfg(feature = "deflate")]
+        "deflate",
+        #[cfg(feature = "brotli")]
+        "br",
+    ];
+    if encodings.is_empty() {
+        return None;
+    }
+    HeaderValue::from_str(&encodings.join(", ")).ok()
+}
+
+/// A streaming decoder for a compressed body.
+pub(crate) struct Decoder {
+    kind: Kind,
+    started: bool,
+    done: bool,
+}
+
+enum Kind {
+    #[cfg(feature = "gzip")]
+    Gzip(Box<Gzip>),
+    #[cfg(feature = "deflate")]
+    Deflate(Option<flate2::Decompress>),
+    #[cfg(feature = "brotli")]
+    Brotli(Box<Brotli>),
+}
+
+impl Decoder {
+    /// Create a decoder for a `Content-Encoding`, or `None` if it isn't
+    /// supported.
+    pub(crate) fn new(encoding: &str) -> Option<Self> {
+        let kind = match encoding.to_ascii_lowercase().as_str() {
+            #[cfg(feature = "gzip")]
+            "gzip" | "x-gzip" => Kind::Gzip(Box::default()),
+            #[cfg(feature = "deflate")]
+            "deflate" => Kind::Deflate(None),
+            #[cfg(feature = "brotli")]
+            "br" => Kind::Brotli(Box::default()),
+            _ => return None,
+        };
+        Some(Self {
+            kind,
+            started: false,
+            done: false,
+        })
+    }
+
+    /// Whether the end of the compressed data was reached.
+    pub(crate) fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Decode some of `input` into `output`, returning how many bytes were
+    /// consumed from `input` and how many were written to `output`.
+    ///
+    /// `eof` means that `input` is all that is left of the body. Fails if the
+    /// compressed data is invalid, or if it ends early.
+    pub(crate) fn decode(
+        &mut self,
+        input: &[u8],
+        output: &mut [u8],
+        eof: bool,
+    ) -> io::Result<(usize, usize)> {
+        if self.done {
+            return Ok((input.len(), 0));
+        }
+        if input.is_empty() && eof && !self.started {
+            // An empty body, such as the body of a response to `HEAD`.
+            self.done = true;
+            return Ok((0, 0));
+        }
+        self.started |= !input.is_empty();
+
+        let (consumed, produced, done) = match self.kind {
+            #[cfg(feature = "gzip")]
+            Kind::Gzip(ref mut gzip) => gzip.decode(input, output)?,
+            #[cfg(feature = "deflate")]
+            Kind::Deflate(ref mut inflate) => {
+                let inflate = inflate.get_or_insert_with(|| {
+                    // Servers send either zlib data, as the specification
+                    // says, or raw deflate data.
+                    let zlib = input.first().is_some_and(|b| b & 0x0f == 8 && b >> 4 <= 7);
+                    flate2::Decompress::new(zlib)
+                });
+                inflate_step(inflate, input, output)?
+            }
+            #[cfg(feature = "brotli")]
+            Kind::Brotli(ref mut brotli) => brotli.decode(input, output)?,
+        };
+        self.done = done;
+        if eof && !done && consumed == 0 && produced == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "compressed body ended early",
+            ));
+        }
+        Ok((consumed, produced))
+    }
+}
+
+impl fmt::Debug for Decoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Decoder")
+            .field("encoding", &self.kind.encoding())
+            .field("done", &self.done)
+            .finish()
+    }
+}
+
+impl Kind {
+    fn encoding(&self) -> &'static str {
+        match *self {
+            #[cfg(feature = "gzip")]
+            Kind::Gzip(_) => "gzip",
+            #[cfg(feature = "deflate")]
+            Kind::Deflate(_) => "deflate",
+            #[cfg(feature = "brotli")]
+            Kind::Brotli(_) => "br",
+        }
+    }
+}
+
+fn invalid_data(encoding: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid {encoding} body"),
+    )
+}
+
+/// Inflate some of `input` into `output`, returning the bytes consumed and
+/// produced, and whether the end of the deflate stream was reached.
+#[cfg(any(feature = "gzip", feature = "deflate"))]
+fn inflate_step(
+    inflate: &mut flate2::Decompress,
+    input: &[u8],
+    output: &mut [u8],
+) -> io::Result<(usize, usize, bool)> {
+    let (before_in, before_out) = (inflate.total_in(), inflate.total_out());
+    let status = inflate
+        .decompress(input, output, flate2::FlushDecompress::None)
+        .map_err(|_| invalid_data("deflate"))?;
+    Ok((
+        (inflate.total_in() - before_in) as usize,
+        (inflate.total_out() - before_out) as usize,
+        status == flate2::Status::StreamEnd,
+    ))
+}
+
+/// The longest gzip header which is accepted, including the file name and
+/// comment.
+#[cfg(feature = "gzip")]
+const MAX_GZIP_HEADER: usize = 64 * 1024;
+
+/// A gzip member: a header, deflate data, and a trailer with its CRC-32 and
+/// length.
+#[cfg(feature = "gzip")]
+struct Gzip {
+    state: GzipState,
+    inflate: flate2::Decompress,
+    crc: flate2::Crc,
+}
+
+#[cfg(feature = "gzip")]
+enum GzipState {
+    Header(Vec<u8>),
+    Body,
+    Trailer(Vec<u8>),
+}
+
+#[cfg(feature = "gzip")]
+impl Default for Gzip {
+    fn default() -> Self {
+        Self {
+            state: GzipState::Header(Vec::new()),
+            inflate: flate2::Decompress::new(false),
+            crc: flate2::Crc::new(),
+        }
+    }
+}
+
+#[cfg(feature = "gzip")]
+impl Gzip {
+    fn decode(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<(usize, usize, bool)> {
+        match &mut self.state {
+            GzipState::Header(header) => {
+                let buffered = header.len();
+                header.extend_from_slice(input);
+                match gzip_header_len(header)? {
+                    Some(len) => {
+                        self.state = GzipState::Body;
+                        Ok((len - buffered, 0, false))
+                    }
+                    None if header.len() > MAX_GZIP_HEADER => Err(invalid_data("gzip")),
+                    None => Ok((input.len(), 0, false)),
+                }
+            }
+            GzipState::Body => {
+                let (consumed, produced, end) = inflate_step(&mut self.inflate, input, output)?;
+                self.crc.update(&output[..produced]);
+                if end {
+                    self.state = GzipState::Trailer(Vec::with_capacity(8));
+                }
+                Ok((consumed, produced, false))
+            }
+            GzipState::Trailer(trailer) => {
+                let n = input.len().min(8 - trailer.len());
+                trailer.extend_from_slice(&input[..n]);
+                if trailer.len() < 8 {
+                    return Ok((n, 0, false));
+                }
+                let crc = u32::from_le_bytes(trailer[..4].try_into().unwrap());
+                let len = u32::from_le_bytes(trailer[4..].try_into().unwrap());
+                if crc != self.crc.sum() || len != self.crc.amount() {
+                    return Err(invalid_data("gzip"));
+                }
+                Ok((n, 0, true))
+            }
+        }
+    }
+}
+
+/// The length of the gzip header at the start of `data`, or `None` if it is
+/// incomplete.
+#[cfg(feature = "gzip")]
+fn gzip_header_len(data: &[u8]) -> io::Result<Option<usize>> {
+    const FHCRC: u8 = 1 << 1;
+    const FEXTRA: u8 = 1 << 2;
+    const FNAME: u8 = 1 << 3;
+    const FCOMMENT: u8 = 1 << 4;
+
+    let magic = [0x1f, 0x8b, 8];
+    let prefix = data.len().min(magic.len());
+    if data[..prefix] != magic[..prefix] {
+        return Err(invalid_data("gzip"));
+    }
+    let Some(&flags) = data.get(3) else {
+        return Ok(None);
+    };
+    let mut len = 10;
+    if flags & FEXTRA != 0 {
+        let Some(extra) = data.get(len..len + 2) else {
+            return Ok(None);
+        };
+        len += 2 + u16::from_le_bytes([extra[0], extra[1]]) as usize;
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            // A zero-terminated string.
+            let Some(end) = data.get(len..).and_then(|s| s.iter().position(|&b| b == 0)) else {
+                return Ok(None);
+            };
+            len += end + 1;
+        }
+    }
+    if flags & FHCRC != 0 {
+        len += 2;
+    }
+    Ok((len <= data.len()).then_some(len))
+}
+
+#[cfg(feature = "brotli")]
+struct Brotli {
+    state: brotli_decompressor::BrotliState<
+        brotli_decompressor::StandardAlloc,
+        brotli_decompressor::StandardAlloc,
+        brotli_decompressor::StandardAlloc,
+    >,
+}
+
+#[cfg(feature = "brotli")]
+impl Default for Brotli {
+    fn default() -> Self {
+        use brotli_decompressor::StandardAlloc;
+        Self {
+            state: brotli_decompressor::BrotliState::new(
+                StandardAlloc::default(),
+                StandardAlloc::default(),
+                StandardAlloc::default(),
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "brotli")]
+impl Brotli {
+    fn decode(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<(usize, usize, bool)> {
+        use brotli_decompressor::BrotliResult;
+
+        let mut available_in = input.len();
+        let mut input_offset = 0;
+        let mut available_out = output.len();
+        let mut output_offset = 0;
+        let mut total_out = 0;
+        let result = brotli_decompressor::BrotliDecompressStream(
+            &mut available_in,
+            &mut input_offset,
+            input,
+            &mut available_out,
+            &mut output_offset,
+            output,
+            &mut total_out,
+            &mut self.state,
+        );
+        match result {
+            BrotliResult::ResultFailure => Err(invalid_data("brotli")),
+            result => Ok((
+                input_offset,
+                output_offset,
+                matches!(result, BrotliResult::ResultSuccess),
+            )),
+        }
+    }
+}
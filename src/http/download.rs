@@ -0,0 +1,292 @@
+//! Resumable downloads
+//!
+//! A [`Download`] writes the body of a response into a writer which can seek,
+//! such as an [`io::Cursor`](crate::io::Cursor). When the connection fails
+//! part of the way through, for example because of a between-bytes timeout,
+//! the download is resumed from the last byte received with a `Range`
+//! request, instead of starting over:
+//!
+//! ```no_run
+//! use wstd::http::download::Download;
+//! use wstd::http::{Client, Method, Request, RetryPolicy};
+//! use wstd::io::Cursor;
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let request = Request::new(Method::GET, "https://example.com/artifact.tar".parse()?);
+//! let mut artifact = Cursor::new(Vec::new());
+//! let len = Download::new(request)
+//!     .with_retry_policy(RetryPolicy::new(10))
+//!     .send(&Client::new(), &mut artifact)
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! A resumed request carries an `If-Range` header with the strong `ETag` or
+//! the `Last-Modified` date of the first response, so that the rest of the
+//! body is only sent if the resource didn't change. The server must then
+//! respond with `206 Partial Content`, and a `Content-Range` which starts
+//! where the download left off. If the server sends the whole resource
+//! instead, the download starts over from the beginning. A response without
+//! either validator can't be resumed safely, so it is downloaded again from
+//! the beginning as well.
+//!
+//! Ranges apply to the body as it is sent, so the response isn't
+//! decompressed, and `Accept-Encoding: identity` is sent unless the request
+//! has an `Accept-Encoding` header.
+
+use http::header::{ACCEPT_ENCODING, CONTENT_RANGE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE};
+
+use super::request::Parts;
+use super::response::IncomingBody;
+use super::{Client, Error, HeaderValue, Request, Response, Result, RetryPolicy};
+use crate::io::{empty, AsyncRead, AsyncSeek, AsyncWrite, Empty, SeekFrom};
+
+/// How much of the body is read at a time.
+const CHUNK_SIZE: usize = 16 * 1024;
+
+/// A download which is resumed after transient errors.
+#[derive(Debug)]
+pub struct Download {
+    parts: Parts,
+    retry_policy: RetryPolicy,
+}
+
+/// What is known about the resource being downloaded.
+#[derive(Debug, Default)]
+struct Progress {
+    // How many bytes of the body were written.
+    offset: u64,
+    // The length of the whole body, if known.
+    len: Option<u64>,
+    // The `ETag` or `Last-Modified` value to send in `If-Range`.
+    validator: Option<HeaderValue>,
+}
+
+impl Download {
+    /// Create a download of the response to `request`.
+    ///
+    /// The download is attempted up to 5 times in a row without receiving
+    /// any bytes, with the default backoff of [`RetryPolicy::new`].
+    pub fn new(request: Request<Empty>) -> Self {
+        let (mut parts, _) = request.into_parts();
+        if !parts.headers.contains_key(ACCEPT_ENCODING) {
+            parts
+                .headers
+                .insert(ACCEPT_ENCODING, HeaderValue::from_static("identity"));
+        }
+        Self {
+            parts,
+            retry_policy: RetryPolicy::new(5),
+        }
+    }
+
+    /// Set how often, and after how long, a failed download is resumed.
+    ///
+    /// The number of attempts counts attempts in a row which didn't receive
+    /// any bytes, so a download which keeps making progress isn't abandoned.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Send the request with `client`, and write the body of the response to
+    /// `writer`, returning its length.
+    ///
+    /// The body is written from the position of `writer` when the download
+    /// starts. If the download starts over, bytes written past the end of
+    /// the new body by an earlier attempt are left as they were.
+    ///
+    /// Fails if the server responds with a status other than `200 OK` or
+    /// `206 Partial Content`, or once the download failed too many times.
+    pub async fn send<W>(&self, client: &Client, mut writer: W) -> Result<u64>
+    where
+        W: AsyncWrite + AsyncSeek,
+    {
+        let io_error = |e| Error::from_io(e).context("writing download");
+        let start = writer.stream_position().await.map_err(io_error)?;
+        let mut progress = Progress::default();
+        let mut attempt = 1;
+        loop {
+            let before = progress.offset;
+            let result = match self.request(client, &mut progress).await {
+                Ok(mut response) => {
+                    writer
+                        .seek(SeekFrom::Start(start + progress.offset))
+                        .await
+                        .map_err(io_error)?;
+                    copy_body(response.body(), &mut writer, &mut progress).await
+                }
+                Err(err) => Err(err),
+            };
+            let err = match result {
+                Ok(()) => {
+                    writer.flush().await.map_err(io_error)?;
+                    return Ok(progress.offset);
+                }
+                Err(Failure::Write(err)) => return Err(io_error(err)),
+                Err(Failure::Fatal(err)) => return Err(err),
+                Err(Failure::Transient(err)) => err,
+            };
+
+            if progress.offset > before {
+                attempt = 1;
+            }
+            let Some(delay) = self.retry_policy.resume_delay(attempt) else {
+                return Err(err.context("download failed too many times"));
+            };
+            if progress.validator.is_none() {
+                progress.offset = 0;
+            }
+            crate::task::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Send a request for the rest of the body, and check that its response
+    /// continues where the download left off.
+    async fn request(
+        &self,
+        client: &Client,
+        progress: &mut Progress,
+    ) -> std::result::Result<Response<IncomingBody>, Failure> {
+        let mut request = Request::from_parts(self.parts.clone(), empty());
+        request.set_decompress(false);
+        if progress.offset > 0 {
+            let headers = request.headers_mut();
+            let range = format!("bytes={}-", progress.offset);
+            headers.insert(RANGE, HeaderValue::try_from(range).map_err(Error::from)?);
+            if let Some(validator) = &progress.validator {
+                headers.insert(IF_RANGE, validator.clone());
+            }
+        }
+        let result = client.send(request).await;
+        let transient = self
+            .retry_policy
+            .delay(1, &self.parts.method, &result)
+            .is_some();
+        let response = match result {
+            Ok(response) => response,
+            Err(err) if transient => return Err(Failure::Transient(err)),
+            Err(err) => return Err(Failure::Fatal(err)),
+        };
+
+        let status = u16::from(response.status_code());
+        match status {
+            200 => {
+                // The whole body, either because this is the first request,
+                // or because the resource changed.
+                progress.offset = 0;
+                progress.len = response.content_length().ok().flatten();
+                progress.validator = validator(&response);
+            }
+            206 if progress.offset > 0 => {
+                let range = response
+                    .headers()
+                    .get(CONTENT_RANGE)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(parse_content_range);
+                let Some((first, len)) = range else {
+                    return Err(Failure::Fatal(Error::other(
+                        "partial response has an invalid Content-Range",
+                    )));
+                };
+                if first != progress.offset || progress.len.zip(len).is_some_and(|(a, b)| a != b) {
+                    return Err(Failure::Fatal(Error::other(format!(
+                        "partial response starts at byte {first}, not {}",
+                        progress.offset
+                    ))));
+                }
+                progress.len = progress.len.or(len);
+            }
+            _ if transient => {
+                return Err(Failure::Transient(Error::other(format!(
+                    "download responded with status {status}"
+                ))))
+            }
+            _ => {
+                return Err(Failure::Fatal(Error::other(format!(
+                    "download responded with status {status}"
+                ))))
+            }
+        }
+        Ok(response)
+    }
+}
+
+/// Why an attempt to download failed.
+enum Failure {
+    /// The download can be resumed.
+    Transient(Error),
+    /// The download can't be resumed.
+    Fatal(Error),
+    /// Writing the body failed.
+    Write(std::io::Error),
+}
+
+impl From<Error> for Failure {
+    fn from(err: Error) -> Self {
+        Failure::Fatal(err)
+    }
+}
+
+/// Copy the body of a response into `writer`, counting the bytes written.
+async fn copy_body<W: AsyncWrite>(
+    body: &mut IncomingBody,
+    writer: &mut W,
+    progress: &mut Progress,
+) -> std::result::Result<(), Failure> {
+    let mut buf = vec![0; CHUNK_SIZE];
+    loop {
+        let n = match body.read(&mut buf).await {
+            Ok(n) => n,
+            // The connection failed, so try again.
+            Err(err) => {
+                return Err(Failure::Transient(
+                    Error::from_io(err).context("reading download"),
+                ))
+            }
+        };
+        if n == 0 {
+            break;
+        }
+        writer.write_all(&buf[..n]).await.map_err(Failure::Write)?;
+        progress.offset += n as u64;
+    }
+    match progress.len {
+        Some(len) if progress.offset < len => Err(Failure::Transient(Error::other(format!(
+            "download ended after {} of {len} bytes",
+            progress.offset
+        )))),
+        _ => Ok(()),
+    }
+}
+
+/// Get the strong `ETag`, or else the `Last-Modified` date of a response,
+/// which can be sent in `If-Range`.
+fn validator(response: &Response<IncomingBody>) -> Option<HeaderValue> {
+    let headers = response.headers();
+    headers
+        .get(ETAG)
+        .filter(|etag| !etag.as_bytes().starts_with(b"W/"))
+        .or_else(|| headers.get(LAST_MODIFIED))
+        .cloned()
+}
+
+/// Parse a `Content-Range` such as `bytes 100-199/1000`, returning the first
+/// byte, and the length of the whole body if it is known.
+fn parse_content_range(value: &str) -> Option<(u64, Option<u64>)> {
+    let range = value.trim().strip_prefix("bytes ")?;
+    let (range, len) = range.split_once('/')?;
+    let (first, last) = range.split_once('-')?;
+    let first: u64 = first.trim().parse().ok()?;
+    let last: u64 = last.trim().parse().ok()?;
+    let len = match len.trim() {
+        "*" => None,
+        len => Some(len.parse::<u64>().ok()?),
+    };
+    if last < first || len.is_some_and(|len| last >= len) {
+        return None;
+    }
+    Some((first, len))
+}
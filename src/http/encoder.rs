@@ -0,0 +1,170 @@
+//! Compressing request bodies.
+
+use super::{Body, HeaderMap, Result};
+use crate::io::{self, AsyncRead};
+
+/// The size of the buffer for the uncompressed body.
+const INPUT_SIZE: usize = 8 * 1024;
+
+/// A `Content-Encoding` which a [`CompressedBody`] can be compressed with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ContentEncoding {
+    /// `gzip`, with the `gzip` feature.
+    #[cfg(feature = "gzip")]
+    Gzip,
+    /// `deflate`, which is zlib data, with the `deflate` feature.
+    #[cfg(feature = "deflate")]
+    Deflate,
+}
+
+impl ContentEncoding {
+    /// The name of the encoding, as used in `Content-Encoding` headers.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            #[cfg(feature = "gzip")]
+            ContentEncoding::Gzip => "gzip",
+            #[cfg(feature = "deflate")]
+            ContentEncoding::Deflate => "deflate",
+        }
+    }
+}
+
+/// A body which is compressed as it is sent.
+///
+/// The length of the compressed body isn't known in advance, so it is sent
+/// with chunked encoding. [`Request::compress`](super::Request::compress)
+/// wraps the body of a request, and sets its `Content-Encoding` header.
+///
+/// ```no_run
+/// use wstd::http::body::ContentEncoding;
+/// use wstd::http::{Method, Request};
+///
+/// let request = Request::new(Method::POST, "https://example.com/telemetry".parse().unwrap())
+///     .set_body("a large, repetitive body")
+///     .compress(ContentEncoding::Gzip);
+/// ```
+#[derive(Debug)]
+pub struct CompressedBody<B> {
+    body: B,
+    encoding: ContentEncoding,
+    compress: flate2::Compress,
+    crc: flate2::Crc,
+    input: Vec<u8>,
+    input_len: usize,
+    // How many bytes have we already compressed from the input?
+    input_offset: usize,
+    input_ended: bool,
+    // The gzip header or trailer, which is sent before or after the
+    // compressed data.
+    framing: Vec<u8>,
+    framing_offset: usize,
+    finished: bool,
+}
+
+impl<B: Body> CompressedBody<B> {
+    /// Create a body which compresses `body` with `encoding`.
+    pub fn new(body: B, encoding: ContentEncoding) -> Self {
+        let level = flate2::Compression::default();
+        let (compress, framing) = match encoding {
+            #[cfg(feature = "gzip")]
+            ContentEncoding::Gzip => (
+                flate2::Compress::new(level, false),
+                // No modification time or flags, from an unknown OS.
+                vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff],
+            ),
+            #[cfg(feature = "deflate")]
+            ContentEncoding::Deflate => (flate2::Compress::new(level, true), Vec::new()),
+        };
+        Self {
+            body,
+            encoding,
+            compress,
+            crc: flate2::Crc::new(),
+            input: vec![0; INPUT_SIZE],
+            input_len: 0,
+            input_offset: 0,
+            input_ended: false,
+            framing,
+            framing_offset: 0,
+            finished: false,
+        }
+    }
+
+    /// The encoding the body is compressed with.
+    pub fn encoding(&self) -> ContentEncoding {
+        self.encoding
+    }
+
+    /// Add the gzip trailer, with the CRC-32 and length of the body.
+    fn finish(&mut self) {
+        self.finished = true;
+        #[cfg(feature = "gzip")]
+        if self.encoding == ContentEncoding::Gzip {
+            self.framing.clear();
+            self.framing_offset = 0;
+            self.framing
+                .extend_from_slice(&self.crc.sum().to_le_bytes());
+            self.framing
+                .extend_from_slice(&self.crc.amount().to_le_bytes());
+        }
+    }
+}
+
+impl<B: Body> AsyncRead for CompressedBody<B> {
+    async fn read(&mut self, buf: &mut [u8]) -> crate::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            if self.framing_offset < self.framing.len() {
+                let framing = &self.framing[self.framing_offset..];
+                let len = framing.len().min(buf.len());
+                buf[..len].copy_from_slice(&framing[..len]);
+                self.framing_offset += len;
+                return Ok(len);
+            }
+            if self.finished {
+                return Ok(0);
+            }
+
+            if self.input_offset == self.input_len && !self.input_ended {
+                let n = self.body.read(&mut self.input).await?;
+                self.crc.update(&self.input[..n]);
+                self.input_len = n;
+                self.input_offset = 0;
+                self.input_ended = n == 0;
+            }
+
+            let flush = if self.input_ended {
+                flate2::FlushCompress::Finish
+            } else {
+                flate2::FlushCompress::None
+            };
+            let (before_in, before_out) = (self.compress.total_in(), self.compress.total_out());
+            let status = self
+                .compress
+                .compress(&self.input[self.input_offset..self.input_len], buf, flush)
+                .map_err(io::Error::other)?;
+            self.input_offset += (self.compress.total_in() - before_in) as usize;
+            let produced = (self.compress.total_out() - before_out) as usize;
+
+            if status == flate2::Status::StreamEnd {
+                self.finish();
+            }
+            if produced > 0 {
+                return Ok(produced);
+            }
+        }
+    }
+}
+
+impl<B: Body> Body for CompressedBody<B> {
+    fn len(&self) -> Option<usize> {
+        None
+    }
+
+    async fn trailers(&mut self) -> Result<Option<HeaderMap>> {
+        self.body.trailers().await
+    }
+}
@@ -16,7 +16,7 @@ pub use wasi::http::types::{ErrorCode as WasiHttpErrorCode, HeaderError as WasiH
 impl fmt::Debug for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         for c in self.context.iter() {
-            write!(f, "in {c}:\n")?;
+            writeln!(f, "in {c}:")?;
         }
         match &self.variant {
             ErrorVariant::WasiHttp(e) => write!(f, "wasi http error: {e:?}"),
@@ -24,6 +24,11 @@ impl fmt::Debug for Error {
             ErrorVariant::HeaderName(e) => write!(f, "header name error: {e:?}"),
             ErrorVariant::HeaderValue(e) => write!(f, "header value error: {e:?}"),
             ErrorVariant::Method(e) => write!(f, "method error: {e:?}"),
+            ErrorVariant::Http(e) => write!(f, "http error: {e:?}"),
+            ErrorVariant::BodyTruncated { expected, received } => {
+                write!(f, "body truncated: received {received} of {expected} bytes")
+            }
+            ErrorVariant::Timeout => write!(f, "request timed out"),
             ErrorVariant::Other(e) => write!(f, "{e}"),
         }
     }
@@ -37,6 +42,11 @@ impl fmt::Display for Error {
             ErrorVariant::HeaderName(e) => write!(f, "header name error: {e}"),
             ErrorVariant::HeaderValue(e) => write!(f, "header value error: {e}"),
             ErrorVariant::Method(e) => write!(f, "method error: {e}"),
+            ErrorVariant::Http(e) => write!(f, "http error: {e}"),
+            ErrorVariant::BodyTruncated { expected, received } => {
+                write!(f, "body truncated: received {received} of {expected} bytes")
+            }
+            ErrorVariant::Timeout => write!(f, "request timed out"),
             ErrorVariant::Other(e) => write!(f, "{e}"),
         }
     }
@@ -48,9 +58,34 @@ impl Error {
     pub fn variant(&self) -> &ErrorVariant {
         &self.variant
     }
+    /// Whether the request timed out, either because its total timeout
+    /// expired, or because of one of the timeouts enforced by the host.
+    pub fn is_timeout(&self) -> bool {
+        matches!(
+            self.variant,
+            ErrorVariant::Timeout
+                | ErrorVariant::WasiHttp(
+                    WasiHttpErrorCode::DnsTimeout
+                        | WasiHttpErrorCode::ConnectionTimeout
+                        | WasiHttpErrorCode::ConnectionReadTimeout
+                        | WasiHttpErrorCode::ConnectionWriteTimeout
+                        | WasiHttpErrorCode::HttpResponseTimeout
+                )
+        )
+    }
     pub(crate) fn other(s: impl Into<String>) -> Self {
         ErrorVariant::Other(s.into()).into()
     }
+    /// Convert an `io::Error` from reading or writing a body, recovering the
+    /// `Error` it wraps, if any.
+    pub(crate) fn from_io(err: std::io::Error) -> Self {
+        if err.get_ref().is_some_and(|inner| inner.is::<Error>()) {
+            let inner = err.into_inner().expect("checked above");
+            *inner.downcast::<Error>().expect("checked above")
+        } else {
+            Error::other(err.to_string())
+        }
+    }
     pub(crate) fn context(self, s: impl Into<String>) -> Self {
         let mut context = self.context;
         context.push(s.into());
@@ -100,6 +135,12 @@ impl From<InvalidMethod> for Error {
     }
 }
 
+impl From<http::Error> for Error {
+    fn from(e: http::Error) -> Error {
+        ErrorVariant::Http(e).into()
+    }
+}
+
 #[derive(Debug)]
 pub enum ErrorVariant {
     WasiHttp(WasiHttpErrorCode),
@@ -107,5 +148,14 @@ pub enum ErrorVariant {
     HeaderName(InvalidHeaderName),
     HeaderValue(InvalidHeaderValue),
     Method(InvalidMethod),
+    Http(http::Error),
+    /// An incoming body ended before its `Content-Length` was received.
+    BodyTruncated {
+        expected: u64,
+        received: u64,
+    },
+    /// The total timeout of a request expired, see
+    /// [`Client::set_timeout`](super::Client::set_timeout).
+    Timeout,
     Other(String),
 }
@@ -0,0 +1,241 @@
+//! Typed access to common headers.
+//!
+//! [`Request`](super::Request), [`Response`](super::Response) and
+//! [`IncomingRequest`](super::server::IncomingRequest) parse and set headers
+//! with these functions. A header which is present but invalid is an error.
+
+use std::fmt;
+use std::str::FromStr;
+
+use http::header::{ACCEPT, CONTENT_LENGTH, CONTENT_TYPE, DATE, ETAG, USER_AGENT};
+
+use super::{date, Error, HeaderMap, HeaderName, HeaderValue, Mime, Result};
+use crate::time::SystemTime;
+
+/// An entity tag, as sent in `ETag` and `If-None-Match` headers.
+///
+/// ```
+/// use wstd::http::ETag;
+///
+/// let etag: ETag = "W/\"v2\"".parse().unwrap();
+/// assert!(etag.is_weak());
+/// assert_eq!(etag.tag(), "v2");
+/// assert!(etag.weak_eq(&ETag::strong("v2").unwrap()));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ETag {
+    tag: String,
+    weak: bool,
+}
+
+impl ETag {
+    /// Create a strong entity tag, which changes whenever the body changes.
+    ///
+    /// Fails if `tag` contains `"`, spaces, or characters other than ASCII.
+    pub fn strong(tag: impl Into<String>) -> Result<Self> {
+        Self::new(tag.into(), false)
+    }
+
+    /// Create a weak entity tag, which only changes when the meaning of the
+    /// body changes.
+    ///
+    /// Fails if `tag` contains `"`, spaces, or characters other than ASCII.
+    pub fn weak(tag: impl Into<String>) -> Result<Self> {
+        Self::new(tag.into(), true)
+    }
+
+    fn new(tag: String, weak: bool) -> Result<Self> {
+        if !tag.bytes().all(|b| b == b'!' || (b'#'..=b'~').contains(&b)) {
+            return Err(Error::other(format!("invalid entity tag: {tag:?}")));
+        }
+        Ok(Self { tag, weak })
+    }
+
+    /// Get the tag, without quotes.
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// Whether the entity tag is weak.
+    pub fn is_weak(&self) -> bool {
+        self.weak
+    }
+
+    /// Whether both entity tags are strong and equal, as required to resume
+    /// a download.
+    pub fn strong_eq(&self, other: &ETag) -> bool {
+        !self.weak && !other.weak && self.tag == other.tag
+    }
+
+    /// Whether both entity tags are equal, regardless of whether they are
+    /// weak, as compared by `If-None-Match`.
+    pub fn weak_eq(&self, other: &ETag) -> bool {
+        self.tag == other.tag
+    }
+}
+
+impl FromStr for ETag {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let (weak, quoted) = match s.strip_prefix("W/") {
+            Some(quoted) => (true, quoted),
+            None => (false, s),
+        };
+        let tag = quoted
+            .strip_prefix('"')
+            .and_then(|tag| tag.strip_suffix('"'))
+            .ok_or_else(|| Error::other(format!("invalid entity tag: {s}")))?;
+        Self::new(tag.to_owned(), weak)
+    }
+}
+
+impl fmt::Display for ETag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.weak {
+            f.write_str("W/")?;
+        }
+        write!(f, "\"{}\"", self.tag)
+    }
+}
+
+impl From<&ETag> for HeaderValue {
+    fn from(etag: &ETag) -> Self {
+        HeaderValue::from_str(&etag.to_string()).expect("entity tags are valid header values")
+    }
+}
+
+/// Get a header as a string, if it is present.
+fn get<'a>(headers: &'a HeaderMap, name: &HeaderName) -> Result<Option<&'a str>> {
+    let Some(value) = headers.get(name) else {
+        return Ok(None);
+    };
+    value
+        .to_str()
+        .map(|value| Some(value.trim()))
+        .map_err(|_| invalid(name))
+}
+
+fn invalid(name: &HeaderName) -> Error {
+    Error::other(format!("invalid {name} header"))
+}
+
+/// Parse the `Content-Type` header.
+pub(crate) fn content_type(headers: &HeaderMap) -> Result<Option<Mime>> {
+    get(headers, &CONTENT_TYPE)?
+        .map(|value| value.parse().map_err(|_| invalid(&CONTENT_TYPE)))
+        .transpose()
+}
+
+/// Parse the `Content-Length` header.
+pub(crate) fn content_length(headers: &HeaderMap) -> Result<Option<u64>> {
+    get(headers, &CONTENT_LENGTH)?
+        .map(|value| {
+            // Only `1*DIGIT`, which `u64::from_str` is more lenient about.
+            if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(invalid(&CONTENT_LENGTH));
+            }
+            value.parse().map_err(|_| invalid(&CONTENT_LENGTH))
+        })
+        .transpose()
+}
+
+/// Parse the `Date` header.
+pub(crate) fn date(headers: &HeaderMap) -> Result<Option<SystemTime>> {
+    get(headers, &DATE)?
+        .map(|value| date::parse(value).ok_or_else(|| invalid(&DATE)))
+        .transpose()
+}
+
+/// Set the `Date` header.
+pub(crate) fn set_date(headers: &mut HeaderMap, date: SystemTime) {
+    let value = HeaderValue::from_str(&date::format(date)).expect("dates are valid header values");
+    headers.insert(DATE, value);
+}
+
+/// Parse the `ETag` header.
+pub(crate) fn etag(headers: &HeaderMap) -> Result<Option<ETag>> {
+    get(headers, &ETAG)?
+        .map(|value| value.parse().map_err(|_| invalid(&ETAG)))
+        .transpose()
+}
+
+/// Get the `User-Agent` header.
+pub(crate) fn user_agent(headers: &HeaderMap) -> Result<Option<&str>> {
+    get(headers, &USER_AGENT)
+}
+
+/// Parse the media types listed by the `Accept` headers.
+pub(crate) fn accept(headers: &HeaderMap) -> Result<Vec<Mime>> {
+    let mut accept = Vec::new();
+    for value in headers.get_all(ACCEPT) {
+        let value = value.to_str().map_err(|_| invalid(&ACCEPT))?;
+        for item in split_list(value) {
+            accept.push(item.parse().map_err(|_| invalid(&ACCEPT))?);
+        }
+    }
+    Ok(accept)
+}
+
+/// Create an `Authorization` header for HTTP Basic authentication.
+pub(crate) fn basic_auth(username: &str, password: Option<&str>) -> HeaderValue {
+    let credentials = format!("{username}:{}", password.unwrap_or(""));
+    let mut value = HeaderValue::from_str(&format!("Basic {}", base64(credentials.as_bytes())))
+        .expect("base64 is a valid header value");
+    value.set_sensitive(true);
+    value
+}
+
+/// Create an `Authorization` header for a bearer token.
+pub(crate) fn bearer_auth(token: &str) -> Result<HeaderValue> {
+    let mut value = HeaderValue::from_str(&format!("Bearer {token}"))?;
+    value.set_sensitive(true);
+    Ok(value)
+}
+
+/// Split a comma-separated list, ignoring commas in quoted strings, and
+/// skipping empty items.
+fn split_list(list: &str) -> impl Iterator<Item = &str> {
+    let mut items = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, b) in list.bytes().enumerate() {
+        match b {
+            _ if escaped => escaped = false,
+            b'\\' if quoted => escaped = true,
+            b'"' => quoted = !quoted,
+            b',' if !quoted => {
+                items.push(&list[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&list[start..]);
+    items
+        .into_iter()
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+/// Encode `bytes` as base64, with padding.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
@@ -18,8 +18,6 @@ pub(crate) fn to_wasi_method(value: Method) -> WasiMethod {
     }
 }
 
-// This will become useful once we support IncomingRequest
-#[allow(dead_code)]
 pub(crate) fn from_wasi_method(value: WasiMethod) -> Result<Method> {
     Ok(match value {
         WasiMethod::Get => Method::GET,
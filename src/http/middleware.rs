@@ -0,0 +1,330 @@
+//! Middleware for HTTP clients and servers
+//!
+//! A [`Service`] sends a [`Request`] and produces a [`Response`]. [`Client`]
+//! is the innermost service, and a [`Layer`] wraps a service in another
+//! service, which can change the request on its way out, and the response on
+//! its way back in:
+//!
+//! ```no_run
+//! use wstd::http::middleware::{DefaultHeadersLayer, LoggingLayer, RequestIdLayer};
+//! use wstd::http::{Client, HeaderMap, HeaderValue, Method, Request, Service};
+//!
+//! # async fn run() -> wstd::http::Result<()> {
+//! let mut headers = HeaderMap::new();
+//! headers.insert("user-agent", HeaderValue::from_static("my-component"));
+//!
+//! let client = Client::new()
+//!     .layer(DefaultHeadersLayer::new(headers))
+//!     .layer(RequestIdLayer::new())
+//!     .layer(LoggingLayer::new());
+//!
+//! let request = Request::new(Method::GET, "https://example.com".parse().unwrap());
+//! let response = client.call(request).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The same layers can wrap a server [`Handler`], such as a
+//! [`Router`](super::Router). On the server side, [`RequestIdLayer`] also
+//! propagates the id of the incoming request to the requests sent by a
+//! `RequestId` client service while the request is being handled.
+//!
+//! [`Client`]: super::Client
+
+use std::cell::RefCell;
+use std::fmt::{self, Write};
+use std::rc::Rc;
+
+use super::body::IncomingBody;
+use super::server::{Finished, Handler, IncomingRequest, Responder};
+use super::{Body, HeaderMap, HeaderName, HeaderValue, Request, Response, Result};
+use crate::time::Instant;
+
+/// An asynchronous function from a [`Request`] to a [`Response`].
+pub trait Service {
+    /// Send a request, and return its response.
+    async fn call<B: Body>(&self, request: Request<B>) -> Result<Response<IncomingBody>>;
+
+    /// Wrap this service in a [`Layer`].
+    fn layer<L: Layer<Self>>(self, layer: L) -> L::Service
+    where
+        Self: Sized,
+    {
+        layer.layer(self)
+    }
+}
+
+impl<S: Service + ?Sized> Service for &S {
+    async fn call<B: Body>(&self, request: Request<B>) -> Result<Response<IncomingBody>> {
+        (**self).call(request).await
+    }
+}
+
+impl<S: Service + ?Sized> Service for Rc<S> {
+    async fn call<B: Body>(&self, request: Request<B>) -> Result<Response<IncomingBody>> {
+        (**self).call(request).await
+    }
+}
+
+/// Wraps a [`Service`] or [`Handler`] in another one.
+pub trait Layer<S> {
+    /// The wrapping service.
+    type Service;
+
+    /// Wrap `inner`.
+    fn layer(&self, inner: S) -> Self::Service;
+}
+
+/// A [`Layer`] which adds default headers to requests and responses.
+///
+/// Headers which are already set are left alone.
+#[derive(Debug, Clone)]
+pub struct DefaultHeadersLayer {
+    headers: HeaderMap,
+}
+
+impl DefaultHeadersLayer {
+    /// Create a new `DefaultHeadersLayer` which adds `headers`.
+    pub fn new(headers: HeaderMap) -> Self {
+        Self { headers }
+    }
+}
+
+impl<S> Layer<S> for DefaultHeadersLayer {
+    type Service = DefaultHeaders<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        DefaultHeaders {
+            inner,
+            headers: self.headers.clone(),
+        }
+    }
+}
+
+/// The service created by a [`DefaultHeadersLayer`].
+#[derive(Debug, Clone)]
+pub struct DefaultHeaders<S> {
+    inner: S,
+    headers: HeaderMap,
+}
+
+impl<S: Service> Service for DefaultHeaders<S> {
+    async fn call<B: Body>(&self, mut request: Request<B>) -> Result<Response<IncomingBody>> {
+        insert_missing(request.headers_mut(), &self.headers);
+        self.inner.call(request).await
+    }
+}
+
+impl<H: Handler> Handler for DefaultHeaders<H> {
+    async fn handle(&self, request: IncomingRequest, mut responder: Responder) -> Finished {
+        insert_missing(responder.headers_mut(), &self.headers);
+        self.inner.handle(request, responder).await
+    }
+}
+
+/// Append the values of the headers in `defaults` which aren't in `headers`.
+fn insert_missing(headers: &mut HeaderMap, defaults: &HeaderMap) {
+    for name in defaults.keys() {
+        if !headers.contains_key(name) {
+            for value in defaults.get_all(name) {
+                headers.append(name.clone(), value.clone());
+            }
+        }
+    }
+}
+
+thread_local! {
+    static CURRENT_REQUEST_ID: RefCell<Option<HeaderValue>> = const { RefCell::new(None) };
+}
+
+/// A [`Layer`] which makes sure every request carries a request id.
+///
+/// On a client, requests without a request id header are given the id of the
+/// incoming request being handled by a server `RequestId`, if any, or else a
+/// new random id.
+///
+/// On a server, incoming requests keep the id they were sent with, or are
+/// given a new random id. The id is sent back in the response headers, and is
+/// propagated to client requests while the request is being handled.
+#[derive(Debug, Clone)]
+pub struct RequestIdLayer {
+    header: HeaderName,
+}
+
+impl RequestIdLayer {
+    /// Create a new `RequestIdLayer` using the `x-request-id` header.
+    pub fn new() -> Self {
+        Self::with_header(HeaderName::from_static("x-request-id"))
+    }
+
+    /// Create a new `RequestIdLayer` using the given header.
+    pub fn with_header(header: HeaderName) -> Self {
+        Self { header }
+    }
+}
+
+impl Default for RequestIdLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for RequestIdLayer {
+    type Service = RequestId<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestId {
+            inner,
+            header: self.header.clone(),
+        }
+    }
+}
+
+/// The service created by a [`RequestIdLayer`].
+#[derive(Debug, Clone)]
+pub struct RequestId<S> {
+    inner: S,
+    header: HeaderName,
+}
+
+impl<S: Service> Service for RequestId<S> {
+    async fn call<B: Body>(&self, mut request: Request<B>) -> Result<Response<IncomingBody>> {
+        if !request.headers().contains_key(&self.header) {
+            let id = CURRENT_REQUEST_ID
+                .with_borrow(|id| id.clone())
+                .unwrap_or_else(new_request_id);
+            request.headers_mut().insert(self.header.clone(), id);
+        }
+        self.inner.call(request).await
+    }
+}
+
+impl<H: Handler> Handler for RequestId<H> {
+    async fn handle(&self, request: IncomingRequest, mut responder: Responder) -> Finished {
+        let id = request
+            .headers()
+            .get(&self.header)
+            .cloned()
+            .unwrap_or_else(new_request_id);
+        responder
+            .headers_mut()
+            .insert(self.header.clone(), id.clone());
+
+        // Each incoming request is handled on its own `block_on`, so the id
+        // can be kept around for the duration of the handler.
+        let previous = CURRENT_REQUEST_ID.replace(Some(id));
+        let _restore = RestoreRequestId(previous);
+        self.inner.handle(request, responder).await
+    }
+}
+
+struct RestoreRequestId(Option<HeaderValue>);
+
+impl Drop for RestoreRequestId {
+    fn drop(&mut self) {
+        CURRENT_REQUEST_ID.set(self.0.take());
+    }
+}
+
+fn new_request_id() -> HeaderValue {
+    let mut bytes = [0; 16];
+    crate::rand::get_insecure_random_bytes(&mut bytes);
+    let mut id = String::with_capacity(32);
+    for b in bytes {
+        write!(id, "{b:02x}").unwrap();
+    }
+    HeaderValue::from_str(&id).expect("hex digits are a valid header value")
+}
+
+/// A [`Layer`] which logs requests and responses.
+///
+/// By default log lines are written to stderr.
+#[derive(Clone)]
+pub struct LoggingLayer {
+    logger: Rc<dyn Fn(&str)>,
+}
+
+impl LoggingLayer {
+    /// Create a new `LoggingLayer` which writes to stderr.
+    pub fn new() -> Self {
+        Self::with_logger(|line| eprintln!("{line}"))
+    }
+
+    /// Create a new `LoggingLayer` which passes every log line to `logger`.
+    pub fn with_logger(logger: impl Fn(&str) + 'static) -> Self {
+        Self {
+            logger: Rc::new(logger),
+        }
+    }
+}
+
+impl Default for LoggingLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for LoggingLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoggingLayer").finish_non_exhaustive()
+    }
+}
+
+impl<S> Layer<S> for LoggingLayer {
+    type Service = Logging<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Logging {
+            inner,
+            logger: self.logger.clone(),
+        }
+    }
+}
+
+/// The service created by a [`LoggingLayer`].
+#[derive(Clone)]
+pub struct Logging<S> {
+    inner: S,
+    logger: Rc<dyn Fn(&str)>,
+}
+
+impl<S: fmt::Debug> fmt::Debug for Logging<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Logging")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S: Service> Service for Logging<S> {
+    async fn call<B: Body>(&self, request: Request<B>) -> Result<Response<IncomingBody>> {
+        let start = Instant::now();
+        let line = format!("{} {}", request.method(), request.uri());
+        (self.logger)(&format!("--> {line}"));
+        let result = self.inner.call(request).await;
+        let elapsed = std::time::Duration::from(start.elapsed());
+        match &result {
+            Ok(response) => (self.logger)(&format!(
+                "<-- {line}: {} ({elapsed:?})",
+                response.status_code()
+            )),
+            Err(err) => (self.logger)(&format!("<-- {line}: error: {err} ({elapsed:?})")),
+        }
+        result
+    }
+}
+
+impl<H: Handler> Handler for Logging<H> {
+    async fn handle(&self, request: IncomingRequest, responder: Responder) -> Finished {
+        let start = Instant::now();
+        let line = format!("{} {}", request.method(), request.uri());
+        (self.logger)(&format!("<-- {line}"));
+        let finished = self.inner.handle(request, responder).await;
+        let elapsed = std::time::Duration::from(start.elapsed());
+        match finished.error() {
+            None => (self.logger)(&format!("--> {line}: done ({elapsed:?})")),
+            Some(err) => (self.logger)(&format!("--> {line}: error: {err} ({elapsed:?})")),
+        }
+        finished
+    }
+}
@@ -0,0 +1,222 @@
+//! Media types, as used by `Content-Type` and `Accept` headers.
+
+use std::fmt;
+use std::str::FromStr;
+
+use super::{Error, HeaderValue, Result};
+
+/// A media type, such as `text/html; charset=utf-8`.
+///
+/// The type, subtype and parameter names are case-insensitive, so they are
+/// kept in lowercase.
+///
+/// ```
+/// use wstd::http::Mime;
+///
+/// let mime: Mime = "Text/HTML; Charset=\"utf-8\"".parse().unwrap();
+/// assert_eq!(mime.essence(), "text/html");
+/// assert_eq!(mime.charset(), Some("utf-8"));
+/// assert_eq!(mime.to_string(), "text/html; charset=utf-8");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Mime {
+    essence: String,
+    // The position of the `/` in `essence`.
+    slash: usize,
+    params: Vec<(String, String)>,
+}
+
+impl Mime {
+    /// Create a media type without parameters.
+    ///
+    /// Fails if `type_` or `subtype` aren't valid tokens.
+    pub fn new(type_: &str, subtype: &str) -> Result<Self> {
+        if !is_token(type_) || !is_token(subtype) {
+            return Err(Error::other(format!(
+                "invalid media type: {type_}/{subtype}"
+            )));
+        }
+        Ok(Self {
+            essence: format!("{type_}/{subtype}").to_ascii_lowercase(),
+            slash: type_.len(),
+            params: Vec::new(),
+        })
+    }
+
+    /// Add a parameter, such as `charset=utf-8`.
+    ///
+    /// Fails if `name` isn't a valid token, or `value` contains control
+    /// characters or characters other than ASCII.
+    pub fn with_param(mut self, name: &str, value: &str) -> Result<Self> {
+        if !is_token(name)
+            || !value
+                .bytes()
+                .all(|b| b == b'\t' || (b' '..=b'~').contains(&b))
+        {
+            return Err(Error::other(format!(
+                "invalid media type parameter: {name}={value}"
+            )));
+        }
+        self.params
+            .push((name.to_ascii_lowercase(), value.to_owned()));
+        Ok(self)
+    }
+
+    /// Get the type, such as `text`.
+    pub fn type_(&self) -> &str {
+        &self.essence[..self.slash]
+    }
+
+    /// Get the subtype, such as `html`.
+    pub fn subtype(&self) -> &str {
+        &self.essence[self.slash + 1..]
+    }
+
+    /// Get the suffix of the subtype, such as `json` for
+    /// `application/problem+json`.
+    pub fn suffix(&self) -> Option<&str> {
+        self.subtype().rsplit_once('+').map(|(_, suffix)| suffix)
+    }
+
+    /// Get the type and subtype without parameters, such as `text/html`.
+    pub fn essence(&self) -> &str {
+        &self.essence
+    }
+
+    /// Get the value of a parameter.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Get the parameters, in order.
+    pub fn params(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.params.iter().map(|(n, v)| (n.as_str(), v.as_str()))
+    }
+
+    /// Get the `charset` parameter.
+    pub fn charset(&self) -> Option<&str> {
+        self.param("charset")
+    }
+
+    /// Whether this media type, which may contain wildcards such as
+    /// `text/*` or `*/*` as in an `Accept` header, matches `other`.
+    ///
+    /// Parameters aren't compared.
+    pub fn matches(&self, other: &Mime) -> bool {
+        match (self.type_(), self.subtype()) {
+            ("*", "*") => true,
+            (type_, "*") => type_ == other.type_(),
+            _ => self.essence == other.essence,
+        }
+    }
+}
+
+impl FromStr for Mime {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::other(format!("invalid media type: {s}"));
+        let mut rest = s.trim_matches([' ', '\t']);
+        let end = rest.find(';').unwrap_or(rest.len());
+        let (type_, subtype) = rest[..end]
+            .trim_end_matches([' ', '\t'])
+            .split_once('/')
+            .ok_or_else(invalid)?;
+        let mut mime = Mime::new(type_, subtype).map_err(|_| invalid())?;
+        rest = &rest[end..];
+
+        while let Some(params) = rest.strip_prefix(';') {
+            let params = params.trim_start_matches([' ', '\t']);
+            if params.is_empty() || params.starts_with(';') {
+                rest = params;
+                continue;
+            }
+            let (name, value) = params.split_once('=').ok_or_else(invalid)?;
+            let (value, after) = if let Some(quoted) = value.strip_prefix('"') {
+                parse_quoted(quoted).ok_or_else(invalid)?
+            } else {
+                let end = value.find(';').unwrap_or(value.len());
+                let token = value[..end].trim_end_matches([' ', '\t']);
+                if !is_token(token) {
+                    return Err(invalid());
+                }
+                (token.to_owned(), &value[end..])
+            };
+            rest = after.trim_start_matches([' ', '\t']);
+            if !rest.is_empty() && !rest.starts_with(';') {
+                return Err(invalid());
+            }
+            mime = mime.with_param(name, &value).map_err(|_| invalid())?;
+        }
+        Ok(mime)
+    }
+}
+
+impl fmt::Display for Mime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.essence)?;
+        for (name, value) in &self.params {
+            if is_token(value) {
+                write!(f, "; {name}={value}")?;
+            } else {
+                write!(f, "; {name}=\"")?;
+                for c in value.chars() {
+                    if c == '"' || c == '\\' {
+                        f.write_str("\\")?;
+                    }
+                    write!(f, "{c}")?;
+                }
+                f.write_str("\"")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<&HeaderValue> for Mime {
+    type Error = Error;
+
+    fn try_from(value: &HeaderValue) -> Result<Self> {
+        value
+            .to_str()
+            .map_err(|_| Error::other("invalid media type: not visible ASCII"))?
+            .parse()
+    }
+}
+
+impl From<&Mime> for HeaderValue {
+    fn from(mime: &Mime) -> Self {
+        HeaderValue::from_str(&mime.to_string()).expect("media types are valid header values")
+    }
+}
+
+impl From<Mime> for HeaderValue {
+    fn from(mime: Mime) -> Self {
+        HeaderValue::from(&mime)
+    }
+}
+
+/// Whether `s` is a token, as defined by RFC 9110.
+fn is_token(s: &str) -> bool {
+    !s.is_empty()
+        && s.bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+/// Parse the rest of a quoted string, after its opening quote, returning its
+/// value and what follows it.
+fn parse_quoted(s: &str) -> Option<(String, &str)> {
+    let mut value = String::new();
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, &s[i + 1..])),
+            '\\' => value.push(chars.next()?.1),
+            c => value.push(c),
+        }
+    }
+    None
+}
@@ -1,23 +1,51 @@
 //! HTTP networking support
 //!
 pub use http::uri::Uri;
+pub use http::Version;
 
 #[doc(inline)]
 pub use body::{Body, IntoBody};
-pub use client::Client;
+pub use cache::Cache;
+pub use client::{Client, ResponseFuture};
+pub use cookie::CookieJar;
 pub use error::{Error, Result};
 pub use fields::{HeaderMap, HeaderName, HeaderValue};
+pub use headers::ETag;
 pub use method::Method;
+pub use middleware::{Layer, Service};
+pub use mime::Mime;
+pub use redirect::RedirectPolicy;
 pub use request::Request;
 pub use response::Response;
+pub use retry::RetryPolicy;
+pub use router::Router;
 pub use status_code::StatusCode;
 
 pub mod body;
 
+pub mod cache;
+mod charset;
 mod client;
+pub mod cookie;
+mod date;
+mod decoder;
+pub mod download;
+#[cfg(any(feature = "gzip", feature = "deflate"))]
+mod encoder;
 pub mod error;
 mod fields;
+mod headers;
 mod method;
-mod request;
-mod response;
+pub mod middleware;
+mod mime;
+mod multipart;
+pub mod redirect;
+pub mod request;
+pub mod response;
+pub mod retry;
+pub mod router;
+pub mod server;
+pub mod sse;
 mod status_code;
+pub mod transport;
+mod urlencoded;
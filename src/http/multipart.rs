@@ -0,0 +1,661 @@
+//! `multipart/form-data` bodies, for form posts with file uploads.
+
+use super::body::DynRead;
+use super::{charset, Body, Error, HeaderMap, HeaderName, HeaderValue, IntoBody, Result};
+use crate::io::{self, AsyncRead, Cursor};
+use std::collections::VecDeque;
+use std::fmt;
+
+/// The longest headers of a part which [`MultipartReader`] accepts.
+const MAX_HEADERS_SIZE: usize = 16 * 1024;
+
+/// How much [`MultipartReader`] reads from its reader at a time.
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// A `multipart/form-data` body, made of text and file parts.
+///
+/// The body is streamed: files are read as the body is sent. The boundary
+/// between parts is generated randomly, and is part of the `Content-Type` of
+/// the body, which [`Request::multipart`](super::Request::multipart) sets.
+///
+/// ```no_run
+/// use wstd::http::body::Multipart;
+/// use wstd::http::{Method, Request};
+/// use wstd::io::Cursor;
+///
+/// let form = Multipart::new()
+///     .text("title", "Holiday")
+///     .file("photo", "beach.jpg", "image/jpeg", Cursor::new(vec![0; 1024]));
+/// let request = Request::new(Method::POST, "https://example.com/upload".parse().unwrap())
+///     .multipart(form);
+/// ```
+#[derive(Debug)]
+pub struct Multipart {
+    boundary: String,
+    parts: Vec<Part>,
+}
+
+impl Multipart {
+    /// Create an empty form with a random boundary.
+    pub fn new() -> Self {
+        let mut bytes = [0; 16];
+        crate::rand::get_random_bytes(&mut bytes);
+        let mut boundary = String::from("wstd-boundary-");
+        for byte in bytes {
+            boundary.push_str(&format!("{byte:02x}"));
+        }
+        Self {
+            boundary,
+            parts: Vec::new(),
+        }
+    }
+
+    /// Get the boundary between the parts of the body.
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    /// Get the `Content-Type` of the body, including its boundary.
+    pub fn content_type(&self) -> HeaderValue {
+        HeaderValue::from_str(&format!("multipart/form-data; boundary={}", self.boundary))
+            .expect("boundary is a valid header value")
+    }
+
+    /// Add a text field.
+    pub fn text(self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.part(Part::text(name, value))
+    }
+
+    /// Add a file, whose contents are read from `reader` as the body is
+    /// sent.
+    pub fn file<R: AsyncRead + 'static>(
+        self,
+        name: impl Into<String>,
+        file_name: impl Into<String>,
+        content_type: impl Into<String>,
+        reader: R,
+    ) -> Self {
+        self.part(
+            Part::reader(name, reader, None)
+                .file_name(file_name)
+                .content_type(content_type),
+        )
+    }
+
+    /// Add a part.
+    pub fn part(mut self, part: Part) -> Self {
+        self.parts.push(part);
+        self
+    }
+}
+
+impl Default for Multipart {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IntoBody for Multipart {
+    type IntoBody = MultipartBody;
+
+    fn into_body(self) -> MultipartBody {
+        let mut len = Some(0u64);
+        let mut parts = VecDeque::with_capacity(self.parts.len());
+        for (i, part) in self.parts.into_iter().enumerate() {
+            let head = part.head(&self.boundary, i == 0);
+            len = len
+                .zip(part.len)
+                .map(|(len, part_len)| len + head.len() as u64 + part_len);
+            parts.push_back((head, part.reader));
+        }
+        let tail = if parts.is_empty() {
+            format!("--{}--\r\n", self.boundary)
+        } else {
+            format!("\r\n--{}--\r\n", self.boundary)
+        };
+        let len = len.map(|len| len + tail.len() as u64);
+        MultipartBody {
+            parts,
+            tail: Some(tail.into_bytes()),
+            head: Vec::new(),
+            head_offset: 0,
+            reader: None,
+            len,
+        }
+    }
+}
+
+/// A part of a [`Multipart`] form.
+pub struct Part {
+    name: String,
+    file_name: Option<String>,
+    content_type: Option<String>,
+    reader: Box<dyn DynRead>,
+    len: Option<u64>,
+}
+
+impl Part {
+    /// Create a text part.
+    pub fn text(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self::bytes(name, value.into().into_bytes())
+    }
+
+    /// Create a part with the given contents.
+    pub fn bytes(name: impl Into<String>, bytes: Vec<u8>) -> Self {
+        let len = bytes.len() as u64;
+        Self::reader(name, Cursor::new(bytes), Some(len))
+    }
+
+    /// Create a part whose contents are read from `reader`.
+    ///
+    /// If `len` is known, and the lengths of all other parts are known too,
+    /// the length of the whole body is sent as its `Content-Length`, and
+    /// `reader` must produce exactly `len` bytes.
+    pub fn reader<R: AsyncRead + 'static>(
+        name: impl Into<String>,
+        reader: R,
+        len: Option<u64>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            file_name: None,
+            content_type: None,
+            reader: Box::new(reader),
+            len,
+        }
+    }
+
+    /// Set the file name of the part, which makes it a file upload.
+    pub fn file_name(mut self, file_name: impl Into<String>) -> Self {
+        self.file_name = Some(file_name.into());
+        self
+    }
+
+    /// Set the `Content-Type` of the part.
+    pub fn content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+
+    /// The boundary and headers which are sent before the part.
+    fn head(&self, boundary: &str, first: bool) -> Vec<u8> {
+        let mut head = String::new();
+        if !first {
+            head.push_str("\r\n");
+        }
+        head.push_str(&format!("--{boundary}\r\n"));
+        head.push_str("Content-Disposition: form-data; name=\"");
+        quote_into(&mut head, &self.name);
+        head.push('"');
+        if let Some(file_name) = &self.file_name {
+            head.push_str("; filename=\"");
+            quote_into(&mut head, file_name);
+            head.push('"');
+        }
+        head.push_str("\r\n");
+        if let Some(content_type) = &self.content_type {
+            head.push_str("Content-Type: ");
+            quote_into(&mut head, content_type);
+            head.push_str("\r\n");
+        }
+        head.push_str("\r\n");
+        head.into_bytes()
+    }
+}
+
+impl fmt::Debug for Part {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Part")
+            .field("name", &self.name)
+            .field("file_name", &self.file_name)
+            .field("content_type", &self.content_type)
+            .field("len", &self.len)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Escape a name in a header the way browsers do, so that it can't end the
+/// quoted string or the header.
+fn quote_into(out: &mut String, s: &str) {
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("%22"),
+            '\r' => out.push_str("%0D"),
+            '\n' => out.push_str("%0A"),
+            c => out.push(c),
+        }
+    }
+}
+
+/// The body of a [`Multipart`] form.
+pub struct MultipartBody {
+    parts: VecDeque<(Vec<u8>, Box<dyn DynRead>)>,
+    tail: Option<Vec<u8>>,
+    head: Vec<u8>,
+    // How many bytes have we already read from the head?
+    head_offset: usize,
+    reader: Option<Box<dyn DynRead>>,
+    len: Option<u64>,
+}
+
+impl MultipartBody {
+    async fn read_inner(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if self.head_offset < self.head.len() {
+                let head = &self.head[self.head_offset..];
+                let len = head.len().min(buf.len());
+                buf[..len].copy_from_slice(&head[..len]);
+                self.head_offset += len;
+                return Ok(len);
+            }
+            if let Some(reader) = &mut self.reader {
+                match reader.read_dyn(buf).await? {
+                    0 => self.reader = None,
+                    n => return Ok(n),
+                }
+            }
+            let (head, reader) = match self.parts.pop_front() {
+                Some((head, reader)) => (head, Some(reader)),
+                None => match self.tail.take() {
+                    Some(tail) => (tail, None),
+                    None => return Ok(0),
+                },
+            };
+            self.head = head;
+            self.head_offset = 0;
+            self.reader = reader;
+        }
+    }
+}
+
+impl AsyncRead for MultipartBody {
+    async fn read(&mut self, buf: &mut [u8]) -> crate::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.read_inner(buf).await
+    }
+}
+
+impl Body for MultipartBody {
+    fn len(&self) -> Option<usize> {
+        self.len.and_then(|len| usize::try_from(len).ok())
+    }
+}
+
+impl fmt::Debug for MultipartBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MultipartBody")
+            .field("parts", &self.parts.len())
+            .field("len", &self.len)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Get the `boundary` parameter of a `multipart/*` `Content-Type` header
+/// value.
+fn boundary(content_type: &HeaderValue) -> Option<&str> {
+    let content_type = content_type.to_str().ok()?;
+    let mut params = content_type.split(';');
+    let mime = params.next()?.trim();
+    if !mime
+        .get(..10)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case("multipart/"))
+    {
+        return None;
+    }
+    params.find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("boundary")
+            .then(|| value.trim().trim_matches('"'))
+            .filter(|boundary| !boundary.is_empty())
+    })
+}
+
+/// A streaming parser for a `multipart/form-data` body, such as the body of
+/// an incoming request.
+///
+/// Fields are read one at a time, without buffering the whole body:
+///
+/// ```no_run
+/// use wstd::http::server::{Finished, IncomingRequest, Responder};
+/// use wstd::http::{Response, StatusCode};
+///
+/// async fn handle(request: IncomingRequest, responder: Responder) -> Finished {
+///     let mut form = match request.multipart() {
+///         Ok(form) => form,
+///         Err(err) => return responder.fail(err),
+///     };
+///     let mut summary = String::new();
+///     loop {
+///         let mut field = match form.next_field().await {
+///             Ok(Some(field)) => field,
+///             Ok(None) => break,
+///             Err(err) => return responder.fail(err),
+///         };
+///         let name = field.name().unwrap_or_default().to_owned();
+///         match field.bytes().await {
+///             Ok(contents) => summary += &format!("{name}: {} bytes\n", contents.len()),
+///             Err(err) => return responder.fail(err),
+///         }
+///     }
+///     responder
+///         .respond(Response::new(StatusCode::Ok).set_body(summary))
+///         .await
+/// }
+/// ```
+#[derive(Debug)]
+pub struct MultipartReader<R> {
+    reader: R,
+    // `\r\n--` followed by the boundary.
+    delimiter: Vec<u8>,
+    buf: Vec<u8>,
+    eof: bool,
+    state: ReaderState,
+}
+
+#[derive(Debug, PartialEq)]
+enum ReaderState {
+    /// Before the first boundary.
+    Preamble,
+    /// Reading the contents of a field.
+    Field,
+    /// At the boundary after a field.
+    Boundary,
+    /// After the final boundary.
+    Done,
+}
+
+impl<R: AsyncRead> MultipartReader<R> {
+    /// Create a parser for a body read from `reader`, whose parts are
+    /// separated by `boundary`.
+    pub fn new(reader: R, boundary: &str) -> Self {
+        Self {
+            reader,
+            delimiter: format!("\r\n--{boundary}").into_bytes(),
+            // The first boundary isn't preceded by a line break, unless there
+            // is a preamble.
+            buf: b"\r\n".to_vec(),
+            eof: false,
+            state: ReaderState::Preamble,
+        }
+    }
+
+    /// Create a parser for a body read from `reader`, with the boundary from
+    /// its `Content-Type`.
+    pub fn from_content_type(reader: R, content_type: &HeaderValue) -> Result<Self> {
+        let boundary = boundary(content_type)
+            .ok_or_else(|| Error::other("content-type is not multipart with a boundary"))?;
+        Ok(Self::new(reader, boundary))
+    }
+
+    /// Get the next field, or `None` if there are no more.
+    ///
+    /// Any part of the previous field which was not read yet is skipped.
+    pub async fn next_field(&mut self) -> Result<Option<MultipartField<'_, R>>> {
+        self.next_field_inner()
+            .await
+            .map_err(|e| Error::from_io(e).context("reading multipart body"))
+    }
+
+    async fn next_field_inner(&mut self) -> io::Result<Option<MultipartField<'_, R>>> {
+        match self.state {
+            ReaderState::Done => return Ok(None),
+            ReaderState::Preamble => {
+                let delimiter_len = self.delimiter.len();
+                loop {
+                    if let Some(pos) = self.find_delimiter() {
+                        self.buf.drain(..pos);
+                        break;
+                    }
+                    // Keep what could be the start of the delimiter.
+                    let keep = self.buf.len().min(delimiter_len - 1);
+                    self.buf.drain(..self.buf.len() - keep);
+                    self.fill().await?;
+                }
+            }
+            ReaderState::Field => {
+                let mut skip = [0; 1024];
+                while self.read_field(&mut skip).await? > 0 {}
+            }
+            ReaderState::Boundary => {}
+        }
+
+        // The buffer starts with the delimiter, which is followed either by
+        // `--` for the final boundary, or by a line break.
+        let delimiter_len = self.delimiter.len();
+        while self.buf.len() < delimiter_len + 2 {
+            self.fill().await?;
+        }
+        if &self.buf[delimiter_len..delimiter_len + 2] == b"--" {
+            self.state = ReaderState::Done;
+            return Ok(None);
+        }
+        let line_end = loop {
+            if let Some(pos) = find(&self.buf[delimiter_len..], b"\r\n") {
+                break delimiter_len + pos + 2;
+            }
+            if self.buf.len() > delimiter_len + 1024 {
+                return Err(invalid("missing line break after boundary"));
+            }
+            self.fill().await?;
+        };
+        self.buf.drain(..line_end);
+
+        // The headers end with an empty line, which is right away if there
+        // are no headers.
+        let headers_end = loop {
+            if self.buf.starts_with(b"\r\n") {
+                break 0;
+            }
+            if let Some(pos) = find(&self.buf, b"\r\n\r\n") {
+                break pos + 2;
+            }
+            if self.buf.len() > MAX_HEADERS_SIZE {
+                return Err(invalid("part headers are too large"));
+            }
+            self.fill().await?;
+        };
+        let headers = parse_headers(&self.buf[..headers_end])?;
+        self.buf.drain(..headers_end + 2);
+        self.state = ReaderState::Field;
+
+        let disposition = headers
+            .get(http::header::CONTENT_DISPOSITION)
+            .and_then(|value| value.to_str().ok());
+        let name = disposition.and_then(|d| disposition_param(d, "name"));
+        let file_name = disposition.and_then(|d| disposition_param(d, "filename"));
+        Ok(Some(MultipartField {
+            reader: self,
+            headers,
+            name,
+            file_name,
+        }))
+    }
+
+    /// Read some of the contents of the current field, returning `0` at the
+    /// end of the field.
+    async fn read_field(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if self.state != ReaderState::Field || out.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let available = match self.find_delimiter() {
+                Some(0) => {
+                    self.state = ReaderState::Boundary;
+                    return Ok(0);
+                }
+                Some(pos) => pos,
+                // Keep what could be the start of the delimiter.
+                None => self.buf.len().saturating_sub(self.delimiter.len() - 1),
+            };
+            if available > 0 {
+                let len = available.min(out.len());
+                out[..len].copy_from_slice(&self.buf[..len]);
+                self.buf.drain(..len);
+                return Ok(len);
+            }
+            self.fill().await?;
+        }
+    }
+
+    fn find_delimiter(&self) -> Option<usize> {
+        find(&self.buf, &self.delimiter)
+    }
+
+    /// Read more of the body into the buffer, failing if it has ended.
+    async fn fill(&mut self) -> io::Result<()> {
+        if self.eof {
+            return Err(io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "multipart body ended early",
+            ));
+        }
+        let len = self.buf.len();
+        self.buf.resize(len + CHUNK_SIZE, 0);
+        let result = self.reader.read(&mut self.buf[len..]).await;
+        let n = *result.as_ref().unwrap_or(&0);
+        self.buf.truncate(len + n);
+        self.eof = n == 0;
+        result.map(drop)
+    }
+}
+
+/// A field of a multipart body, read with [`MultipartReader::next_field`].
+///
+/// The contents of the field are read through [`AsyncRead`], or with
+/// [`MultipartField::bytes`] or [`MultipartField::text`].
+#[derive(Debug)]
+pub struct MultipartField<'a, R> {
+    reader: &'a mut MultipartReader<R>,
+    headers: HeaderMap,
+    name: Option<String>,
+    file_name: Option<String>,
+}
+
+impl<R: AsyncRead> MultipartField<'_, R> {
+    /// Get the name of the field, from its `Content-Disposition`.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Get the file name of the field, if it is a file upload.
+    pub fn file_name(&self) -> Option<&str> {
+        self.file_name.as_deref()
+    }
+
+    /// Get the `Content-Type` of the field, if any.
+    pub fn content_type(&self) -> Option<&str> {
+        self.headers
+            .get(http::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+    }
+
+    /// Get the headers of the field.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Read the rest of the field into a byte vector.
+    pub async fn bytes(&mut self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.read_to_end(&mut bytes)
+            .await
+            .map_err(|e| Error::from_io(e).context("reading multipart field"))?;
+        Ok(bytes)
+    }
+
+    /// Read the rest of the field as text.
+    ///
+    /// The field is decoded according to the `charset` of its
+    /// `Content-Type`, which defaults to UTF-8.
+    pub async fn text(&mut self) -> Result<String> {
+        let bytes = self.bytes().await?;
+        let charset = self
+            .headers
+            .get(http::header::CONTENT_TYPE)
+            .and_then(charset::from_content_type);
+        charset::decode(bytes, charset)
+    }
+}
+
+impl<R: AsyncRead> AsyncRead for MultipartField<'_, R> {
+    async fn read(&mut self, buf: &mut [u8]) -> crate::io::Result<usize> {
+        self.reader.read_field(buf).await
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(std::io::ErrorKind::InvalidData, message.to_owned())
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Parse the `Name: value` lines of the headers of a part.
+fn parse_headers(data: &[u8]) -> io::Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    for line in data.split(|&b| b == b'\n') {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.is_empty() {
+            continue;
+        }
+        let colon = line
+            .iter()
+            .position(|&b| b == b':')
+            .ok_or_else(|| invalid("invalid part header"))?;
+        let name = HeaderName::from_bytes(&line[..colon])
+            .map_err(|_| invalid("invalid part header name"))?;
+        let value = HeaderValue::from_bytes(line[colon + 1..].trim_ascii())
+            .map_err(|_| invalid("invalid part header value"))?;
+        headers.append(name, value);
+    }
+    Ok(headers)
+}
+
+/// Get a parameter of a `Content-Disposition` header, such as its `name`.
+fn disposition_param(disposition: &str, name: &str) -> Option<String> {
+    let mut rest = disposition.split_once(';')?.1;
+    while !rest.is_empty() {
+        // A parameter ends at the next `;`, unless a value comes first.
+        let end = rest.find([';', '=']).unwrap_or(rest.len());
+        let param = &rest[..end];
+        if !rest[end..].starts_with('=') {
+            rest = rest.get(end + 1..).unwrap_or("");
+            continue;
+        }
+        rest = &rest[end + 1..];
+        let value;
+        let after = rest.trim_start();
+        if let Some(quoted) = after.strip_prefix('"') {
+            // A quoted string, in which `\` escapes the next character.
+            let mut unquoted = String::new();
+            let mut chars = quoted.char_indices();
+            let mut end = quoted.len();
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    '\\' => unquoted.extend(chars.next().map(|(_, c)| c)),
+                    '"' => {
+                        end = i + 1;
+                        break;
+                    }
+                    c => unquoted.push(c),
+                }
+            }
+            value = unquoted;
+            rest = quoted[end..].split_once(';').map_or("", |(_, rest)| rest);
+        } else {
+            let (token, next) = after.split_once(';').unwrap_or((after, ""));
+            value = token.trim().to_owned();
+            rest = next;
+        }
+        if param.trim().eq_ignore_ascii_case(name) {
+            return Some(value);
+        }
+    }
+    None
+}
@@ -0,0 +1,286 @@
+//! Following HTTP redirects
+//!
+//! By default a [`Client`] returns redirect responses as they are. A
+//! [`RedirectPolicy`] makes it follow them instead:
+//!
+//! ```no_run
+//! use wstd::http::{Client, RedirectPolicy};
+//!
+//! let mut client = Client::new();
+//! client.set_redirect_policy(RedirectPolicy::limited(10));
+//! ```
+//!
+//! `301`, `302` and `303` redirects of a `POST` request, and `303` redirects
+//! of any request other than `GET` or `HEAD`, are followed with a `GET`
+//! request without a body. Other redirects are followed with the same method
+//! and body; this is only possible when the body was small enough for the
+//! client to keep a copy of it, and the redirect response is returned as-is
+//! otherwise.
+//!
+//! When a redirect leads to a different origin, the `Authorization`,
+//! `Proxy-Authorization` and `Cookie` headers are not sent along.
+//!
+//! [`Client`]: super::Client
+
+use std::fmt;
+use std::rc::Rc;
+
+use super::{Error, HeaderMap, Method, Result, StatusCode, Uri};
+
+/// Which redirects a [`Client`](super::Client) follows.
+#[derive(Clone)]
+pub struct RedirectPolicy {
+    kind: PolicyKind,
+}
+
+#[derive(Clone)]
+enum PolicyKind {
+    None,
+    Limited(usize),
+    Custom(Rc<dyn Fn(Attempt<'_>) -> Action>),
+}
+
+impl RedirectPolicy {
+    /// Don't follow any redirects.
+    pub fn none() -> Self {
+        Self {
+            kind: PolicyKind::None,
+        }
+    }
+
+    /// Follow up to `max` redirects, and fail if there are more.
+    pub fn limited(max: usize) -> Self {
+        Self {
+            kind: PolicyKind::Limited(max),
+        }
+    }
+
+    /// Decide whether to follow each redirect with `policy`.
+    ///
+    /// ```
+    /// use wstd::http::RedirectPolicy;
+    ///
+    /// let policy = RedirectPolicy::custom(|attempt| {
+    ///     if attempt.previous().len() > 5 {
+    ///         attempt.error("too many redirects")
+    ///     } else if attempt.url().host() == Some("example.com") {
+    ///         attempt.stop()
+    ///     } else {
+    ///         attempt.follow()
+    ///     }
+    /// });
+    /// ```
+    pub fn custom(policy: impl Fn(Attempt<'_>) -> Action + 'static) -> Self {
+        Self {
+            kind: PolicyKind::Custom(Rc::new(policy)),
+        }
+    }
+
+    pub(crate) fn is_none(&self) -> bool {
+        matches!(self.kind, PolicyKind::None)
+    }
+
+    pub(crate) fn check(&self, attempt: Attempt<'_>) -> Action {
+        match &self.kind {
+            PolicyKind::None => attempt.stop(),
+            PolicyKind::Limited(max) if attempt.previous.len() > *max => {
+                attempt.error(format!("too many redirects (more than {max})"))
+            }
+            PolicyKind::Limited(_) => attempt.follow(),
+            PolicyKind::Custom(policy) => policy(attempt),
+        }
+    }
+}
+
+impl Default for RedirectPolicy {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+impl fmt::Debug for RedirectPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            PolicyKind::None => f.write_str("RedirectPolicy::none()"),
+            PolicyKind::Limited(max) => write!(f, "RedirectPolicy::limited({max})"),
+            PolicyKind::Custom(_) => f.write_str("RedirectPolicy::custom(..)"),
+        }
+    }
+}
+
+/// A redirect which a [`RedirectPolicy`] decides whether to follow.
+#[derive(Debug)]
+pub struct Attempt<'a> {
+    status: StatusCode,
+    next: &'a Uri,
+    previous: &'a [Uri],
+}
+
+impl<'a> Attempt<'a> {
+    /// The status code of the redirect response.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// The URL the redirect leads to.
+    pub fn url(&self) -> &Uri {
+        self.next
+    }
+
+    /// The URLs which were requested so far, starting with the original one.
+    pub fn previous(&self) -> &[Uri] {
+        self.previous
+    }
+
+    /// Follow the redirect.
+    pub fn follow(self) -> Action {
+        Action {
+            kind: ActionKind::Follow,
+        }
+    }
+
+    /// Don't follow the redirect, and return the redirect response.
+    pub fn stop(self) -> Action {
+        Action {
+            kind: ActionKind::Stop,
+        }
+    }
+
+    /// Don't follow the redirect, and fail with `message`.
+    pub fn error(self, message: impl Into<String>) -> Action {
+        Action {
+            kind: ActionKind::Error(message.into()),
+        }
+    }
+}
+
+/// What to do about a redirect, as decided by a [`RedirectPolicy`].
+#[derive(Debug)]
+pub struct Action {
+    kind: ActionKind,
+}
+
+#[derive(Debug)]
+enum ActionKind {
+    Follow,
+    Stop,
+    Error(String),
+}
+
+/// How to follow a redirect.
+#[derive(Debug)]
+pub(crate) struct Redirect {
+    /// The URL to request next.
+    pub(crate) url: Uri,
+    /// Whether the next request is sent with the same method and body.
+    pub(crate) keep_body: bool,
+}
+
+/// Decide whether a response with `status` and `headers` to a `method`
+/// request for `url` should be followed, after `previous` requests.
+///
+/// Returns `None` if the response isn't a redirect, or if `policy` doesn't
+/// follow it.
+pub(crate) fn check(
+    policy: &RedirectPolicy,
+    method: &Method,
+    url: &Uri,
+    status: StatusCode,
+    headers: &HeaderMap,
+    previous: &[Uri],
+) -> Result<Option<Redirect>> {
+    let keep_body = match u16::from(status) {
+        301 | 302 => *method != Method::POST,
+        303 => *method == Method::GET || *method == Method::HEAD,
+        307 | 308 => true,
+        _ => return Ok(None),
+    };
+    let Some(location) = headers.get("location") else {
+        return Ok(None);
+    };
+    let location = location
+        .to_str()
+        .map_err(|_| Error::other("redirect location is not a valid string"))?;
+    let next = resolve(url, location)?;
+
+    let attempt = Attempt {
+        status,
+        next: &next,
+        previous,
+    };
+    match policy.check(attempt).kind {
+        ActionKind::Follow => Ok(Some(Redirect {
+            url: next,
+            keep_body,
+        })),
+        ActionKind::Stop => Ok(None),
+        ActionKind::Error(message) => Err(Error::other(message)),
+    }
+}
+
+/// Whether `a` and `b` have the same scheme, host and port.
+pub(crate) fn same_origin(a: &Uri, b: &Uri) -> bool {
+    a.scheme() == b.scheme() && a.host() == b.host() && port(a) == port(b)
+}
+
+fn port(uri: &Uri) -> Option<u16> {
+    uri.port_u16().or(match uri.scheme_str() {
+        Some("http") => Some(80),
+        Some("https") => Some(443),
+        _ => None,
+    })
+}
+
+/// Resolve a `Location` header against the URL it was received from.
+fn resolve(base: &Uri, location: &str) -> Result<Uri> {
+    let invalid = || Error::other(format!("invalid redirect location: {location}"));
+    // Fragments are never sent to the server.
+    let location = location.split('#').next().unwrap_or_default();
+
+    if let Ok(uri) = location.parse::<Uri>() {
+        if uri.scheme().is_some() {
+            return Ok(uri);
+        }
+    }
+    let scheme = base.scheme_str().unwrap_or("https");
+    let authority = base.authority().ok_or_else(invalid)?;
+    let resolved = if let Some(rest) = location.strip_prefix("//") {
+        format!("{scheme}://{rest}")
+    } else if location.starts_with('/') {
+        format!("{scheme}://{authority}{}", remove_dot_segments(location))
+    } else if location.starts_with('?') {
+        format!("{scheme}://{authority}{}{location}", base.path())
+    } else {
+        let path = base.path();
+        let dir = &path[..path.rfind('/').map_or(0, |i| i + 1)];
+        let dir = if dir.is_empty() { "/" } else { dir };
+        let path = remove_dot_segments(&format!("{dir}{location}"));
+        format!("{scheme}://{authority}{path}")
+    };
+    resolved.parse().map_err(|_| invalid())
+}
+
+/// Remove `.` and `..` segments from the path of `path_and_query`.
+fn remove_dot_segments(path_and_query: &str) -> String {
+    let (path, query) = match path_and_query.find('?') {
+        Some(i) => path_and_query.split_at(i),
+        None => (path_and_query, ""),
+    };
+    let mut segments: Vec<&str> = Vec::new();
+    let mut parts = path.split('/').skip(1).peekable();
+    while let Some(segment) = parts.next() {
+        let last = parts.peek().is_none();
+        match segment {
+            "." | ".." => {
+                if segment == ".." {
+                    segments.pop();
+                }
+                if last {
+                    segments.push("");
+                }
+            }
+            segment => segments.push(segment),
+        }
+    }
+    format!("/{}{query}", segments.join("/"))
+}
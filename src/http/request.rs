@@ -1,19 +1,27 @@
+//! HTTP requests
+
 use crate::io::{empty, Empty};
 
 use super::{
-    fields::header_map_to_wasi, method::to_wasi_method, Body, Error, HeaderMap, IntoBody, Method,
-    Result,
+    client::RequestOptions, fields::header_map_to_wasi, headers, method::to_wasi_method,
+    urlencoded, Body, Error, HeaderMap, HeaderName, HeaderValue, IntoBody, Method, Mime, Result,
+    Uri, Version,
 };
-use http::uri::Uri;
+use crate::time::{Duration, SystemTime};
+use http::Extensions;
 use wasi::http::outgoing_handler::OutgoingRequest;
 use wasi::http::types::Scheme;
 
+pub use http::request::Parts;
+
 /// An HTTP request
 #[derive(Debug)]
 pub struct Request<B: Body> {
     method: Method,
     uri: Uri,
+    version: Version,
     headers: HeaderMap,
+    extensions: Extensions,
     body: B,
 }
 
@@ -24,12 +32,75 @@ impl Request<Empty> {
             body: empty(),
             method,
             uri,
+            version: Version::default(),
             headers: HeaderMap::new(),
+            extensions: Extensions::new(),
         }
     }
+
+    /// Create a new [`Builder`] for a request.
+    pub fn builder() -> Builder {
+        Builder::new()
+    }
 }
 
 impl<B: Body> Request<B> {
+    /// Create a request from its parts and body.
+    pub fn from_parts(parts: Parts, body: B) -> Self {
+        Self {
+            method: parts.method,
+            uri: parts.uri,
+            version: parts.version,
+            headers: parts.headers,
+            extensions: parts.extensions,
+            body,
+        }
+    }
+
+    /// Split the request into its parts and body.
+    pub fn into_parts(self) -> (Parts, B) {
+        let (mut parts, ()) = http::Request::new(()).into_parts();
+        parts.method = self.method;
+        parts.uri = self.uri;
+        parts.version = self.version;
+        parts.headers = self.headers;
+        parts.extensions = self.extensions;
+        (parts, self.body)
+    }
+
+    /// Get the HTTP method of the request
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    /// Mutably get the HTTP method of the request
+    pub fn method_mut(&mut self) -> &mut Method {
+        &mut self.method
+    }
+
+    /// Get the URI of the request
+    pub fn uri(&self) -> &Uri {
+        &self.uri
+    }
+
+    /// Mutably get the URI of the request
+    pub fn uri_mut(&mut self) -> &mut Uri {
+        &mut self.uri
+    }
+
+    /// Get the HTTP version of the request
+    ///
+    /// The version used to send the request is chosen by the wasi-http
+    /// implementation; this is only informational.
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// Mutably get the HTTP version of the request
+    pub fn version_mut(&mut self) -> &mut Version {
+        &mut self.version
+    }
+
     /// Get the HTTP headers from the impl
     pub fn headers(&self) -> &HeaderMap {
         &self.headers
@@ -40,23 +111,234 @@ impl<B: Body> Request<B> {
         &mut self.headers
     }
 
+    /// Parse the `Content-Type` header, if any.
+    pub fn content_type(&self) -> Result<Option<Mime>> {
+        headers::content_type(&self.headers)
+    }
+
+    /// Set the `Content-Type` header.
+    pub fn set_content_type(&mut self, mime: &Mime) {
+        self.headers
+            .insert(http::header::CONTENT_TYPE, HeaderValue::from(mime));
+    }
+
+    /// Parse the `Content-Length` header, if any.
+    ///
+    /// When a request is sent, this header takes precedence over the length
+    /// of its body, and sending fails if the body has a different length.
+    /// Without it, the length of the body is sent when it's known.
+    pub fn content_length(&self) -> Result<Option<u64>> {
+        headers::content_length(&self.headers)
+    }
+
+    /// Parse the `Date` header, if any.
+    pub fn date(&self) -> Result<Option<SystemTime>> {
+        headers::date(&self.headers)
+    }
+
+    /// Get the `User-Agent` header, if any.
+    pub fn user_agent(&self) -> Result<Option<&str>> {
+        headers::user_agent(&self.headers)
+    }
+
+    /// Set the `User-Agent` header.
+    pub fn set_user_agent(&mut self, user_agent: &str) -> Result<()> {
+        let value = HeaderValue::from_str(user_agent)?;
+        self.headers.insert(http::header::USER_AGENT, value);
+        Ok(())
+    }
+
+    /// Parse the media types listed by the `Accept` header, in order.
+    pub fn accept(&self) -> Result<Vec<Mime>> {
+        headers::accept(&self.headers)
+    }
+
+    /// Set the `Accept` header.
+    pub fn set_accept(&mut self, mime: &Mime) {
+        self.headers
+            .insert(http::header::ACCEPT, HeaderValue::from(mime));
+    }
+
+    /// Set the `Authorization` header for HTTP Basic authentication.
+    pub fn set_basic_auth(&mut self, username: &str, password: Option<&str>) {
+        self.headers.insert(
+            http::header::AUTHORIZATION,
+            headers::basic_auth(username, password),
+        );
+    }
+
+    /// Set the `Authorization` header to a bearer token.
+    ///
+    /// Fails if the token isn't a valid header value.
+    pub fn set_bearer_auth(&mut self, token: &str) -> Result<()> {
+        let value = headers::bearer_auth(token)?;
+        self.headers.insert(http::header::AUTHORIZATION, value);
+        Ok(())
+    }
+
+    /// Get the extensions of the request
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    /// Mutably get the extensions of the request
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
+    /// Get whether a [`Client`](super::Client) decompresses the response to
+    /// the request.
+    ///
+    /// This is `true` unless it was turned off with
+    /// [`Request::set_decompress`].
+    pub fn decompress(&self) -> bool {
+        self.extensions
+            .get::<Decompress>()
+            .map_or(true, |decompress| decompress.0)
+    }
+
+    /// Set whether a [`Client`](super::Client) decompresses the response to
+    /// the request.
+    ///
+    /// With the `gzip`, `deflate` or `brotli` features, the client asks for
+    /// a compressed response with an `Accept-Encoding` header, unless the
+    /// request already has one, and decodes the response body as it is
+    /// read. The `Content-Encoding` and `Content-Length` headers of a decoded
+    /// response are removed. Turning this off returns the response as it was
+    /// received.
+    pub fn set_decompress(&mut self, decompress: bool) {
+        self.extensions.insert(Decompress(decompress));
+    }
+
+    /// Set a total timeout for sending the request with a
+    /// [`Client`](super::Client), following redirects, retrying, and reading
+    /// the response body, overriding the one set with
+    /// [`Client::set_timeout`](super::Client::set_timeout).
+    pub fn set_timeout(&mut self, d: impl Into<Duration>) {
+        self.options_mut().timeout = Some(d.into());
+    }
+
+    /// Set the timeout on connecting to the server, overriding the one set
+    /// with [`Client::set_connect_timeout`](super::Client::set_connect_timeout).
+    pub fn set_connect_timeout(&mut self, d: impl Into<Duration>) {
+        self.options_mut().connect_timeout = Some(d.into());
+    }
+
+    /// Set the timeout on receiving the first byte of the response,
+    /// overriding the one set with
+    /// [`Client::set_first_byte_timeout`](super::Client::set_first_byte_timeout).
+    pub fn set_first_byte_timeout(&mut self, d: impl Into<Duration>) {
+        self.options_mut().first_byte_timeout = Some(d.into());
+    }
+
+    /// Set the timeout on receiving subsequent chunks of bytes of the
+    /// response body, overriding the one set with
+    /// [`Client::set_between_bytes_timeout`](super::Client::set_between_bytes_timeout).
+    pub fn set_between_bytes_timeout(&mut self, d: impl Into<Duration>) {
+        self.options_mut().between_bytes_timeout = Some(d.into());
+    }
+
+    fn options_mut(&mut self) -> &mut RequestOptions {
+        if self.extensions.get::<RequestOptions>().is_none() {
+            self.extensions.insert(RequestOptions::default());
+        }
+        self.extensions
+            .get_mut::<RequestOptions>()
+            .expect("inserted above")
+    }
+
+    /// Get the HTTP body of the request
+    pub fn body(&self) -> &B {
+        &self.body
+    }
+
+    /// Mutably get the HTTP body of the request
+    pub fn body_mut(&mut self) -> &mut B {
+        &mut self.body
+    }
+
+    /// Consume the request, returning its body
+    pub fn into_body(self) -> B {
+        self.body
+    }
+
     /// Set an HTTP body.
     pub fn set_body<C: IntoBody>(self, body: C) -> Request<C::IntoBody> {
-        let Self {
-            method,
-            uri,
-            headers,
-            ..
-        } = self;
-        Request {
-            method,
-            uri,
-            headers,
-            body: body.into_body(),
-        }
+        let (parts, _) = self.into_parts();
+        Request::from_parts(parts, body.into_body())
+    }
+
+    /// Set the body to `value` serialized as JSON, and set the `Content-Type`
+    /// header to `application/json`.
+    #[cfg(feature = "json")]
+    pub fn json<T: serde::Serialize + ?Sized>(
+        self,
+        value: &T,
+    ) -> Result<Request<super::body::BoundedBody<Vec<u8>>>> {
+        let body = serde_json::to_vec(value)
+            .map_err(|e| Error::other(e.to_string()).context("serializing JSON body"))?;
+        let mut request = self.set_body(body);
+        request.headers.insert(
+            http::header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        );
+        Ok(request)
+    }
+
+    /// Set the body to `form`, and set the `Content-Type` header to
+    /// `application/x-www-form-urlencoded`.
+    pub fn form(self, form: super::body::Form) -> Request<super::body::BoundedBody<Vec<u8>>> {
+        let mut request = self.set_body(form);
+        request.headers.insert(
+            http::header::CONTENT_TYPE,
+            super::body::Form::content_type(),
+        );
+        request
+    }
+
+    /// Set the body to `form`, and set the `Content-Type` header to
+    /// `multipart/form-data` with the boundary of the form.
+    pub fn multipart(self, form: super::body::Multipart) -> Request<super::body::MultipartBody> {
+        let content_type = form.content_type();
+        let mut request = self.set_body(form);
+        request
+            .headers
+            .insert(http::header::CONTENT_TYPE, content_type);
+        request
+    }
+
+    /// Compress the body with `encoding` as it is sent, and set the
+    /// `Content-Encoding` header.
+    ///
+    /// The compressed length isn't known in advance, so any `Content-Length`
+    /// header is removed, and the body is sent with chunked encoding. The
+    /// server must support the encoding.
+    #[cfg(any(feature = "gzip", feature = "deflate"))]
+    pub fn compress(
+        self,
+        encoding: super::body::ContentEncoding,
+    ) -> Request<super::body::CompressedBody<B>> {
+        let (mut parts, body) = self.into_parts();
+        parts.headers.remove(http::header::CONTENT_LENGTH);
+        parts.headers.insert(
+            http::header::CONTENT_ENCODING,
+            HeaderValue::from_static(encoding.as_str()),
+        );
+        Request::from_parts(parts, super::body::CompressedBody::new(body, encoding))
     }
 
-    pub(crate) fn into_outgoing(self) -> Result<(OutgoingRequest, B)> {
+    pub(crate) fn into_outgoing(mut self) -> Result<(OutgoingRequest, B)> {
+        // Send the length of the body when it's known, rather than chunking
+        // it. Methods which don't usually have a body don't get an empty one.
+        if let Some(len) = self.body.len() {
+            let has_body =
+                len > 0 || matches!(self.method, Method::POST | Method::PUT | Method::PATCH);
+            if has_body && !self.headers.contains_key(http::header::CONTENT_LENGTH) {
+                self.headers
+                    .insert(http::header::CONTENT_LENGTH, HeaderValue::from(len));
+            }
+        }
+
         let wasi_req = OutgoingRequest::new(header_map_to_wasi(&self.headers)?);
 
         // Set the HTTP method
@@ -94,3 +376,192 @@ impl<B: Body> Request<B> {
         Ok((wasi_req, self.body))
     }
 }
+
+impl<T: IntoBody> From<http::Request<T>> for Request<T::IntoBody> {
+    fn from(request: http::Request<T>) -> Self {
+        let (parts, body) = request.into_parts();
+        Request::from_parts(parts, body.into_body())
+    }
+}
+
+impl<B: Body> From<Request<B>> for http::Request<B> {
+    fn from(request: Request<B>) -> Self {
+        let (parts, body) = request.into_parts();
+        http::Request::from_parts(parts, body)
+    }
+}
+
+/// Whether the response to a request is decompressed, kept in its
+/// extensions.
+#[derive(Debug, Clone, Copy)]
+struct Decompress(bool);
+
+/// A builder for [`Request`]s.
+///
+/// Errors from invalid arguments are reported when the request is built.
+///
+/// ```no_run
+/// use wstd::http::{Method, Request};
+///
+/// # fn run() -> wstd::http::Result<()> {
+/// let request = Request::builder()
+///     .method(Method::POST)
+///     .uri("https://example.com/search")
+///     .query([("q", "wasi components")])
+///     .header("content-type", "text/plain")
+///     .body("hello")?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+#[must_use]
+pub struct Builder {
+    inner: Result<Parts>,
+    query: String,
+}
+
+impl Builder {
+    /// Create a new `Builder` for a `GET` request to `/`.
+    pub fn new() -> Self {
+        let (parts, ()) = http::Request::new(()).into_parts();
+        Self {
+            inner: Ok(parts),
+            query: String::new(),
+        }
+    }
+
+    /// Set the HTTP method of the request.
+    pub fn method<M>(self, method: M) -> Self
+    where
+        M: TryInto<Method>,
+        M::Error: Into<http::Error>,
+    {
+        self.and_then(|mut parts| {
+            parts.method = method.try_into().map_err(Into::into)?;
+            Ok(parts)
+        })
+    }
+
+    /// Set the URI of the request.
+    pub fn uri<U>(self, uri: U) -> Self
+    where
+        U: TryInto<Uri>,
+        U::Error: Into<http::Error>,
+    {
+        self.and_then(|mut parts| {
+            parts.uri = uri.try_into().map_err(Into::into)?;
+            Ok(parts)
+        })
+    }
+
+    /// Append `name=value` pairs to the query string of the URI.
+    ///
+    /// Names and values are percent-encoded.
+    pub fn query<I, K, V>(mut self, pairs: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        for (name, value) in pairs {
+            urlencoded::append_pair(&mut self.query, name.as_ref(), value.as_ref());
+        }
+        self
+    }
+
+    /// Set the HTTP version of the request.
+    pub fn version(self, version: Version) -> Self {
+        self.and_then(|mut parts| {
+            parts.version = version;
+            Ok(parts)
+        })
+    }
+
+    /// Append a header to the request.
+    pub fn header<K, V>(self, name: K, value: V) -> Self
+    where
+        K: TryInto<HeaderName>,
+        K::Error: Into<http::Error>,
+        V: TryInto<HeaderValue>,
+        V::Error: Into<http::Error>,
+    {
+        self.and_then(|mut parts| {
+            let name = name.try_into().map_err(Into::into)?;
+            let value = value.try_into().map_err(Into::into)?;
+            parts.headers.append(name, value);
+            Ok(parts)
+        })
+    }
+
+    /// Set the `Authorization` header for HTTP Basic authentication.
+    pub fn basic_auth(self, username: &str, password: Option<&str>) -> Self {
+        let value = headers::basic_auth(username, password);
+        self.and_then(|mut parts| {
+            parts.headers.insert(http::header::AUTHORIZATION, value);
+            Ok(parts)
+        })
+    }
+
+    /// Set the `Authorization` header to a bearer token.
+    pub fn bearer_auth(mut self, token: &str) -> Self {
+        match headers::bearer_auth(token) {
+            Ok(value) => self.and_then(|mut parts| {
+                parts.headers.insert(http::header::AUTHORIZATION, value);
+                Ok(parts)
+            }),
+            Err(err) => {
+                self.inner = self.inner.and(Err(err));
+                self
+            }
+        }
+    }
+
+    /// Get the headers of the request being built, or `None` if an error
+    /// occurred.
+    pub fn headers_mut(&mut self) -> Option<&mut HeaderMap> {
+        self.inner.as_mut().ok().map(|parts| &mut parts.headers)
+    }
+
+    /// Build the request with the given body.
+    pub fn body<T: IntoBody>(self, body: T) -> Result<Request<T::IntoBody>> {
+        let mut parts = self.inner?;
+        if !self.query.is_empty() {
+            parts.uri = append_query(parts.uri, &self.query)?;
+        }
+        Ok(Request::from_parts(parts, body.into_body()))
+    }
+
+    /// Build the request without a body.
+    pub fn build(self) -> Result<Request<Empty>> {
+        self.body(empty())
+    }
+
+    fn and_then(self, f: impl FnOnce(Parts) -> std::result::Result<Parts, http::Error>) -> Self {
+        Self {
+            inner: self.inner.and_then(|parts| f(parts).map_err(Error::from)),
+            query: self.query,
+        }
+    }
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Append an already-encoded `query` to the query string of `uri`.
+fn append_query(uri: Uri, query: &str) -> Result<Uri> {
+    let mut parts = uri.into_parts();
+    let path_and_query = match &parts.path_and_query {
+        Some(pq) => match pq.query() {
+            Some(existing) if !existing.is_empty() => {
+                format!("{}?{existing}&{query}", pq.path())
+            }
+            _ => format!("{}?{query}", pq.path()),
+        },
+        None => format!("/?{query}"),
+    };
+    parts.path_and_query = Some(path_and_query.parse().map_err(http::Error::from)?);
+    Ok(Uri::from_parts(parts).map_err(http::Error::from)?)
+}
@@ -1,9 +1,25 @@
-use wasi::http::types::{IncomingBody as WasiIncomingBody, IncomingResponse};
+//! HTTP responses
+
+use wasi::http::types::{
+    http_error_code, ErrorCode as WasiHttpErrorCode, FutureTrailers,
+    IncomingBody as WasiIncomingBody, IncomingResponse, OutgoingResponse,
+};
 use wasi::io::streams::{InputStream, StreamError};
 
-use super::{fields::header_map_from_wasi, Body, Error, HeaderMap, Result, StatusCode};
-use crate::io::AsyncRead;
+use super::{
+    body::{stream_error, DynRead},
+    charset,
+    decoder::Decoder,
+    error::ErrorVariant,
+    fields::{header_map_from_wasi, header_map_to_wasi},
+    headers, Body, ETag, Error, HeaderMap, HeaderName, HeaderValue, IntoBody, Method, Mime, Result,
+    StatusCode, Uri, Version,
+};
+use crate::future::FutureExt;
+use crate::io::{empty, AsyncRead, Empty};
 use crate::runtime::Reactor;
+use crate::time::{Instant, SystemTime};
+use http::Extensions;
 
 /// Stream 2kb chunks at a time
 const CHUNK_SIZE: u64 = 2048;
@@ -13,70 +29,171 @@ const CHUNK_SIZE: u64 = 2048;
 pub struct Response<B: Body> {
     headers: HeaderMap,
     status: StatusCode,
+    version: Version,
+    extensions: Extensions,
     body: B,
 }
 
+/// The parts of a [`Response`] other than its body.
+///
+/// This mirrors `http::response::Parts`, but carries a wstd [`StatusCode`],
+/// which can represent any status code sent over wasi-http.
 #[derive(Debug)]
-enum BodyKind {
+#[non_exhaustive]
+pub struct Parts {
+    /// The HTTP status code
+    pub status: StatusCode,
+    /// The HTTP version
+    pub version: Version,
+    /// The HTTP headers
+    pub headers: HeaderMap,
+    /// The extensions of the response
+    pub extensions: Extensions,
+}
+
+impl Parts {
+    pub(crate) fn new(status: StatusCode) -> Self {
+        Self {
+            status,
+            version: Version::default(),
+            headers: HeaderMap::new(),
+            extensions: Extensions::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum BodyKind {
     Fixed(u64),
     Chunked,
 }
 
 impl BodyKind {
-    fn from_headers(headers: &HeaderMap) -> Result<BodyKind> {
-        if let Some(value) = headers.get("content-length") {
-            let content_length = std::str::from_utf8(value.as_ref())
-                .unwrap()
-                .parse::<u64>()
-                .map_err(|_| {
-                    Error::other("incoming content-length should be a u64; violates HTTP/1.1")
-                })?;
-            Ok(BodyKind::Fixed(content_length))
-        } else if headers.contains_key("transfer-encoding") {
-            Ok(BodyKind::Chunked)
-        } else {
-            Ok(BodyKind::Chunked)
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Result<BodyKind> {
+        match headers::content_length(headers) {
+            Ok(Some(content_length)) => Ok(BodyKind::Fixed(content_length)),
+            Ok(None) => Ok(BodyKind::Chunked),
+            Err(_) => Err(Error::other(
+                "incoming content-length should be a u64; violates HTTP/1.1",
+            )),
+        }
+    }
+
+    /// The kind of body of a response with `status` to a `method` request.
+    ///
+    /// Responses to `HEAD` requests, and 1xx, 204 and 304 responses, never
+    /// have a body, even when they have a `Content-Length`.
+    pub(crate) fn from_response(
+        method: &Method,
+        status: StatusCode,
+        headers: &HeaderMap,
+    ) -> Result<BodyKind> {
+        if *method == Method::HEAD || matches!(u16::from(status), 100..=199 | 204 | 304) {
+            return Ok(BodyKind::Fixed(0));
         }
+        BodyKind::from_headers(headers)
     }
 }
 
 impl Response<IncomingBody> {
-    pub(crate) fn try_from_incoming_response(incoming: IncomingResponse) -> Result<Self> {
+    pub(crate) fn try_from_incoming_response(
+        incoming: IncomingResponse,
+        method: &Method,
+    ) -> Result<Self> {
         let headers: HeaderMap = header_map_from_wasi(incoming.headers())?;
         let status = incoming.status().into();
 
-        let kind = BodyKind::from_headers(&headers)?;
-        // `body_stream` is a child of `incoming_body` which means we cannot
-        // drop the parent before we drop the child
+        let kind = BodyKind::from_response(method, status, &headers)?;
         let incoming_body = incoming
             .consume()
             .expect("cannot call `consume` twice on incoming response");
-        let body_stream = incoming_body
-            .stream()
-            .expect("cannot call `stream` twice on an incoming body");
+        let content_type = headers.get("content-type").cloned();
+        let body = IncomingBody::new(kind, content_type, incoming_body);
 
-        let body = IncomingBody {
-            kind,
-            buf_offset: 0,
-            buf: None,
-            body_stream,
-            _incoming_body: incoming_body,
+        let mut parts = Parts::new(status);
+        parts.headers = headers;
+        Ok(Self::from_parts(parts, body))
+    }
+}
+
+impl Response<IncomingBody> {
+    /// Decode the body according to its `Content-Encoding`, if it is
+    /// supported, and remove the headers which describe the encoded body.
+    pub(crate) fn decompress(&mut self) {
+        if matches!(self.body.kind, BodyKind::Fixed(0)) {
+            return;
+        }
+        let Some(encoding) = self
+            .headers
+            .get(http::header::CONTENT_ENCODING)
+            .and_then(|value| value.to_str().ok())
+        else {
+            return;
+        };
+        let Some(decoder) = Decoder::new(encoding.trim()) else {
+            return;
         };
+        self.headers.remove(http::header::CONTENT_ENCODING);
+        self.headers.remove(http::header::CONTENT_LENGTH);
+        self.body.set_decoder(decoder);
+    }
+}
 
-        Ok(Self {
-            headers,
-            body,
-            status,
-        })
+impl Response<Empty> {
+    /// Create a new HTTP response to send off to the peer.
+    pub fn new(status: StatusCode) -> Self {
+        Self::from_parts(Parts::new(status), empty())
+    }
+
+    /// Create a new [`Builder`] for a response.
+    pub fn builder() -> Builder {
+        Builder::new()
     }
 }
 
 impl<B: Body> Response<B> {
+    /// Create a response from its parts and body.
+    pub fn from_parts(parts: Parts, body: B) -> Self {
+        Self {
+            headers: parts.headers,
+            status: parts.status,
+            version: parts.version,
+            extensions: parts.extensions,
+            body,
+        }
+    }
+
+    /// Split the response into its parts and body.
+    pub fn into_parts(self) -> (Parts, B) {
+        let parts = Parts {
+            status: self.status,
+            version: self.version,
+            headers: self.headers,
+            extensions: self.extensions,
+        };
+        (parts, self.body)
+    }
+
     // Get the HTTP status code
     pub fn status_code(&self) -> StatusCode {
         self.status
     }
 
+    /// Mutably get the HTTP status code
+    pub fn status_code_mut(&mut self) -> &mut StatusCode {
+        &mut self.status
+    }
+
+    /// Get the HTTP version of the response
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// Mutably get the HTTP version of the response
+    pub fn version_mut(&mut self) -> &mut Version {
+        &mut self.version
+    }
+
     /// Get the HTTP headers from the impl
     pub fn headers(&self) -> &HeaderMap {
         &self.headers
@@ -87,68 +204,635 @@ impl<B: Body> Response<B> {
         &mut self.headers
     }
 
+    /// Parse the `Content-Type` header, if any.
+    pub fn content_type(&self) -> Result<Option<Mime>> {
+        headers::content_type(&self.headers)
+    }
+
+    /// Set the `Content-Type` header.
+    pub fn set_content_type(&mut self, mime: &Mime) {
+        self.headers
+            .insert(http::header::CONTENT_TYPE, HeaderValue::from(mime));
+    }
+
+    /// Parse the `Content-Length` header, if any.
+    ///
+    /// A [`Client`](super::Client) removes this header from responses which
+    /// it decompresses.
+    pub fn content_length(&self) -> Result<Option<u64>> {
+        headers::content_length(&self.headers)
+    }
+
+    /// Parse the `Date` header, if any.
+    pub fn date(&self) -> Result<Option<SystemTime>> {
+        headers::date(&self.headers)
+    }
+
+    /// Set the `Date` header.
+    pub fn set_date(&mut self, date: SystemTime) {
+        headers::set_date(&mut self.headers, date);
+    }
+
+    /// Parse the `ETag` header, if any.
+    pub fn etag(&self) -> Result<Option<ETag>> {
+        headers::etag(&self.headers)
+    }
+
+    /// Set the `ETag` header.
+    pub fn set_etag(&mut self, etag: &ETag) {
+        self.headers
+            .insert(http::header::ETAG, HeaderValue::from(etag));
+    }
+
+    /// Get the extensions of the response
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    /// Mutably get the extensions of the response
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
+    /// Get the URL the response was received from.
+    ///
+    /// This is set on responses received by a [`Client`](super::Client), and
+    /// is the URL of the last request sent when redirects were followed.
+    pub fn url(&self) -> Option<&Uri> {
+        self.extensions.get::<ResponseUrl>().map(|url| &url.0)
+    }
+
+    pub(crate) fn set_url(&mut self, url: Uri) {
+        self.extensions.insert(ResponseUrl(url));
+    }
+
     pub fn body(&mut self) -> &mut B {
         &mut self.body
     }
+
+    /// Consume the response, returning its body
+    pub fn into_body(self) -> B {
+        self.body
+    }
+
+    /// Set an HTTP body.
+    pub fn set_body<C: IntoBody>(self, body: C) -> Response<C::IntoBody> {
+        self.map_body(|_| body)
+    }
+
+    /// Replace the body with the result of applying `f` to it.
+    pub fn map_body<C, F>(self, f: F) -> Response<C::IntoBody>
+    where
+        C: IntoBody,
+        F: FnOnce(B) -> C,
+    {
+        let (parts, body) = self.into_parts();
+        Response::from_parts(parts, f(body).into_body())
+    }
+
+    pub(crate) fn into_outgoing(self) -> Result<(OutgoingResponse, B)> {
+        let wasi_resp = OutgoingResponse::new(header_map_to_wasi(&self.headers)?);
+
+        // Set the HTTP status code
+        let status = self.status.into();
+        wasi_resp
+            .set_status_code(status)
+            .map_err(|()| Error::other(format!("status code rejected by wasi-http: {status}")))?;
+
+        // All done; response is ready for send-off
+        Ok((wasi_resp, self.body))
+    }
+}
+
+/// The URL a response was received from, kept in its extensions.
+#[derive(Debug, Clone)]
+struct ResponseUrl(Uri);
+
+impl<T: IntoBody> From<http::Response<T>> for Response<T::IntoBody> {
+    fn from(response: http::Response<T>) -> Self {
+        let (parts, body) = response.into_parts();
+        let parts = Parts {
+            status: parts.status.into(),
+            version: parts.version,
+            headers: parts.headers,
+            extensions: parts.extensions,
+        };
+        Response::from_parts(parts, body.into_body())
+    }
+}
+
+/// Fails if the status code is outside of the range `http` supports.
+impl<B: Body> TryFrom<Response<B>> for http::Response<B> {
+    type Error = Error;
+
+    fn try_from(response: Response<B>) -> Result<Self> {
+        let (parts, body) = response.into_parts();
+        let mut response = http::Response::new(body);
+        *response.status_mut() = parts.status.try_into().map_err(http::Error::from)?;
+        *response.version_mut() = parts.version;
+        *response.headers_mut() = parts.headers;
+        *response.extensions_mut() = parts.extensions;
+        Ok(response)
+    }
+}
+
+/// A builder for [`Response`]s.
+///
+/// Errors from invalid arguments are reported when the response is built.
+///
+/// ```
+/// use wstd::http::{Response, StatusCode};
+///
+/// # fn run() -> wstd::http::Result<()> {
+/// let response = Response::builder()
+///     .status(StatusCode::Created)
+///     .header("content-type", "text/plain")
+///     .body("created\n")?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+#[must_use]
+pub struct Builder {
+    inner: Result<Parts>,
+}
+
+impl Builder {
+    /// Create a new `Builder` for a `200 Ok` response.
+    pub fn new() -> Self {
+        Self {
+            inner: Ok(Parts::new(StatusCode::Ok)),
+        }
+    }
+
+    /// Set the HTTP status code of the response.
+    pub fn status(self, status: impl Into<StatusCode>) -> Self {
+        self.and_then(|mut parts| {
+            parts.status = status.into();
+            Ok(parts)
+        })
+    }
+
+    /// Set the HTTP version of the response.
+    pub fn version(self, version: Version) -> Self {
+        self.and_then(|mut parts| {
+            parts.version = version;
+            Ok(parts)
+        })
+    }
+
+    /// Append a header to the response.
+    pub fn header<K, V>(self, name: K, value: V) -> Self
+    where
+        K: TryInto<HeaderName>,
+        K::Error: Into<http::Error>,
+        V: TryInto<HeaderValue>,
+        V::Error: Into<http::Error>,
+    {
+        self.and_then(|mut parts| {
+            let name = name.try_into().map_err(Into::into)?;
+            let value = value.try_into().map_err(Into::into)?;
+            parts.headers.append(name, value);
+            Ok(parts)
+        })
+    }
+
+    /// Get the headers of the response being built, or `None` if an error
+    /// occurred.
+    pub fn headers_mut(&mut self) -> Option<&mut HeaderMap> {
+        self.inner.as_mut().ok().map(|parts| &mut parts.headers)
+    }
+
+    /// Build the response with the given body.
+    pub fn body<T: IntoBody>(self, body: T) -> Result<Response<T::IntoBody>> {
+        Ok(Response::from_parts(self.inner?, body.into_body()))
+    }
+
+    /// Build the response without a body.
+    pub fn build(self) -> Result<Response<Empty>> {
+        self.body(empty())
+    }
+
+    fn and_then(self, f: impl FnOnce(Parts) -> std::result::Result<Parts, http::Error>) -> Self {
+        Self {
+            inner: self.inner.and_then(|parts| f(parts).map_err(Error::from)),
+        }
+    }
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 /// An incoming HTTP body
 #[derive(Debug)]
 pub struct IncomingBody {
     kind: BodyKind,
+    content_type: Option<HeaderValue>,
+    max_size: Option<u64>,
+    decoder: Option<Decoder>,
     buf: Option<Vec<u8>>,
     // How many bytes have we already read from the buf?
     buf_offset: usize,
+    // How many bytes have we received from the body stream?
+    received: u64,
+    // Bytes which were already read, and are returned before the rest of
+    // the body.
+    read_ahead: Vec<u8>,
+    read_ahead_offset: usize,
+    // When reading the body fails, because the request timed out.
+    deadline: Option<Instant>,
+    // Where the body is read from, if it wasn't received by wasi-http.
+    reader: Option<BodyReader>,
 
     // IMPORTANT: the order of these fields here matters. `body_stream` must
-    // be dropped before `_incoming_body`.
-    body_stream: InputStream,
-    _incoming_body: WasiIncomingBody,
+    // be dropped before the incoming body in `trailers`.
+    body_stream: Option<InputStream>,
+    trailers: Trailers,
 }
 
-impl AsyncRead for IncomingBody {
-    async fn read(&mut self, out_buf: &mut [u8]) -> crate::io::Result<usize> {
-        let buf = match &mut self.buf {
-            Some(ref mut buf) => buf,
-            None => {
-                // Wait for an event to be ready
-                let pollable = self.body_stream.subscribe();
-                Reactor::current().wait_for(pollable).await;
-
-                // Read the bytes from the body stream
-                let buf = match self.body_stream.read(CHUNK_SIZE) {
-                    Ok(buf) => buf,
-                    Err(StreamError::Closed) => return Ok(0),
-                    Err(StreamError::LastOperationFailed(err)) => {
-                        return Err(std::io::Error::other(format!(
-                            "last operation failed: {}",
-                            err.to_debug_string()
-                        )))
-                    }
-                };
-                self.buf.insert(buf)
+#[derive(Debug)]
+enum Trailers {
+    /// The body hasn't been finished yet.
+    Body(WasiIncomingBody),
+    /// The body was finished, and we're waiting for the trailers.
+    Receiving(FutureTrailers),
+    /// The trailers were received.
+    Received(Option<HeaderMap>),
+}
+
+impl IncomingBody {
+    /// The default maximum size of a body read with [`IncomingBody::bytes`],
+    /// [`IncomingBody::text`] or `IncomingBody::json`: 16 MiB.
+    pub const DEFAULT_MAX_SIZE: u64 = 16 * 1024 * 1024;
+
+    pub(crate) fn new(
+        kind: BodyKind,
+        content_type: Option<HeaderValue>,
+        incoming_body: WasiIncomingBody,
+    ) -> Self {
+        // `body_stream` is a child of `incoming_body` which means we cannot
+        // drop the parent before we drop the child
+        let body_stream = incoming_body
+            .stream()
+            .expect("cannot call `stream` twice on an incoming body");
+        Self {
+            kind,
+            content_type,
+            max_size: Some(Self::DEFAULT_MAX_SIZE),
+            decoder: None,
+            buf_offset: 0,
+            buf: None,
+            received: 0,
+            read_ahead: Vec::new(),
+            read_ahead_offset: 0,
+            deadline: None,
+            reader: None,
+            body_stream: Some(body_stream),
+            trailers: Trailers::Body(incoming_body),
+        }
+    }
+
+    /// Create a body which was received earlier, such as a cached body.
+    pub(crate) fn from_bytes(
+        bytes: Vec<u8>,
+        content_type: Option<HeaderValue>,
+        trailers: Option<HeaderMap>,
+    ) -> Self {
+        Self {
+            kind: BodyKind::Fixed(bytes.len() as u64),
+            content_type,
+            max_size: Some(Self::DEFAULT_MAX_SIZE),
+            decoder: None,
+            buf_offset: 0,
+            buf: None,
+            received: bytes.len() as u64,
+            read_ahead: bytes,
+            read_ahead_offset: 0,
+            deadline: None,
+            reader: None,
+            body_stream: None,
+            trailers: Trailers::Received(trailers),
+        }
+    }
+
+    /// Create a body which is read from `reader`, such as the body of a
+    /// response produced by a [`Transport`](super::transport::Transport).
+    ///
+    /// [`IncomingBody::text`] decodes the body as UTF-8.
+    pub fn from_reader(reader: impl AsyncRead + 'static) -> Self {
+        Self::from_dyn_reader(Box::new(reader), BodyKind::Chunked, None, None)
+    }
+
+    /// Create a body which is read from `reader`, followed by `trailers`.
+    pub(crate) fn from_dyn_reader(
+        reader: Box<dyn DynRead>,
+        kind: BodyKind,
+        content_type: Option<HeaderValue>,
+        trailers: Option<HeaderMap>,
+    ) -> Self {
+        let mut body = Self::from_bytes(Vec::new(), content_type, trailers);
+        body.kind = kind;
+        body.received = 0;
+        body.reader = Some(BodyReader(reader));
+        body
+    }
+
+    /// Get the maximum size of a body read with [`IncomingBody::bytes`],
+    /// [`IncomingBody::text`] or `IncomingBody::json`, if any.
+    pub fn max_size(&self) -> Option<u64> {
+        self.max_size
+    }
+
+    /// Set the maximum size of a body read with [`IncomingBody::bytes`],
+    /// [`IncomingBody::text`] or `IncomingBody::json`.
+    ///
+    /// `None` removes the limit. This doesn't limit reading the body through
+    /// [`AsyncRead`].
+    pub fn set_max_size(&mut self, max_size: Option<u64>) {
+        self.max_size = max_size;
+    }
+
+    /// Read the rest of the body into a byte vector.
+    ///
+    /// Fails without reading the body if it is known to be larger than
+    /// [`IncomingBody::max_size`], or once more than that has been read.
+    pub async fn bytes(&mut self) -> Result<Vec<u8>> {
+        let max_size = self.max_size.unwrap_or(u64::MAX);
+        let too_large = || Error::other(format!("body is larger than {max_size} bytes"));
+        let mut bytes = Vec::new();
+        if let (BodyKind::Fixed(len), None) = (&self.kind, &self.decoder) {
+            let len = *len;
+            if len > max_size {
+                return Err(too_large());
             }
-        };
+            bytes.reserve(len.min(Self::DEFAULT_MAX_SIZE) as usize);
+        }
+        let mut buf = [0; CHUNK_SIZE as usize];
+        loop {
+            let n = self
+                .read(&mut buf)
+                .await
+                .map_err(|e| Error::from_io(e).context("reading body"))?;
+            if n == 0 {
+                return Ok(bytes);
+            }
+            if (bytes.len() + n) as u64 > max_size {
+                return Err(too_large());
+            }
+            bytes.extend_from_slice(&buf[..n]);
+        }
+    }
+
+    /// Read the rest of the body as text.
+    ///
+    /// The body is decoded according to the `charset` of its `Content-Type`,
+    /// which defaults to UTF-8. UTF-8, US-ASCII, ISO-8859-1 and UTF-16 are
+    /// supported; invalid sequences are replaced with `U+FFFD`.
+    pub async fn text(&mut self) -> Result<String> {
+        let bytes = self.bytes().await?;
+        let charset = self
+            .content_type
+            .as_ref()
+            .and_then(charset::from_content_type);
+        charset::decode(bytes, charset)
+    }
 
-        // copy bytes
-        let len = (buf.len() - self.buf_offset).min(out_buf.len());
-        let max = self.buf_offset + len;
-        let slice = &buf[self.buf_offset..max];
-        out_buf[0..len].copy_from_slice(slice);
-        self.buf_offset += len;
+    /// Read the rest of the body, and deserialize it from JSON.
+    #[cfg(feature = "json")]
+    pub async fn json<T: serde::de::DeserializeOwned>(&mut self) -> Result<T> {
+        let bytes = self.bytes().await?;
+        serde_json::from_slice(&bytes)
+            .map_err(|e| Error::other(e.to_string()).context("deserializing JSON body"))
+    }
 
-        // reset the local slice if necessary
-        if self.buf_offset == buf.len() {
+    /// Get the trailers which were sent after the body, if any.
+    ///
+    /// Trailers are only received once the body has ended, so this should be
+    /// called after reading the body to the end. Any part of the body which
+    /// was not read yet is discarded.
+    pub async fn trailers(&mut self) -> Result<Option<HeaderMap>> {
+        self.read_ahead = Vec::new();
+        self.read_ahead_offset = 0;
+        self.reader = None;
+        if let Trailers::Body(_) = self.trailers {
+            // The stream must be dropped before its parent body is finished.
+            self.body_stream = None;
             self.buf = None;
             self.buf_offset = 0;
+            let Trailers::Body(body) =
+                std::mem::replace(&mut self.trailers, Trailers::Received(None))
+            else {
+                unreachable!()
+            };
+            self.trailers = Trailers::Receiving(WasiIncomingBody::finish(body));
         }
+        if let Trailers::Receiving(future) = &self.trailers {
+            Reactor::current().wait_for(future.subscribe()).await;
+            let result = future
+                .get()
+                .ok_or_else(|| Error::other("trailers were not ready"))
+                .and_then(|r| r.map_err(|()| Error::other("trailers were already taken")))
+                .and_then(|r| r.map_err(|e| Error::from(e).context("receiving trailers")))
+                .and_then(|fields| fields.map(header_map_from_wasi).transpose());
+            match result {
+                Ok(trailers) => self.trailers = Trailers::Received(trailers),
+                Err(err) => {
+                    // The future can't be polled again, so don't leave it
+                    // behind for the next call.
+                    self.trailers = Trailers::Received(None);
+                    return Err(err);
+                }
+            }
+        }
+        match &self.trailers {
+            Trailers::Received(trailers) => Ok(trailers.clone()),
+            _ => unreachable!("trailers were received above"),
+        }
+    }
+}
+
+impl IncomingBody {
+    /// Decode the body with `decoder` as it is read.
+    pub(crate) fn set_decoder(&mut self, decoder: Decoder) {
+        self.decoder = Some(decoder);
+    }
+
+    /// Fail reads with [`ErrorVariant::Timeout`] from `deadline` on.
+    pub(crate) fn set_deadline(&mut self, deadline: Instant) {
+        self.deadline = Some(deadline);
+    }
+
+    /// Return `bytes`, which were read from the start of the body, from the
+    /// next reads again.
+    pub(crate) fn unread(&mut self, bytes: Vec<u8>) {
+        self.read_ahead = bytes;
+        self.read_ahead_offset = 0;
+    }
+
+    /// Wait until there are received bytes in `buf`, unless the body has
+    /// ended.
+    async fn fill_buf(&mut self) -> std::io::Result<()> {
+        while self.buf.is_none() {
+            if let Some(BodyReader(reader)) = &mut self.reader {
+                let mut buf = vec![0; CHUNK_SIZE as usize];
+                let n = reader.read_dyn(&mut buf).await?;
+                if n == 0 {
+                    self.reader = None;
+                    self.stream_ended(StreamError::Closed)?;
+                    return Ok(());
+                }
+                buf.truncate(n);
+                self.received += n as u64;
+                self.buf = Some(buf);
+                continue;
+            }
+            let Some(body_stream) = &self.body_stream else {
+                // The body was finished by reading its trailers.
+                return Ok(());
+            };
 
-        Ok(len)
+            // Wait for an event to be ready
+            let pollable = body_stream.subscribe();
+            Reactor::current().wait_for(pollable).await;
+
+            // Read the bytes from the body stream. A stream may be ready
+            // without any bytes to read, which doesn't mean it has ended.
+            match body_stream.read(CHUNK_SIZE) {
+                Ok(buf) if buf.is_empty() => continue,
+                Ok(buf) => {
+                    self.received += buf.len() as u64;
+                    self.buf = Some(buf);
+                }
+                Err(err) => {
+                    self.stream_ended(err)?;
+                    return Ok(());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Like [`IncomingBody::fill_buf`], but fail once the deadline expired.
+    async fn fill_buf_before_deadline(&mut self) -> std::io::Result<()> {
+        match self.deadline {
+            Some(deadline) => self
+                .fill_buf()
+                .timeout(deadline)
+                .await
+                .map_err(|_| timed_out())?,
+            None => self.fill_buf().await,
+        }
+    }
+
+    /// Mark `n` bytes of `buf` as read.
+    fn consume(&mut self, n: usize) {
+        self.buf_offset += n;
+        if self
+            .buf
+            .as_ref()
+            .is_some_and(|buf| self.buf_offset >= buf.len())
+        {
+            self.buf = None;
+            self.buf_offset = 0;
+        }
+    }
+
+    /// Handle the body stream ending, either because it was closed or
+    /// because of an error.
+    ///
+    /// The host reports a body which ends before its `Content-Length` either
+    /// as the end of the stream or as a protocol error; both are reported as
+    /// [`ErrorVariant::BodyTruncated`].
+    fn stream_ended(&self, err: StreamError) -> std::io::Result<usize> {
+        let ended_early = match &err {
+            StreamError::Closed => true,
+            StreamError::LastOperationFailed(err) => matches!(
+                http_error_code(err),
+                Some(WasiHttpErrorCode::HttpProtocolError)
+            ),
+        };
+        match self.kind {
+            BodyKind::Fixed(expected) if ended_early && self.received < expected => {
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    Error::from(ErrorVariant::BodyTruncated {
+                        expected,
+                        received: self.received,
+                    }),
+                ))
+            }
+            _ if matches!(err, StreamError::Closed) => Ok(0),
+            _ => Err(stream_error(err)),
+        }
     }
 }
 
+impl AsyncRead for IncomingBody {
+    async fn read(&mut self, out_buf: &mut [u8]) -> crate::io::Result<usize> {
+        if out_buf.is_empty() {
+            return Ok(0);
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Err(timed_out());
+        }
+        if self.read_ahead_offset < self.read_ahead.len() {
+            let read_ahead = &self.read_ahead[self.read_ahead_offset..];
+            let len = read_ahead.len().min(out_buf.len());
+            out_buf[..len].copy_from_slice(&read_ahead[..len]);
+            self.read_ahead_offset += len;
+            return Ok(len);
+        }
+        loop {
+            self.fill_buf_before_deadline().await?;
+            let input = match &self.buf {
+                Some(buf) => &buf[self.buf_offset..],
+                None => &[],
+            };
+            let Some(decoder) = &mut self.decoder else {
+                let len = input.len().min(out_buf.len());
+                out_buf[..len].copy_from_slice(&input[..len]);
+                self.consume(len);
+                return Ok(len);
+            };
+            let eof = input.is_empty();
+            let (consumed, produced) = decoder.decode(input, out_buf, eof)?;
+            let done = decoder.is_done();
+            self.consume(consumed);
+            if produced > 0 || done {
+                return Ok(produced);
+            }
+        }
+    }
+}
+
+/// Where an [`IncomingBody`] which wasn't received by wasi-http is read from.
+struct BodyReader(Box<dyn DynRead>);
+
+impl std::fmt::Debug for BodyReader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BodyReader").finish_non_exhaustive()
+    }
+}
+
+/// The error for reading a body after its request timed out.
+fn timed_out() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::TimedOut,
+        Error::from(ErrorVariant::Timeout),
+    )
+}
+
 impl Body for IncomingBody {
     fn len(&self) -> Option<usize> {
+        if self.decoder.is_some() {
+            return None;
+        }
         match self.kind {
             BodyKind::Fixed(l) => {
                 if l > (usize::MAX as u64) {
@@ -160,4 +844,8 @@ impl Body for IncomingBody {
             BodyKind::Chunked => None,
         }
     }
+
+    async fn trailers(&mut self) -> Result<Option<HeaderMap>> {
+        IncomingBody::trailers(self).await
+    }
 }
@@ -0,0 +1,174 @@
+//! Retrying failed requests
+//!
+//! By default a [`Client`] sends each request once. A [`RetryPolicy`] makes it
+//! retry requests which failed for reasons which are likely to be transient:
+//!
+//! ```no_run
+//! use wstd::http::{Client, RetryPolicy};
+//! use wstd::time::Duration;
+//!
+//! let mut client = Client::new();
+//! client.set_retry_policy(
+//!     RetryPolicy::new(4).with_backoff(Duration::from_millis(50), Duration::from_secs(5)),
+//! );
+//! ```
+//!
+//! A request is retried when connecting to the server failed or timed out, or
+//! when the server responded with `429 Too Many Requests`, `502 Bad Gateway`,
+//! `503 Service Unavailable` or `504 Gateway Timeout`.
+//!
+//! Only requests with idempotent methods are retried, unless
+//! [`RetryPolicy::retry_non_idempotent`] is set. A request is also not retried
+//! if its body was too large for the client to keep a copy of it.
+//!
+//! Retries are delayed by an exponential backoff with jitter, or by the
+//! `Retry-After` header of the response, if any. A response asking to wait
+//! for longer than the maximum backoff is returned as-is.
+//!
+//! [`Client`]: super::Client
+
+use super::error::{ErrorVariant, WasiHttpErrorCode};
+use super::{date, Body, Error, HeaderMap, Method, Response, Result};
+use crate::time::{Duration, SystemTime};
+
+/// Which requests a [`Client`](super::Client) retries, and when.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    retry_non_idempotent: bool,
+}
+
+impl RetryPolicy {
+    /// Don't retry any requests.
+    pub fn none() -> Self {
+        Self::new(1)
+    }
+
+    /// Send each request up to `max_attempts` times, including the first
+    /// attempt.
+    ///
+    /// The backoff starts at 100 milliseconds, and is at most 10 seconds.
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            retry_non_idempotent: false,
+        }
+    }
+
+    /// Set the backoff before the first retry, which doubles for every retry
+    /// after it, up to `max`.
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Set whether requests with methods which aren't idempotent, such as
+    /// `POST`, are retried.
+    pub fn retry_non_idempotent(mut self, retry: bool) -> Self {
+        self.retry_non_idempotent = retry;
+        self
+    }
+
+    pub(crate) fn is_none(&self) -> bool {
+        self.max_attempts <= 1
+    }
+
+    /// How long to wait before retrying a `method` request which had
+    /// `result` on its `attempt`th attempt, or `None` if it shouldn't be
+    /// retried.
+    pub(crate) fn delay<B: Body>(
+        &self,
+        attempt: u32,
+        method: &Method,
+        result: &Result<Response<B>>,
+    ) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+        if !self.retry_non_idempotent && !is_idempotent(method) {
+            return None;
+        }
+        match result {
+            Err(err) if is_transient(err) => Some(self.backoff(attempt)),
+            Err(_) => None,
+            Ok(response) => match u16::from(response.status_code()) {
+                429 | 502 | 503 | 504 => match retry_after(response.headers()) {
+                    Some(delay) if delay > self.max_backoff => None,
+                    Some(delay) => Some(delay),
+                    None => Some(self.backoff(attempt)),
+                },
+                _ => None,
+            },
+        }
+    }
+
+    /// How long to wait before resuming a transfer which failed on its
+    /// `attempt`th attempt, or `None` if it shouldn't be resumed.
+    pub(crate) fn resume_delay(&self, attempt: u32) -> Option<Duration> {
+        (attempt < self.max_attempts).then(|| self.backoff(attempt))
+    }
+
+    /// The backoff after the `attempt`th attempt: half of the exponential
+    /// backoff, plus a random part of the other half.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let initial = std::time::Duration::from(self.initial_backoff);
+        let max = std::time::Duration::from(self.max_backoff);
+        let backoff = initial
+            .checked_mul(1 << (attempt - 1).min(31))
+            .map_or(max, |backoff| backoff.min(max));
+        let mut bytes = [0; 8];
+        crate::rand::get_insecure_random_bytes(&mut bytes);
+        let jitter = (u64::from_le_bytes(bytes) >> 11) as f64 / (1u64 << 53) as f64;
+        (backoff / 2 + backoff.mul_f64(jitter / 2.0)).into()
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE | Method::PUT | Method::DELETE
+    )
+}
+
+/// Whether `err` is likely to go away when the request is sent again.
+fn is_transient(err: &Error) -> bool {
+    matches!(
+        err.variant(),
+        ErrorVariant::WasiHttp(
+            WasiHttpErrorCode::DnsTimeout
+                | WasiHttpErrorCode::DestinationUnavailable
+                | WasiHttpErrorCode::ConnectionRefused
+                | WasiHttpErrorCode::ConnectionTerminated
+                | WasiHttpErrorCode::ConnectionTimeout
+                | WasiHttpErrorCode::ConnectionReadTimeout
+                | WasiHttpErrorCode::ConnectionWriteTimeout
+                | WasiHttpErrorCode::ConnectionLimitReached
+                | WasiHttpErrorCode::HttpResponseTimeout
+        )
+    )
+}
+
+/// Parse a `Retry-After` header, which is either a number of seconds or an
+/// HTTP date.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get("retry-after")?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = date::parse(value)?;
+    Some(
+        date.checked_duration_since(SystemTime::now())
+            .unwrap_or(Duration::from_secs(0)),
+    )
+}
@@ -0,0 +1,620 @@
+//! Request routing for HTTP servers
+
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+
+use super::server::{Finished, Handler, IncomingRequest, Responder};
+use super::{HeaderValue, Method, Response, StatusCode};
+
+type BoxHandler =
+    Rc<dyn Fn(IncomingRequest, Params, Responder) -> Pin<Box<dyn Future<Output = Finished>>>>;
+
+/// Routes incoming requests to handlers, based on their method and path.
+///
+/// Routes are matched in the order they were added. Path patterns are made up
+/// of segments separated by `/`, where each segment is one of:
+///
+/// - a literal, such as `users`, which must match exactly,
+/// - a parameter, such as `:id`, which matches any single segment,
+/// - a wildcard, such as `*rest`, which matches all remaining segments. A
+///   wildcard must be the last segment of a pattern, and a wildcard without a
+///   name, `*`, doesn't add a parameter.
+///
+/// Matched parameters and wildcards are available through [`Routed::params`],
+/// with percent-encoded characters decoded. Literals are matched against the
+/// decoded path as well.
+///
+/// # Panics
+///
+/// Adding a route whose pattern has a wildcard before its last segment
+/// panics, as does nesting a router under such a prefix.
+///
+/// `HEAD` requests are routed to `GET` routes, unless a route for `HEAD` or
+/// any method matches first.
+///
+/// When routes match the path but not the method, the router responds with
+/// `405 Method Not Allowed`. When no route matches the path, the fallback of
+/// the innermost [nested](Router::nest) router whose prefix matches handles
+/// the request, then the [`fallback`](Router::fallback) of this router, or it
+/// responds with `404 Not Found`.
+///
+/// Every router carries a state value, which is cloned and passed to its
+/// handlers. Use [`Rc`] or a similar type to share state which should not be
+/// cloned.
+///
+/// # Example
+///
+/// ```no_run
+/// use wstd::http::router::Routed;
+/// use wstd::http::server::{Finished, IncomingRequest, Responder};
+/// use wstd::http::{Response, Router, StatusCode};
+///
+/// #[wstd::http_server]
+/// async fn main(request: IncomingRequest, responder: Responder) -> Finished {
+///     Router::new()
+///         .get("/users/:id", |routed: Routed, responder: Responder| async move {
+///             let id = routed.params.get("id").unwrap_or_default();
+///             let response = Response::new(StatusCode::Ok).set_body(format!("user {id}\n"));
+///             responder.respond(response).await
+///         })
+///         .handle(request, responder)
+///         .await
+/// }
+/// ```
+pub struct Router<S = ()> {
+    state: S,
+    routes: Vec<Route>,
+    nested_fallbacks: Vec<(Pattern, BoxHandler)>,
+    fallback: Option<BoxHandler>,
+}
+
+struct Route {
+    method: Option<Method>,
+    pattern: Pattern,
+    handler: BoxHandler,
+}
+
+/// What handles a request, as found by [`Router::find`].
+#[derive(Debug, PartialEq)]
+enum Found {
+    /// The route at this index.
+    Route(usize, Params),
+    /// Routes match the path, but only with these methods.
+    NotAllowed(Vec<Method>),
+    /// The nested fallback at this index.
+    NestedFallback(usize, Params),
+    /// The fallback of the router, if any.
+    Fallback,
+}
+
+impl Router<()> {
+    /// Create a new `Router` without any routes or state.
+    pub fn new() -> Self {
+        Self::with_state(())
+    }
+}
+
+impl Default for Router<()> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Clone + 'static> Router<S> {
+    /// Create a new `Router` without any routes, which passes `state` to its
+    /// handlers.
+    pub fn with_state(state: S) -> Self {
+        Self {
+            state,
+            routes: Vec::new(),
+            nested_fallbacks: Vec::new(),
+            fallback: None,
+        }
+    }
+
+    /// Add a route for requests with the given method and path pattern.
+    pub fn route<F, Fut>(self, method: Method, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(Routed<S>, Responder) -> Fut + 'static,
+        Fut: Future<Output = Finished> + 'static,
+    {
+        self.add_route(Some(method), pattern, handler)
+    }
+
+    /// Add a route for requests with any method and the given path pattern.
+    pub fn any<F, Fut>(self, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(Routed<S>, Responder) -> Fut + 'static,
+        Fut: Future<Output = Finished> + 'static,
+    {
+        self.add_route(None, pattern, handler)
+    }
+
+    /// Add a route for `GET` requests.
+    pub fn get<F, Fut>(self, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(Routed<S>, Responder) -> Fut + 'static,
+        Fut: Future<Output = Finished> + 'static,
+    {
+        self.route(Method::GET, pattern, handler)
+    }
+
+    /// Add a route for `POST` requests.
+    pub fn post<F, Fut>(self, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(Routed<S>, Responder) -> Fut + 'static,
+        Fut: Future<Output = Finished> + 'static,
+    {
+        self.route(Method::POST, pattern, handler)
+    }
+
+    /// Add a route for `PUT` requests.
+    pub fn put<F, Fut>(self, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(Routed<S>, Responder) -> Fut + 'static,
+        Fut: Future<Output = Finished> + 'static,
+    {
+        self.route(Method::PUT, pattern, handler)
+    }
+
+    /// Add a route for `PATCH` requests.
+    pub fn patch<F, Fut>(self, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(Routed<S>, Responder) -> Fut + 'static,
+        Fut: Future<Output = Finished> + 'static,
+    {
+        self.route(Method::PATCH, pattern, handler)
+    }
+
+    /// Add a route for `DELETE` requests.
+    pub fn delete<F, Fut>(self, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(Routed<S>, Responder) -> Fut + 'static,
+        Fut: Future<Output = Finished> + 'static,
+    {
+        self.route(Method::DELETE, pattern, handler)
+    }
+
+    /// Set the handler for requests which don't match any route.
+    pub fn fallback<F, Fut>(mut self, handler: F) -> Self
+    where
+        F: Fn(Routed<S>, Responder) -> Fut + 'static,
+        Fut: Future<Output = Finished> + 'static,
+    {
+        self.fallback = Some(self.box_handler(handler));
+        self
+    }
+
+    /// Serve all routes of `router` under the path `prefix`.
+    ///
+    /// The nested router keeps its own state. Its fallback, if any, handles
+    /// the requests under `prefix` which don't match any of its routes.
+    pub fn nest<T>(mut self, prefix: &str, router: Router<T>) -> Self {
+        let prefix = Pattern::parse(prefix);
+        for route in router.routes {
+            let pattern = prefix.join(&route.pattern);
+            assert!(
+                pattern.wildcard_is_last(),
+                "a wildcard must be the last segment of a route pattern: {pattern:?}"
+            );
+            self.routes.push(Route {
+                method: route.method,
+                pattern,
+                handler: route.handler,
+            });
+        }
+        for (pattern, handler) in router.nested_fallbacks {
+            self.nested_fallbacks.push((prefix.join(&pattern), handler));
+        }
+        if let Some(handler) = router.fallback {
+            self.nested_fallbacks
+                .push((prefix.join(&Pattern::parse("/*")), handler));
+        }
+        self
+    }
+
+    /// Route a request to its handler, and respond with its response.
+    pub async fn handle(&self, request: IncomingRequest, responder: Responder) -> Finished {
+        let path = request.uri().path().to_owned();
+        match self.find(request.method(), &path) {
+            Found::Route(i, params) => (self.routes[i].handler)(request, params, responder).await,
+            Found::NotAllowed(allowed) => {
+                let allow = allowed
+                    .iter()
+                    .map(|m| m.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let mut response = Response::new(StatusCode::MethodNotAllowed);
+                match HeaderValue::from_str(&allow) {
+                    Ok(allow) => {
+                        response.headers_mut().insert("allow", allow);
+                    }
+                    Err(err) => return responder.fail(err.into()),
+                }
+                responder.respond(response).await
+            }
+            Found::NestedFallback(i, params) => {
+                (self.nested_fallbacks[i].1)(request, params, responder).await
+            }
+            Found::Fallback => match &self.fallback {
+                Some(handler) => handler(request, Params::default(), responder).await,
+                None => responder.respond(Response::new(StatusCode::NotFound)).await,
+            },
+        }
+    }
+
+    /// Find what handles a request with `method` and `path`.
+    fn find(&self, method: &Method, path: &str) -> Found {
+        let mut allowed = Vec::new();
+        let mut get = None;
+        for (i, route) in self.routes.iter().enumerate() {
+            let Some(params) = route.pattern.matches(path) else {
+                continue;
+            };
+            match &route.method {
+                Some(m) if m == method => return Found::Route(i, params),
+                None => return Found::Route(i, params),
+                Some(m) => {
+                    if *m == Method::GET && *method == Method::HEAD && get.is_none() {
+                        get = Some((i, params));
+                    }
+                    if !allowed.contains(m) {
+                        allowed.push(m.clone());
+                    }
+                }
+            }
+        }
+        if let Some((i, params)) = get {
+            return Found::Route(i, params);
+        }
+
+        if !allowed.is_empty() {
+            if allowed.contains(&Method::GET) && !allowed.contains(&Method::HEAD) {
+                allowed.push(Method::HEAD);
+            }
+            return Found::NotAllowed(allowed);
+        }
+
+        // The innermost nested router, with the longest prefix, comes first.
+        self.nested_fallbacks
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (pattern, _))| Some((i, pattern.matches(path)?, pattern)))
+            .max_by_key(|(i, _, pattern)| (pattern.segments.len(), std::cmp::Reverse(*i)))
+            .map_or(Found::Fallback, |(i, params, _)| {
+                Found::NestedFallback(i, params)
+            })
+    }
+
+    fn add_route<F, Fut>(mut self, method: Option<Method>, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(Routed<S>, Responder) -> Fut + 'static,
+        Fut: Future<Output = Finished> + 'static,
+    {
+        let parsed = Pattern::parse(pattern);
+        assert!(
+            parsed.wildcard_is_last(),
+            "a wildcard must be the last segment of a route pattern: {pattern}"
+        );
+        let handler = self.box_handler(handler);
+        self.routes.push(Route {
+            method,
+            pattern: parsed,
+            handler,
+        });
+        self
+    }
+
+    fn box_handler<F, Fut>(&self, handler: F) -> BoxHandler
+    where
+        F: Fn(Routed<S>, Responder) -> Fut + 'static,
+        Fut: Future<Output = Finished> + 'static,
+    {
+        let state = self.state.clone();
+        Rc::new(move |request, params, responder| {
+            let routed = Routed {
+                request,
+                params,
+                state: state.clone(),
+            };
+            Box::pin(handler(routed, responder))
+        })
+    }
+}
+
+impl<S: Clone + 'static> Handler for Router<S> {
+    async fn handle(&self, request: IncomingRequest, responder: Responder) -> Finished {
+        Router::handle(self, request, responder).await
+    }
+}
+
+impl<S> std::fmt::Debug for Router<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let routes: Vec<_> = self
+            .routes
+            .iter()
+            .map(|r| {
+                (
+                    r.method.as_ref().map(|m| m.as_str()).unwrap_or("*"),
+                    &r.pattern,
+                )
+            })
+            .collect();
+        f.debug_struct("Router").field("routes", &routes).finish()
+    }
+}
+
+/// A request which was matched to a route by a [`Router`].
+#[derive(Debug)]
+pub struct Routed<S = ()> {
+    /// The incoming request.
+    pub request: IncomingRequest,
+    /// The parameters extracted from the request path.
+    pub params: Params,
+    /// The state of the router which matched the request.
+    pub state: S,
+}
+
+/// Parameters extracted from a request path by a [`Router`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Params {
+    params: Vec<(String, String)>,
+}
+
+impl Params {
+    /// Get the value of the parameter or wildcard named `name`.
+    ///
+    /// The value of a wildcard is the remainder of the path, without a leading
+    /// `/`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Iterate over the names and values of all parameters.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.params.iter().map(|(n, v)| (n.as_str(), v.as_str()))
+    }
+
+    /// Returns the number of parameters.
+    pub fn len(&self) -> usize {
+        self.params.len()
+    }
+
+    /// Returns `true` if there are no parameters.
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Literal(String),
+    Param(String),
+    Wildcard(String),
+}
+
+#[derive(Debug, Clone)]
+struct Pattern {
+    segments: Vec<Segment>,
+}
+
+impl Pattern {
+    fn parse(pattern: &str) -> Self {
+        let segments = pattern
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                if let Some(name) = s.strip_prefix(':') {
+                    Segment::Param(name.to_owned())
+                } else if let Some(name) = s.strip_prefix('*') {
+                    Segment::Wildcard(name.to_owned())
+                } else {
+                    Segment::Literal(s.to_owned())
+                }
+            })
+            .collect();
+        Self { segments }
+    }
+
+    fn join(&self, other: &Pattern) -> Pattern {
+        let mut segments = self.segments.clone();
+        segments.extend(other.segments.iter().cloned());
+        Pattern { segments }
+    }
+
+    /// Returns `false` if a wildcard is followed by more segments, which it
+    /// would swallow.
+    fn wildcard_is_last(&self) -> bool {
+        self.segments
+            .iter()
+            .rev()
+            .skip(1)
+            .all(|s| !matches!(s, Segment::Wildcard(_)))
+    }
+
+    fn matches(&self, path: &str) -> Option<Params> {
+        let mut params = Vec::new();
+        let mut rest = path.trim_start_matches('/');
+        for segment in &self.segments {
+            if let Segment::Wildcard(name) = segment {
+                if !name.is_empty() {
+                    params.push((name.clone(), percent_decode(rest)));
+                }
+                return Some(Params { params });
+            }
+            let (head, tail) = rest.split_once('/').unwrap_or((rest, ""));
+            if head.is_empty() {
+                return None;
+            }
+            let head = percent_decode(head);
+            match segment {
+                Segment::Literal(literal) if *literal != head => return None,
+                Segment::Param(name) => params.push((name.clone(), head)),
+                _ => {}
+            }
+            rest = tail.trim_start_matches('/');
+        }
+        rest.is_empty().then_some(Params { params })
+    }
+}
+
+/// Decode the percent-encoded characters of a path. Malformed escapes are
+/// kept as they are, and invalid UTF-8 is replaced.
+fn percent_decode(s: &str) -> String {
+    fn hex(byte: u8) -> Option<u8> {
+        match byte {
+            b'0'..=b'9' => Some(byte - b'0'),
+            b'a'..=b'f' => Some(byte - b'a' + 10),
+            b'A'..=b'F' => Some(byte - b'A' + 10),
+            _ => None,
+        }
+    }
+
+    if !s.contains('%') {
+        return s.to_owned();
+    }
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = match bytes[i..] {
+            [b'%', hi, lo, ..] => hex(hi).zip(hex(lo)).map(|(hi, lo)| hi << 4 | lo),
+            _ => None,
+        };
+        match escaped {
+            Some(byte) => {
+                out.push(byte);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(pattern: &str, path: &str) -> Option<Vec<(String, String)>> {
+        Pattern::parse(pattern).matches(path).map(|p| p.params)
+    }
+
+    fn pairs(pairs: &[(&str, &str)]) -> Option<Vec<(String, String)>> {
+        Some(
+            pairs
+                .iter()
+                .map(|(n, v)| (n.to_string(), v.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn literals_and_params() {
+        assert_eq!(params("/users", "/users"), pairs(&[]));
+        assert_eq!(params("/users", "/users/1"), None);
+        assert_eq!(params("/users/:id", "/users/1"), pairs(&[("id", "1")]));
+        assert_eq!(params("/users/:id", "/users/"), None);
+        assert_eq!(params("/users/:id", "/groups/1"), None);
+    }
+
+    #[test]
+    fn wildcard() {
+        assert_eq!(
+            params("/files/*path", "/files/a/b.txt"),
+            pairs(&[("path", "a/b.txt")])
+        );
+        assert_eq!(params("/files/*path", "/files"), pairs(&[("path", "")]));
+    }
+
+    #[test]
+    fn percent_decoded() {
+        assert_eq!(
+            params("/users/:name", "/users/J%C3%BCrgen%20M"),
+            pairs(&[("name", "Jürgen M")])
+        );
+        // An encoded `/` doesn't separate segments.
+        assert_eq!(
+            params("/users/:name", "/users/a%2Fb"),
+            pairs(&[("name", "a/b")])
+        );
+        assert_eq!(params("/caf\u{e9}", "/caf%C3%A9"), pairs(&[]));
+        assert_eq!(
+            params("/files/*path", "/files/a%20b/c"),
+            pairs(&[("path", "a b/c")])
+        );
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%4"), "%zz%4");
+    }
+
+    #[test]
+    fn wildcard_must_be_last() {
+        assert!(Pattern::parse("/files/*path").wildcard_is_last());
+        assert!(Pattern::parse("/").wildcard_is_last());
+        assert!(!Pattern::parse("/files/*path/edit").wildcard_is_last());
+        let nested = Pattern::parse("/files/*path").join(&Pattern::parse("/edit"));
+        assert!(!nested.wildcard_is_last());
+    }
+
+    async fn ok(_: Routed, responder: Responder) -> Finished {
+        responder.respond(Response::new(StatusCode::Ok)).await
+    }
+
+    fn no_params(i: usize) -> Found {
+        Found::Route(i, Params::default())
+    }
+
+    #[test]
+    fn head_is_routed_to_get() {
+        let router = Router::new()
+            .get("/x", ok)
+            .route(Method::HEAD, "/y", ok)
+            .get("/y", ok);
+        assert_eq!(router.find(&Method::HEAD, "/x"), no_params(0));
+        assert_eq!(router.find(&Method::HEAD, "/y"), no_params(1));
+        assert_eq!(
+            router.find(&Method::POST, "/x"),
+            Found::NotAllowed(vec![Method::GET, Method::HEAD])
+        );
+    }
+
+    #[test]
+    fn nested_fallback_comes_last() {
+        let api = Router::new().get("/greet", ok).fallback(ok);
+        let router = Router::new().nest("/api", api).get("/api/other", ok);
+        // Wrong methods for nested routes are still not allowed.
+        assert_eq!(
+            router.find(&Method::POST, "/api/greet"),
+            Found::NotAllowed(vec![Method::GET, Method::HEAD])
+        );
+        // Routes added after nesting aren't shadowed by the nested fallback.
+        assert_eq!(router.find(&Method::GET, "/api/other"), no_params(1));
+        assert_eq!(
+            router.find(&Method::GET, "/api/nowhere"),
+            Found::NestedFallback(0, Params::default())
+        );
+        assert_eq!(router.find(&Method::GET, "/nowhere"), Found::Fallback);
+    }
+
+    #[test]
+    fn innermost_nested_fallback() {
+        let inner = Router::new().fallback(ok);
+        let outer = Router::new().fallback(ok).nest("/inner", inner);
+        let router = Router::new().nest("/outer", outer);
+        assert_eq!(
+            router.find(&Method::GET, "/outer/inner/x"),
+            Found::NestedFallback(0, Params::default())
+        );
+        assert_eq!(
+            router.find(&Method::GET, "/outer/x"),
+            Found::NestedFallback(1, Params::default())
+        );
+    }
+}
@@ -0,0 +1,327 @@
+//! HTTP servers
+//!
+//! A component serves HTTP by exporting the `wasi:http/incoming-handler`
+//! interface. The [`http_server`] attribute macro takes care of that: it turns
+//! an `async fn main` which receives an [`IncomingRequest`] and a
+//! [`Responder`] into the exported handler, and runs it on
+//! [`block_on`](crate::runtime::block_on) for every incoming request.
+//!
+//! ```rust,no_run
+#![doc = include_str!("../../examples/http_server.rs")]
+//! ```
+//!
+//! [`http_server`]: crate::http_server
+
+use std::future::Future;
+
+use wasi::http::types::{
+    IncomingRequest as WasiIncomingRequest, ResponseOutparam, Scheme as WasiScheme,
+};
+
+use super::{
+    body::MultipartReader,
+    body::{IncomingBody, OutgoingBody},
+    error::{ErrorVariant, WasiHttpErrorCode},
+    fields::header_map_from_wasi,
+    headers,
+    method::from_wasi_method,
+    response::BodyKind,
+    Body, Error, HeaderMap, Method, Mime, Response, Result, Uri,
+};
+use crate::io::Empty;
+use crate::runtime::block_on;
+use crate::time::SystemTime;
+
+/// An incoming HTTP request, as received by a server.
+#[derive(Debug)]
+pub struct IncomingRequest {
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: IncomingBody,
+}
+
+impl IncomingRequest {
+    pub(crate) fn try_from_wasi(incoming: WasiIncomingRequest) -> Result<Self> {
+        let method = from_wasi_method(incoming.method())?;
+        let uri = uri_from_wasi(&incoming)?;
+        let headers = header_map_from_wasi(incoming.headers())?;
+
+        let kind = BodyKind::from_headers(&headers)?;
+        let incoming_body = incoming
+            .consume()
+            .expect("cannot call `consume` twice on incoming request");
+        let content_type = headers.get("content-type").cloned();
+        let body = IncomingBody::new(kind, content_type, incoming_body);
+
+        Ok(Self {
+            method,
+            uri,
+            headers,
+            body,
+        })
+    }
+
+    /// Get the HTTP method of the request
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    /// Get the URI of the request
+    pub fn uri(&self) -> &Uri {
+        &self.uri
+    }
+
+    /// Get the HTTP headers from the impl
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Parse the `Content-Type` header, if any.
+    pub fn content_type(&self) -> Result<Option<Mime>> {
+        headers::content_type(&self.headers)
+    }
+
+    /// Parse the `Content-Length` header, if any.
+    pub fn content_length(&self) -> Result<Option<u64>> {
+        headers::content_length(&self.headers)
+    }
+
+    /// Parse the `Date` header, if any.
+    pub fn date(&self) -> Result<Option<SystemTime>> {
+        headers::date(&self.headers)
+    }
+
+    /// Get the `User-Agent` header, if any.
+    pub fn user_agent(&self) -> Result<Option<&str>> {
+        headers::user_agent(&self.headers)
+    }
+
+    /// Parse the media types listed by the `Accept` header, in order.
+    pub fn accept(&self) -> Result<Vec<Mime>> {
+        headers::accept(&self.headers)
+    }
+
+    /// Mutably get the HTTP body of the request
+    pub fn body(&mut self) -> &mut IncomingBody {
+        &mut self.body
+    }
+
+    /// Consume the request, returning its body
+    pub fn into_body(self) -> IncomingBody {
+        self.body
+    }
+
+    /// Consume the request, returning a parser for its `multipart/form-data`
+    /// body.
+    ///
+    /// Fails if the request's `Content-Type` isn't multipart.
+    pub fn multipart(self) -> Result<MultipartReader<IncomingBody>> {
+        let content_type = self
+            .headers
+            .get(http::header::CONTENT_TYPE)
+            .ok_or_else(|| Error::other("request has no content-type"))?;
+        MultipartReader::from_content_type(self.body, content_type)
+    }
+}
+
+fn uri_from_wasi(incoming: &WasiIncomingRequest) -> Result<Uri> {
+    let mut uri = Uri::builder();
+    // A URI can only carry a scheme alongside an authority.
+    if let Some(authority) = incoming.authority() {
+        let scheme = match incoming.scheme() {
+            Some(WasiScheme::Http) => "http".to_owned(),
+            Some(WasiScheme::Https) | None => "https".to_owned(),
+            Some(WasiScheme::Other(other)) => other,
+        };
+        uri = uri.scheme(scheme.as_str()).authority(authority);
+    }
+    let path_with_query = incoming.path_with_query().unwrap_or_else(|| "/".to_owned());
+    uri.path_and_query(path_with_query)
+        .build()
+        .map_err(|e| Error::other(format!("incoming request uri is invalid: {e}")))
+}
+
+/// Sends the response to an [`IncomingRequest`].
+///
+/// Every request must be responded to exactly once, which is why all methods
+/// on `Responder` consume it and return [`Finished`].
+#[derive(Debug)]
+#[must_use = "the peer will not receive a response unless one is sent"]
+pub struct Responder {
+    outparam: ResponseOutparam,
+    headers: HeaderMap,
+}
+
+impl Responder {
+    pub(crate) fn new(outparam: ResponseOutparam) -> Self {
+        Self {
+            outparam,
+            headers: HeaderMap::new(),
+        }
+    }
+
+    /// Mutably get the headers which will be added to the response.
+    ///
+    /// Headers which the response sets itself take precedence over these.
+    pub fn headers_mut(&mut self) -> &mut HeaderMap {
+        &mut self.headers
+    }
+
+    /// Send a response, streaming its body to the peer.
+    pub async fn respond<B: Body>(self, mut response: Response<B>) -> Finished {
+        self.add_headers(response.headers_mut());
+        let (wasi_response, body) = match response.into_outgoing() {
+            Ok(outgoing) => outgoing,
+            Err(err) => return self.fail(err),
+        };
+        let wasi_body = wasi_response
+            .body()
+            .expect("cannot call `body` twice on an outgoing response");
+        let outgoing_body = OutgoingBody::new(wasi_body, &wasi_response.headers());
+        ResponseOutparam::set(self.outparam, Ok(wasi_response));
+
+        let result = async {
+            outgoing_body?
+                .send(body)
+                .await
+                .map_err(|e| e.context("sending response body"))
+        };
+        Finished(result.await)
+    }
+
+    /// Send the head of a response, returning an [`OutgoingBody`] to write
+    /// the response body to.
+    ///
+    /// Once the body has been written, it must be completed with
+    /// [`Finished::finish`].
+    pub fn start_response(self, mut response: Response<Empty>) -> Result<OutgoingBody> {
+        self.add_headers(response.headers_mut());
+        let (wasi_response, _) = match response.into_outgoing() {
+            Ok(outgoing) => outgoing,
+            Err(err) => {
+                ResponseOutparam::set(self.outparam, Err(to_wasi_error_code(&err)));
+                return Err(err);
+            }
+        };
+        let wasi_body = wasi_response
+            .body()
+            .expect("cannot call `body` twice on an outgoing response");
+        let outgoing_body = OutgoingBody::new(wasi_body, &wasi_response.headers());
+        ResponseOutparam::set(self.outparam, Ok(wasi_response));
+        outgoing_body
+    }
+
+    /// Respond with an error instead of a response.
+    ///
+    /// The host will send an error response to the peer on our behalf.
+    pub fn fail(self, err: Error) -> Finished {
+        ResponseOutparam::set(self.outparam, Err(to_wasi_error_code(&err)));
+        Finished(Err(err))
+    }
+
+    fn add_headers(&self, headers: &mut HeaderMap) {
+        for name in self.headers.keys() {
+            if !headers.contains_key(name) {
+                for value in self.headers.get_all(name) {
+                    headers.append(name.clone(), value.clone());
+                }
+            }
+        }
+    }
+}
+
+fn to_wasi_error_code(err: &Error) -> WasiHttpErrorCode {
+    match err.variant() {
+        ErrorVariant::WasiHttp(code) => code.clone(),
+        _ => WasiHttpErrorCode::InternalError(Some(err.to_string())),
+    }
+}
+
+/// A token showing that a response has been sent.
+///
+/// This is returned from the methods on [`Responder`] and from handlers
+/// annotated with [`http_server`](crate::http_server).
+#[derive(Debug)]
+#[must_use]
+pub struct Finished(Result<()>);
+
+impl Finished {
+    /// Finish sending a response body which was started with
+    /// [`Responder::start_response`].
+    pub fn finish(body: OutgoingBody) -> Self {
+        Self(body.finish())
+    }
+
+    /// Returns whether the response was sent successfully.
+    pub fn result(self) -> Result<()> {
+        self.0
+    }
+
+    /// Returns the error which prevented the response from being sent, if
+    /// any.
+    pub fn error(&self) -> Option<&Error> {
+        self.0.as_ref().err()
+    }
+}
+
+/// Handlers which fail after the response head was sent can drop the
+/// [`OutgoingBody`] without finishing it and return the error, so the peer sees
+/// an incomplete response.
+impl From<Error> for Finished {
+    fn from(err: Error) -> Self {
+        Self(Err(err))
+    }
+}
+
+/// Handles incoming requests.
+///
+/// This is implemented for [`Router`](super::Router), for functions and
+/// closures taking an [`IncomingRequest`] and a [`Responder`], and for the
+/// services in [`middleware`](super::middleware), so that they can be layered
+/// around a handler.
+pub trait Handler {
+    /// Handle a request, and respond to it.
+    async fn handle(&self, request: IncomingRequest, responder: Responder) -> Finished;
+
+    /// Wrap this handler in a [`Layer`](super::Layer).
+    fn layer<L: super::Layer<Self>>(self, layer: L) -> L::Service
+    where
+        Self: Sized,
+    {
+        layer.layer(self)
+    }
+}
+
+impl<F, Fut> Handler for F
+where
+    F: Fn(IncomingRequest, Responder) -> Fut,
+    Fut: Future<Output = Finished>,
+{
+    async fn handle(&self, request: IncomingRequest, responder: Responder) -> Finished {
+        self(request, responder).await
+    }
+}
+
+/// Run a handler for an incoming request.
+///
+/// This is used by the [`http_server`](crate::http_server) macro and is not
+/// meant to be called directly.
+#[doc(hidden)]
+pub fn handle<F, Fut>(request: WasiIncomingRequest, outparam: ResponseOutparam, handler: F)
+where
+    F: FnOnce(IncomingRequest, Responder) -> Fut,
+    Fut: Future<Output = Finished>,
+{
+    let responder = Responder::new(outparam);
+    let request = match IncomingRequest::try_from_wasi(request) {
+        Ok(request) => request,
+        Err(err) => {
+            let _ = responder.fail(err.context("reading incoming request"));
+            return;
+        }
+    };
+    // If sending the response body failed, the body was not finished and the
+    // host reports the error to the peer; there's nothing left for us to do.
+    let _ = block_on(handler(request, responder));
+}
@@ -0,0 +1,373 @@
+//! Server-Sent Events
+//!
+//! An [`EventStream`] parses a `text/event-stream` body into [`Event`]s, as
+//! described by the [HTML standard]:
+//!
+//! ```no_run
+//! use wstd::http::sse::EventStream;
+//! use wstd::http::{Client, Method, Request};
+//! use wstd::iter::AsyncIterator;
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let request = Request::new(Method::GET, "https://example.com/events".parse()?);
+//! let mut events = EventStream::connect(Client::new(), request).await?;
+//! while let Some(event) = events.next().await {
+//!     let event = event?;
+//!     println!("{}: {}", event.event(), event.data());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! A stream opened with [`EventStream::connect`] reconnects when the
+//! connection ends, after the delay set by the server's `retry` field, and
+//! sends the `Last-Event-ID` header to resume where it left off. The stream
+//! ends when the server responds to a reconnection with `204 No Content`.
+//!
+//! [HTML standard]: https://html.spec.whatwg.org/multipage/server-sent-events.html
+
+use std::fmt;
+
+use super::body::DynRead;
+use super::request::Parts;
+use super::response::IncomingBody;
+use super::{Client, Error, HeaderValue, Request, Result, StatusCode};
+use crate::io::{empty, AsyncRead, Empty};
+use crate::iter::AsyncIterator;
+use crate::time::Duration;
+
+/// How many milliseconds to wait before reconnecting, unless the server
+/// sets it.
+const DEFAULT_RETRY_MS: u64 = 3000;
+
+/// The largest event which is accepted, including its field names.
+const MAX_EVENT_SIZE: usize = IncomingBody::DEFAULT_MAX_SIZE as usize;
+
+/// How much is read from the body at a time.
+const CHUNK_SIZE: usize = 4096;
+
+/// An event received from an [`EventStream`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    event: String,
+    data: String,
+    id: String,
+}
+
+impl Event {
+    /// Get the type of the event, from its `event` field, which defaults to
+    /// `message`.
+    pub fn event(&self) -> &str {
+        &self.event
+    }
+
+    /// Get the data of the event, from its `data` fields, which are joined
+    /// by line breaks.
+    pub fn data(&self) -> &str {
+        &self.data
+    }
+
+    /// Get the last event ID set by this or an earlier event, if any.
+    pub fn id(&self) -> Option<&str> {
+        (!self.id.is_empty()).then_some(self.id.as_str())
+    }
+
+    /// Consume the event, returning its data.
+    pub fn into_data(self) -> String {
+        self.data
+    }
+
+    /// Deserialize the data of the event from JSON.
+    #[cfg(feature = "json")]
+    pub fn json<T: serde::de::DeserializeOwned>(&self) -> Result<T> {
+        serde_json::from_str(&self.data)
+            .map_err(|e| Error::other(e.to_string()).context("deserializing JSON event"))
+    }
+}
+
+/// A stream of [`Event`]s parsed from a `text/event-stream` body.
+pub struct EventStream {
+    body: Option<Box<dyn DynRead>>,
+    reconnect: Option<Reconnect>,
+    buf: Vec<u8>,
+    // How many bytes have we already parsed from the buf?
+    buf_offset: usize,
+    line: Vec<u8>,
+    // Whether the last line ended with `\r`, which may be followed by `\n`.
+    after_cr: bool,
+    at_start: bool,
+    event: String,
+    data: String,
+    last_event_id: String,
+    retry: Duration,
+}
+
+/// How to reconnect to an event stream.
+struct Reconnect {
+    client: Client,
+    parts: Parts,
+}
+
+impl EventStream {
+    /// Parse the events of `body`, such as the body of a response.
+    ///
+    /// The stream ends at the end of the body.
+    pub fn new<B: AsyncRead + 'static>(body: B) -> Self {
+        Self {
+            body: Some(Box::new(body)),
+            reconnect: None,
+            buf: Vec::new(),
+            buf_offset: 0,
+            line: Vec::new(),
+            after_cr: false,
+            at_start: true,
+            event: String::new(),
+            data: String::new(),
+            last_event_id: String::new(),
+            retry: Duration::from_millis(DEFAULT_RETRY_MS),
+        }
+    }
+
+    /// Send `request` with `client`, and parse the events of the response,
+    /// reconnecting when the connection ends.
+    ///
+    /// The `Accept` header of the request is set to `text/event-stream`.
+    /// Fails unless the server responds with `200 OK` and a
+    /// `text/event-stream` body.
+    pub async fn connect(client: Client, request: Request<Empty>) -> Result<Self> {
+        let (mut parts, _) = request.into_parts();
+        parts.headers.insert(
+            http::header::ACCEPT,
+            HeaderValue::from_static("text/event-stream"),
+        );
+        parts.headers.insert(
+            http::header::CACHE_CONTROL,
+            HeaderValue::from_static("no-cache"),
+        );
+        let reconnect = Reconnect { client, parts };
+        let body = reconnect.open(None).await?;
+        let mut stream = match body {
+            Some(body) => Self::new(body),
+            None => Self {
+                body: None,
+                ..Self::new(empty())
+            },
+        };
+        stream.reconnect = Some(reconnect);
+        Ok(stream)
+    }
+
+    /// Get the ID of the last event, which is sent when reconnecting.
+    pub fn last_event_id(&self) -> Option<&str> {
+        (!self.last_event_id.is_empty()).then_some(self.last_event_id.as_str())
+    }
+
+    /// Get how long to wait before reconnecting, as set by the server.
+    pub fn retry(&self) -> Duration {
+        self.retry
+    }
+
+    /// Parse the buffered lines until an event is complete.
+    fn parse_buffered(&mut self) -> Result<Option<Event>> {
+        while self.buf_offset < self.buf.len() {
+            let rest = &self.buf[self.buf_offset..];
+            if self.after_cr {
+                // A `\r\n` line break was split between reads.
+                self.after_cr = false;
+                if rest[0] == b'\n' {
+                    self.buf_offset += 1;
+                    continue;
+                }
+            }
+            let Some(end) = rest.iter().position(|&b| b == b'\r' || b == b'\n') else {
+                self.line.extend_from_slice(rest);
+                self.buf_offset = self.buf.len();
+                break;
+            };
+            self.line.extend_from_slice(&rest[..end]);
+            self.buf_offset += end + 1;
+            match rest[end] {
+                b'\r' if end + 1 < rest.len() => {
+                    if rest[end + 1] == b'\n' {
+                        self.buf_offset += 1;
+                    }
+                }
+                b'\r' => self.after_cr = true,
+                _ => {}
+            }
+
+            let line = std::mem::take(&mut self.line);
+            if let Some(event) = self.parse_line(&line) {
+                return Ok(Some(event));
+            }
+        }
+        if self.line.len() + self.data.len() > MAX_EVENT_SIZE {
+            return Err(Error::other(format!(
+                "event is larger than {MAX_EVENT_SIZE} bytes"
+            )));
+        }
+        Ok(None)
+    }
+
+    /// Parse a line, returning the event which it completes, if any.
+    fn parse_line(&mut self, line: &[u8]) -> Option<Event> {
+        let mut line = String::from_utf8_lossy(line);
+        if self.at_start {
+            self.at_start = false;
+            if let Some(rest) = line.strip_prefix('\u{feff}') {
+                line = rest.to_owned().into();
+            }
+        }
+
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            // A comment.
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (&*line, ""),
+        };
+        match field {
+            "event" => self.event = value.to_owned(),
+            "data" => {
+                self.data.push_str(value);
+                self.data.push('\n');
+            }
+            "id" if !value.contains('\0') => self.last_event_id = value.to_owned(),
+            "retry" if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) => {
+                if let Ok(ms) = value.parse() {
+                    self.retry = Duration::from_millis(ms);
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// Complete the event which was parsed so far, unless it has no data.
+    fn dispatch(&mut self) -> Option<Event> {
+        let event = std::mem::take(&mut self.event);
+        let mut data = std::mem::take(&mut self.data);
+        if data.is_empty() {
+            return None;
+        }
+        data.pop();
+        Some(Event {
+            event: if event.is_empty() {
+                "message".to_owned()
+            } else {
+                event
+            },
+            data,
+            id: self.last_event_id.clone(),
+        })
+    }
+
+    /// Discard the state of a connection which ended.
+    fn reset(&mut self) {
+        self.body = None;
+        self.buf.clear();
+        self.buf_offset = 0;
+        self.line.clear();
+        self.after_cr = false;
+        self.at_start = true;
+        self.event.clear();
+        self.data.clear();
+    }
+}
+
+impl AsyncIterator for EventStream {
+    type Item = Result<Event>;
+
+    async fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.parse_buffered() {
+                Ok(Some(event)) => return Some(Ok(event)),
+                Ok(None) => {}
+                Err(err) => {
+                    self.reset();
+                    self.reconnect = None;
+                    return Some(Err(err));
+                }
+            }
+
+            let body = self.body.as_mut()?;
+            self.buf.resize(CHUNK_SIZE, 0);
+            self.buf_offset = 0;
+            let result = body.read_dyn(&mut self.buf).await;
+            self.buf.truncate(*result.as_ref().unwrap_or(&0));
+            match result {
+                Ok(n) if n > 0 => continue,
+                Ok(_) => {}
+                Err(err) if self.reconnect.is_none() => {
+                    self.reset();
+                    return Some(Err(Error::from_io(err).context("reading event stream")));
+                }
+                Err(_) => {}
+            }
+
+            // The connection ended, along with any incomplete event.
+            self.reset();
+            let reconnect = self.reconnect.as_ref()?;
+            crate::task::sleep(self.retry).await;
+            let last_event_id = self.last_event_id();
+            match reconnect.open(last_event_id).await {
+                Ok(Some(body)) => self.body = Some(Box::new(body)),
+                Ok(None) => {
+                    self.reconnect = None;
+                    return None;
+                }
+                Err(err) => {
+                    self.reconnect = None;
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for EventStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventStream")
+            .field("last_event_id", &self.last_event_id)
+            .field("retry", &self.retry)
+            .field("reconnects", &self.reconnect.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Reconnect {
+    /// Send the request, returning the body of the event stream, or `None`
+    /// if the server responded with `204 No Content`.
+    async fn open(&self, last_event_id: Option<&str>) -> Result<Option<IncomingBody>> {
+        let mut request = Request::from_parts(self.parts.clone(), empty());
+        if let Some(id) = last_event_id {
+            let id = HeaderValue::from_str(id)
+                .map_err(|_| Error::other("last event ID is not a valid header value"))?;
+            request.headers_mut().insert("last-event-id", id);
+        }
+        let response = self.client.send(request).await?;
+        match response.status_code() {
+            StatusCode::Ok => {}
+            StatusCode::NoContent => return Ok(None),
+            status => {
+                return Err(Error::other(format!(
+                    "event stream responded with status {status}"
+                )))
+            }
+        }
+        let is_event_stream = response
+            .headers()
+            .get(http::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("text/event-stream"));
+        if !is_event_stream {
+            return Err(Error::other("response is not a text/event-stream"));
+        }
+        Ok(Some(response.into_body()))
+    }
+}
@@ -212,3 +212,18 @@ impl Display for StatusCode {
         }
     }
 }
+
+impl From<http::StatusCode> for StatusCode {
+    fn from(input: http::StatusCode) -> Self {
+        input.as_u16().into()
+    }
+}
+
+/// Fails for status codes outside of `100..=999`.
+impl TryFrom<StatusCode> for http::StatusCode {
+    type Error = http::status::InvalidStatusCode;
+
+    fn try_from(input: StatusCode) -> Result<Self, Self::Error> {
+        http::StatusCode::from_u16(input.into())
+    }
+}
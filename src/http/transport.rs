@@ -0,0 +1,594 @@
+//! Transports which send the requests of a [`Client`]
+//!
+//! A [`Client`] follows redirects, retries requests and answers them from its
+//! cache, and sends each single request through a [`Transport`]. By default,
+//! that is [`WasiTransport`], which sends requests with
+//! `wasi:http/outgoing-handler`.
+//!
+//! A [`MockTransport`] answers requests with canned responses instead, so
+//! that code which sends requests can be tested without a network:
+//!
+//! ```no_run
+//! use wstd::http::transport::{Mock, MockResponse, MockTransport};
+//! use wstd::http::{Client, Method, Request, StatusCode};
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let mock = MockTransport::new();
+//! mock.add(
+//!     Mock::new()
+//!         .method(Method::GET)
+//!         .path("/users/1")
+//!         .respond(MockResponse::new(StatusCode::Ok).body("ferris")),
+//! );
+//! let mut client = Client::new();
+//! client.set_transport(mock.clone());
+//!
+//! let request = Request::new(Method::GET, "https://example.com/users/1".parse()?);
+//! let body = client.send(request).await?.body().text().await?;
+//! assert_eq!(body, "ferris");
+//! assert_eq!(mock.requests()[0].uri().path(), "/users/1");
+//! # Ok(())
+//! # }
+//! ```
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::future::{self, Future};
+use std::pin::{pin, Pin};
+use std::rc::Rc;
+use std::task::Poll;
+
+use http::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use wasi::http::outgoing_handler::OutgoingRequest;
+
+use super::body::{BoxBody, OutgoingBody};
+use super::client::{RequestOptions, ResponseFuture};
+use super::error::WasiHttpErrorCode;
+use super::method::from_wasi_method;
+use super::response::{BodyKind, IncomingBody, Parts};
+use super::{
+    Error, HeaderMap, HeaderName, HeaderValue, Method, Request, Response, Result, StatusCode, Uri,
+};
+use crate::io::{self, AsyncRead};
+use crate::time::Duration;
+
+#[cfg(doc)]
+use super::Client;
+
+/// The future returned by [`Transport::send`].
+pub type TransportFuture<'a> = Pin<Box<dyn Future<Output = Result<Response<IncomingBody>>> + 'a>>;
+
+/// Sends single requests for a [`Client`].
+///
+/// Transports are boxed by the client, so `send` returns a boxed future:
+///
+/// ```
+/// use wstd::http::body::BoxBody;
+/// use wstd::http::transport::{Transport, TransportFuture, WasiTransport};
+/// use wstd::http::Request;
+///
+/// /// Sends requests with wasi-http, and counts them.
+/// #[derive(Default)]
+/// struct Counting(std::cell::Cell<usize>);
+///
+/// impl Transport for Counting {
+///     fn send<'a>(&'a self, request: Request<BoxBody<'a>>) -> TransportFuture<'a> {
+///         self.0.set(self.0.get() + 1);
+///         WasiTransport.send(request)
+///     }
+/// }
+/// ```
+pub trait Transport {
+    /// Send `request`, and return its response as it was received.
+    ///
+    /// The timeouts which apply to the request are kept in its extensions
+    /// by the client, and are applied by [`WasiTransport`].
+    fn send<'a>(&'a self, request: Request<BoxBody<'a>>) -> TransportFuture<'a>;
+}
+
+/// Sends requests with `wasi:http/outgoing-handler`, which is what a
+/// [`Client`] does by default.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WasiTransport;
+
+impl Transport for WasiTransport {
+    fn send<'a>(&'a self, request: Request<BoxBody<'a>>) -> TransportFuture<'a> {
+        Box::pin(send(request))
+    }
+}
+
+/// Send a request with wasi-http, sending its body while waiting for the
+/// response.
+///
+/// If the server responds with an error status before the whole body was
+/// sent, the rest of the body is not sent, and the response is returned right
+/// away.
+async fn send(req: Request<BoxBody<'_>>) -> Result<Response<IncomingBody>> {
+    let options = req
+        .extensions()
+        .get::<RequestOptions>()
+        .copied()
+        .unwrap_or_default();
+    let (wasi_req, body) = req.into_outgoing()?;
+    let (outgoing_body, mut response) = start(wasi_req, options)?;
+
+    let upload = async move {
+        outgoing_body
+            .send(body)
+            .await
+            .map_err(|e| e.context("sending request body"))
+    };
+    let mut upload = pin!(upload);
+
+    let first = future::poll_fn(|cx| {
+        if let Poll::Ready(res) = Pin::new(&mut response).poll(cx) {
+            return Poll::Ready(Either::Response(res));
+        }
+        upload.as_mut().poll(cx).map(Either::Upload)
+    })
+    .await;
+
+    match first {
+        Either::Upload(Ok(())) => response.await,
+        // The server may have stopped reading the body because it already
+        // responded, in which case the response is more useful.
+        Either::Upload(Err(err)) => match response.await {
+            Ok(res) => Ok(res),
+            Err(_) => Err(err),
+        },
+        Either::Response(Ok(res)) if u16::from(res.status_code()) >= 400 => Ok(res),
+        Either::Response(Ok(res)) => {
+            upload.await?;
+            Ok(res)
+        }
+        // The connection failed, and the upload's error says how far it got.
+        Either::Response(Err(err)) => match upload.await {
+            Ok(()) => Err(err),
+            Err(upload_err) => Err(upload_err),
+        },
+    }
+}
+
+/// Send the head of a request with wasi-http, returning an [`OutgoingBody`]
+/// to write the request body to, and a [`ResponseFuture`] for its response.
+pub(crate) fn start(
+    wasi_req: OutgoingRequest,
+    options: RequestOptions,
+) -> Result<(OutgoingBody, ResponseFuture)> {
+    let wasi_body = wasi_req
+        .body()
+        .map_err(|()| Error::other("outgoing request body was already taken"))?;
+    let outgoing_body = OutgoingBody::new(wasi_body, &wasi_req.headers())?;
+    let method = from_wasi_method(wasi_req.method())?;
+
+    let res = wasi::http::outgoing_handler::handle(wasi_req, options.to_wasi()?)
+        .map_err(|e| Error::from(e).context("sending request"))?;
+    Ok((outgoing_body, ResponseFuture::new(res, method)))
+}
+
+enum Either<A, B> {
+    Response(A),
+    Upload(B),
+}
+
+/// A [`Transport`] which answers requests with [`Mock`]s, and keeps the
+/// requests it received.
+///
+/// Clones of a `MockTransport` share their mocks and requests, so a clone can
+/// be given to a [`Client`], and the requests checked afterwards.
+#[derive(Debug, Clone, Default)]
+pub struct MockTransport {
+    inner: Rc<RefCell<Inner>>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    mocks: Vec<Mock>,
+    requests: Vec<ReceivedRequest>,
+}
+
+impl MockTransport {
+    /// Create a transport without mocks, which fails every request.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a mock.
+    ///
+    /// A request is answered by the first mock which matches it, and which
+    /// didn't answer as many requests as its [`Mock::times`] yet. A request
+    /// which no mock matches fails.
+    pub fn add(&self, mock: Mock) {
+        self.inner.borrow_mut().mocks.push(mock);
+    }
+
+    /// Get the requests which were received so far, in order.
+    pub fn requests(&self) -> Vec<ReceivedRequest> {
+        self.inner.borrow().requests.clone()
+    }
+
+    /// Check that every mock with [`Mock::times`] answered that many
+    /// requests.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a mock answered fewer requests than expected.
+    #[track_caller]
+    pub fn verify(&self) {
+        let inner = self.inner.borrow();
+        for mock in &inner.mocks {
+            if let Some(times) = mock.times {
+                assert!(
+                    mock.answered == times,
+                    "expected {times} requests to match {mock:?}, but {} did",
+                    mock.answered
+                );
+            }
+        }
+    }
+
+    async fn respond(&self, request: Request<BoxBody<'_>>) -> Result<Response<IncomingBody>> {
+        let (parts, mut body) = request.into_parts();
+        let mut bytes = Vec::new();
+        body.read_to_end(&mut bytes)
+            .await
+            .map_err(|e| Error::from_io(e).context("reading request body"))?;
+        let request = ReceivedRequest {
+            method: parts.method,
+            uri: parts.uri,
+            headers: parts.headers,
+            body: bytes,
+        };
+
+        let mock = {
+            let mut inner = self.inner.borrow_mut();
+            let mock = inner
+                .mocks
+                .iter_mut()
+                .find(|mock| mock.matches(&request))
+                .map(|mock| {
+                    mock.answered += 1;
+                    (mock.delay, mock.reply.clone())
+                });
+            inner.requests.push(request.clone());
+            mock
+        };
+        let Some((delay, reply)) = mock else {
+            return Err(Error::other(format!(
+                "no mock matches request: {} {}",
+                request.method, request.uri
+            )));
+        };
+        if let Some(delay) = delay {
+            crate::task::sleep(delay).await;
+        }
+        match reply {
+            Reply::Response(response) => response.into_response(&request.method),
+            Reply::Fail(code) => Err(Error::from(code).context("sending request")),
+        }
+    }
+}
+
+impl Transport for MockTransport {
+    fn send<'a>(&'a self, request: Request<BoxBody<'a>>) -> TransportFuture<'a> {
+        Box::pin(self.respond(request))
+    }
+}
+
+/// A request received by a [`MockTransport`].
+#[derive(Debug, Clone)]
+pub struct ReceivedRequest {
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+impl ReceivedRequest {
+    /// Get the HTTP method of the request.
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    /// Get the URI of the request.
+    pub fn uri(&self) -> &Uri {
+        &self.uri
+    }
+
+    /// Get the HTTP headers of the request.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Get the body of the request.
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+}
+
+/// Which requests a [`MockTransport`] answers, and how.
+///
+/// Mocks which only answer a number of requests with [`Mock::times`] can be
+/// added one after the other to answer a series of requests differently,
+/// such as failing a request and then answering it when it is retried.
+#[derive(Debug, Clone)]
+pub struct Mock {
+    method: Option<Method>,
+    path: Option<String>,
+    headers: Vec<(String, String)>,
+    times: Option<usize>,
+    answered: usize,
+    delay: Option<Duration>,
+    reply: Reply,
+}
+
+#[derive(Debug, Clone)]
+enum Reply {
+    Response(MockResponse),
+    Fail(WasiHttpErrorCode),
+}
+
+impl Mock {
+    /// Create a mock which matches every request, and responds with
+    /// `200 OK` and an empty body.
+    pub fn new() -> Self {
+        Self {
+            method: None,
+            path: None,
+            headers: Vec::new(),
+            times: None,
+            answered: 0,
+            delay: None,
+            reply: Reply::Response(MockResponse::new(StatusCode::Ok)),
+        }
+    }
+
+    /// Only match requests with `method`.
+    pub fn method(mut self, method: Method) -> Self {
+        self.method = Some(method);
+        self
+    }
+
+    /// Only match requests for `path`, such as `/search`. If `path` has a
+    /// query, such as `/search?q=wasi`, the query must match as well.
+    pub fn path(mut self, path: &str) -> Self {
+        self.path = Some(path.to_owned());
+        self
+    }
+
+    /// Only match requests with a `name` header whose value is `value`.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Only answer `n` requests, and expect as many in
+    /// [`MockTransport::verify`].
+    pub fn times(mut self, n: usize) -> Self {
+        self.times = Some(n);
+        self
+    }
+
+    /// Wait for `delay` before answering a request.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Respond to matching requests with `response`.
+    pub fn respond(mut self, response: MockResponse) -> Self {
+        self.reply = Reply::Response(response);
+        self
+    }
+
+    /// Fail matching requests with `code`, as if sending them had failed.
+    pub fn fail(mut self, code: WasiHttpErrorCode) -> Self {
+        self.reply = Reply::Fail(code);
+        self
+    }
+
+    fn matches(&self, request: &ReceivedRequest) -> bool {
+        if self.times.is_some_and(|times| self.answered >= times) {
+            return false;
+        }
+        if self
+            .method
+            .as_ref()
+            .is_some_and(|method| *method != request.method)
+        {
+            return false;
+        }
+        if let Some(path) = &self.path {
+            let actual = if path.contains('?') {
+                request.uri.path_and_query().map_or("/", |pq| pq.as_str())
+            } else {
+                request.uri.path()
+            };
+            if actual != path {
+                return false;
+            }
+        }
+        self.headers.iter().all(|(name, value)| {
+            request
+                .headers
+                .get_all(name.as_str())
+                .iter()
+                .any(|actual| actual.as_bytes() == value.as_bytes())
+        })
+    }
+}
+
+impl Default for Mock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A response sent by a [`Mock`].
+///
+/// The body can be sent all at once with [`MockResponse::body`], or as a
+/// script of chunks, delays and failures:
+///
+/// ```
+/// use wstd::http::error::WasiHttpErrorCode;
+/// use wstd::http::transport::MockResponse;
+/// use wstd::http::StatusCode;
+/// use wstd::time::Duration;
+///
+/// // Send part of the body, and then fail as if the connection was lost.
+/// let response = MockResponse::new(StatusCode::Ok)
+///     .header("content-type", "text/event-stream")
+///     .chunk("data: 1\n\n")
+///     .delay(Duration::from_millis(100))
+///     .chunk("data: 2\n\n")
+///     .fail(WasiHttpErrorCode::ConnectionTerminated);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    status: StatusCode,
+    headers: Vec<(String, String)>,
+    steps: Vec<Step>,
+    // The length of a body set with `MockResponse::body`.
+    len: Option<u64>,
+    trailers: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone)]
+enum Step {
+    Chunk(Vec<u8>),
+    Delay(Duration),
+    Fail(WasiHttpErrorCode),
+}
+
+impl MockResponse {
+    /// Create a response with `status` and an empty body.
+    pub fn new(status: StatusCode) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            steps: Vec::new(),
+            len: Some(0),
+            trailers: Vec::new(),
+        }
+    }
+
+    /// Add a header.
+    ///
+    /// An invalid header makes the request fail.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Set the body, which is sent with a `Content-Length` unless the
+    /// response has one.
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        let body = body.into();
+        self.len = Some(body.len() as u64);
+        self.steps = vec![Step::Chunk(body)];
+        self
+    }
+
+    /// Send `chunk` after the rest of the body so far.
+    ///
+    /// A body sent in chunks has no `Content-Length`, unless the response
+    /// has one.
+    pub fn chunk(mut self, chunk: impl Into<Vec<u8>>) -> Self {
+        self.len = None;
+        self.steps.push(Step::Chunk(chunk.into()));
+        self
+    }
+
+    /// Wait for `delay` before sending the rest of the body.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.steps.push(Step::Delay(delay));
+        self
+    }
+
+    /// Fail reading the rest of the body with `code`, as if the connection
+    /// had failed.
+    pub fn fail(mut self, code: WasiHttpErrorCode) -> Self {
+        self.steps.push(Step::Fail(code));
+        self
+    }
+
+    /// Add a trailer, which is received after the body.
+    ///
+    /// An invalid trailer makes the request fail.
+    pub fn trailer(mut self, name: &str, value: &str) -> Self {
+        self.trailers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    fn into_response(self, method: &Method) -> Result<Response<IncomingBody>> {
+        let mut headers = header_map(&self.headers)?;
+        if let Some(len) = self.len {
+            if !headers.contains_key(CONTENT_LENGTH) {
+                headers.insert(CONTENT_LENGTH, HeaderValue::from(len));
+            }
+        }
+        let trailers = match self.trailers.is_empty() {
+            true => None,
+            false => Some(header_map(&self.trailers)?),
+        };
+        let kind = BodyKind::from_response(method, self.status, &headers)?;
+        let content_type = headers.get(CONTENT_TYPE).cloned();
+        let script = Script {
+            steps: self.steps.into(),
+            offset: 0,
+        };
+        let body = IncomingBody::from_dyn_reader(Box::new(script), kind, content_type, trailers);
+
+        let mut parts = Parts::new(self.status);
+        parts.headers = headers;
+        Ok(Response::from_parts(parts, body))
+    }
+}
+
+fn header_map(pairs: &[(String, String)]) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    for (name, value) in pairs {
+        headers.append(
+            HeaderName::from_bytes(name.as_bytes())?,
+            HeaderValue::from_str(value)?,
+        );
+    }
+    Ok(headers)
+}
+
+/// The body of a [`MockResponse`], as it is read.
+struct Script {
+    steps: VecDeque<Step>,
+    // How much of the first chunk was read.
+    offset: usize,
+}
+
+impl AsyncRead for Script {
+    async fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            match self.steps.front() {
+                None => return Ok(0),
+                Some(Step::Chunk(chunk)) if self.offset >= chunk.len() => {
+                    self.steps.pop_front();
+                    self.offset = 0;
+                }
+                Some(Step::Chunk(chunk)) => {
+                    let chunk = &chunk[self.offset..];
+                    let len = chunk.len().min(buf.len());
+                    buf[..len].copy_from_slice(&chunk[..len]);
+                    self.offset += len;
+                    return Ok(len);
+                }
+                Some(Step::Delay(delay)) => {
+                    // The step is only removed once the delay is over, in
+                    // case reading is cancelled.
+                    crate::task::sleep(*delay).await;
+                    self.steps.pop_front();
+                }
+                Some(Step::Fail(code)) => {
+                    return Err(std::io::Error::other(Error::from(code.clone())));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,29 @@
+//! `application/x-www-form-urlencoded` encoding, as used in query strings and
+//! form bodies.
+
+/// Append `name=value` to `out`, separated from existing pairs by `&`.
+pub(crate) fn append_pair(out: &mut String, name: &str, value: &str) {
+    if !out.is_empty() {
+        out.push('&');
+    }
+    encode_into(out, name);
+    out.push('=');
+    encode_into(out, value);
+}
+
+fn encode_into(out: &mut String, s: &str) {
+    for byte in s.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'*' => {
+                out.push(byte as char)
+            }
+            b' ' => out.push('+'),
+            _ => {
+                const HEX: &[u8; 16] = b"0123456789ABCDEF";
+                out.push('%');
+                out.push(HEX[(byte >> 4) as usize] as char);
+                out.push(HEX[(byte & 0xf) as usize] as char);
+            }
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use super::{AsyncRead, AsyncWrite};
 
+#[derive(Debug)]
 #[non_exhaustive]
 pub struct Empty;
 impl AsyncRead for Empty {
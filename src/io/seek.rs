@@ -36,6 +36,13 @@ pub trait AsyncSeek {
     }
 }
 
+impl<S: AsyncSeek + ?Sized> AsyncSeek for &mut S {
+    #[inline]
+    async fn seek(&mut self, pos: SeekFrom) -> super::Result<u64> {
+        (**self).seek(pos).await
+    }
+}
+
 /// Enumeration of possible methods to seek within an I/O object.
 ///
 /// It is used by the [`AsyncSeek`] trait.
@@ -17,3 +17,20 @@ pub trait AsyncWrite {
         }
     }
 }
+
+impl<W: AsyncWrite + ?Sized> AsyncWrite for &mut W {
+    #[inline]
+    async fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (**self).write(buf).await
+    }
+
+    #[inline]
+    async fn flush(&mut self) -> io::Result<()> {
+        (**self).flush().await
+    }
+
+    #[inline]
+    async fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        (**self).write_all(buf).await
+    }
+}
@@ -58,9 +58,16 @@ pub mod runtime;
 pub mod task;
 pub mod time;
 
+pub use wstd_macro::attr_macro_http_server as http_server;
 pub use wstd_macro::attr_macro_main as main;
 pub use wstd_macro::attr_macro_test as test;
 
+// Used by the `http_server` macro to export `wasi:http/incoming-handler`.
+#[doc(hidden)]
+pub mod __internal {
+    pub use wasi;
+}
+
 pub mod prelude {
     pub use crate::future::FutureExt as _;
     pub use crate::http::Body as _;
@@ -0,0 +1,151 @@
+use std::fmt;
+use std::io::ErrorKind;
+
+use crate::io;
+
+pub use wasi::io::streams::StreamError as WasiStreamError;
+pub use wasi::sockets::network::ErrorCode as WasiSocketErrorCode;
+
+/// The error type for socket and stream operations.
+///
+/// Operations in `wstd::net` return an [`io::Error`]. When an operation fails
+/// because of an error reported by WASI, the `io::Error` wraps one of these,
+/// so the original error can be inspected:
+///
+/// ```no_run
+/// use wstd::net::{Error, TcpListener, WasiSocketErrorCode};
+///
+/// # async fn example() {
+/// if let Err(err) = TcpListener::bind("127.0.0.1:8080").await {
+///     let code = Error::from_io_error(&err).and_then(|e| e.wasi_code());
+///     if code == Some(WasiSocketErrorCode::AddressInUse) {
+///         // pick another port
+///     }
+/// }
+/// # }
+/// ```
+pub struct Error {
+    variant: ErrorVariant,
+}
+
+/// The source of an [`Error`].
+#[derive(Debug)]
+pub enum ErrorVariant {
+    /// An error reported by a `wasi:sockets` operation.
+    Socket(WasiSocketErrorCode),
+    /// An error reported by a `wasi:io` stream operation.
+    Stream(WasiStreamError),
+}
+
+impl Error {
+    /// Get the source of this error.
+    pub fn variant(&self) -> &ErrorVariant {
+        &self.variant
+    }
+
+    /// Returns the `wasi:sockets` error code, if this error has one.
+    pub fn wasi_code(&self) -> Option<WasiSocketErrorCode> {
+        match &self.variant {
+            ErrorVariant::Socket(code) => Some(*code),
+            ErrorVariant::Stream(_) => None,
+        }
+    }
+
+    /// Returns the corresponding [`ErrorKind`] for this error.
+    ///
+    /// Codes which do not have a matching `ErrorKind` on stable Rust map to
+    /// [`ErrorKind::Other`]; use [`Error::wasi_code`] to tell those apart.
+    pub fn kind(&self) -> ErrorKind {
+        match &self.variant {
+            ErrorVariant::Socket(code) => socket_error_kind(*code),
+            ErrorVariant::Stream(WasiStreamError::Closed) => ErrorKind::BrokenPipe,
+            ErrorVariant::Stream(WasiStreamError::LastOperationFailed(_)) => ErrorKind::Other,
+        }
+    }
+
+    /// Get the `Error` wrapped by an `io::Error`, if there is one.
+    pub fn from_io_error(err: &io::Error) -> Option<&Error> {
+        err.get_ref()?.downcast_ref()
+    }
+}
+
+fn socket_error_kind(code: WasiSocketErrorCode) -> ErrorKind {
+    match code {
+        WasiSocketErrorCode::Unknown => ErrorKind::Other,
+        WasiSocketErrorCode::AccessDenied => ErrorKind::PermissionDenied,
+        WasiSocketErrorCode::NotSupported => ErrorKind::Unsupported,
+        WasiSocketErrorCode::InvalidArgument => ErrorKind::InvalidInput,
+        WasiSocketErrorCode::OutOfMemory => ErrorKind::OutOfMemory,
+        WasiSocketErrorCode::Timeout => ErrorKind::TimedOut,
+        // EALREADY: the operation is already in progress.
+        WasiSocketErrorCode::ConcurrencyConflict => ErrorKind::AlreadyExists,
+        WasiSocketErrorCode::NotInProgress => ErrorKind::InvalidInput,
+        WasiSocketErrorCode::WouldBlock => ErrorKind::WouldBlock,
+        WasiSocketErrorCode::InvalidState => ErrorKind::InvalidData,
+        // TODO: use `ErrorKind::QuotaExceeded` once it is stable.
+        WasiSocketErrorCode::NewSocketLimit => ErrorKind::Other,
+        WasiSocketErrorCode::AddressNotBindable => ErrorKind::AddrNotAvailable,
+        WasiSocketErrorCode::AddressInUse => ErrorKind::AddrInUse,
+        // TODO: use `ErrorKind::HostUnreachable` once our toolchain has it.
+        WasiSocketErrorCode::RemoteUnreachable => ErrorKind::Other,
+        WasiSocketErrorCode::ConnectionRefused => ErrorKind::ConnectionRefused,
+        WasiSocketErrorCode::ConnectionReset => ErrorKind::ConnectionReset,
+        WasiSocketErrorCode::ConnectionAborted => ErrorKind::ConnectionAborted,
+        WasiSocketErrorCode::DatagramTooLarge => ErrorKind::InvalidInput,
+        WasiSocketErrorCode::NameUnresolvable => ErrorKind::NotFound,
+        WasiSocketErrorCode::TemporaryResolverFailure => ErrorKind::Other,
+        WasiSocketErrorCode::PermanentResolverFailure => ErrorKind::Other,
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.variant {
+            ErrorVariant::Socket(e) => write!(f, "wasi socket error: {e:?}"),
+            ErrorVariant::Stream(WasiStreamError::LastOperationFailed(e)) => {
+                write!(f, "wasi stream error: {}", e.to_debug_string())
+            }
+            ErrorVariant::Stream(WasiStreamError::Closed) => write!(f, "wasi stream closed"),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.variant {
+            ErrorVariant::Socket(e) => write!(f, "{}", e.message()),
+            ErrorVariant::Stream(WasiStreamError::LastOperationFailed(e)) => {
+                write!(f, "last operation failed: {}", e.to_debug_string())
+            }
+            ErrorVariant::Stream(WasiStreamError::Closed) => write!(f, "stream was closed"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<WasiSocketErrorCode> for Error {
+    fn from(code: WasiSocketErrorCode) -> Error {
+        Error {
+            variant: ErrorVariant::Socket(code),
+        }
+    }
+}
+
+impl From<WasiStreamError> for Error {
+    fn from(err: WasiStreamError) -> Error {
+        Error {
+            variant: ErrorVariant::Stream(err),
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> io::Error {
+        io::Error::new(err.kind(), err)
+    }
+}
+
+pub(super) fn to_io_err(err: impl Into<Error>) -> io::Error {
+    err.into().into()
+}
@@ -1,7 +1,13 @@
 //! Async network abstractions.
 
+mod error;
+mod shutdown;
 mod tcp_listener;
 mod tcp_stream;
+mod tracker;
 
+pub use error::{Error, ErrorVariant, WasiSocketErrorCode, WasiStreamError};
+pub use shutdown::*;
 pub use tcp_listener::*;
 pub use tcp_stream::*;
+pub use tracker::*;
@@ -0,0 +1,65 @@
+use std::cell::RefCell;
+use std::future;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+/// A token used to signal that a server should stop accepting connections.
+///
+/// Cloning a `ShutdownToken` yields a handle to the same token, so one clone
+/// can be handed to an accept loop while another is used to trigger the
+/// shutdown. See [`TcpListener::incoming_with_shutdown`] for more.
+///
+/// [`TcpListener::incoming_with_shutdown`]: super::TcpListener::incoming_with_shutdown
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownToken {
+    inner: Rc<RefCell<Inner>>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    shutdown: bool,
+    wakers: Vec<Waker>,
+}
+
+impl ShutdownToken {
+    /// Create a new `ShutdownToken` which has not been triggered yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request a shutdown.
+    ///
+    /// Every accept loop observing this token will stop yielding new
+    /// connections. Calling this more than once has no further effect.
+    pub fn shutdown(&self) {
+        let wakers = {
+            let mut inner = self.inner.borrow_mut();
+            inner.shutdown = true;
+            std::mem::take(&mut inner.wakers)
+        };
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    /// Returns `true` if a shutdown has been requested.
+    pub fn is_shutdown(&self) -> bool {
+        self.inner.borrow().shutdown
+    }
+
+    /// Wait until a shutdown has been requested.
+    pub async fn wait(&self) {
+        future::poll_fn(|cx| self.poll_shutdown(cx)).await
+    }
+
+    pub(crate) fn poll_shutdown(&self, cx: &mut Context<'_>) -> Poll<()> {
+        let mut inner = self.inner.borrow_mut();
+        if inner.shutdown {
+            return Poll::Ready(());
+        }
+        if !inner.wakers.iter().any(|w| w.will_wake(cx.waker())) {
+            inner.wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
@@ -1,13 +1,15 @@
 use wasi::sockets::network::Ipv4SocketAddress;
-use wasi::sockets::tcp::{ErrorCode, IpAddressFamily, IpSocketAddress, TcpSocket};
+use wasi::sockets::tcp::{IpAddressFamily, IpSocketAddress, TcpSocket};
 
 use crate::io;
 use crate::iter::AsyncIterator;
 use crate::runtime::Reactor;
-use std::io::ErrorKind;
+use std::future::{self, Future};
 use std::net::SocketAddr;
+use std::pin::pin;
+use std::task::Poll;
 
-use super::TcpStream;
+use super::{error::to_io_err, ShutdownToken, TcpStream};
 
 /// A TCP socket server, listening for connections.
 #[derive(Debug)]
@@ -63,23 +65,53 @@ impl TcpListener {
 
     /// Returns an iterator over the connections being received on this listener.
     pub fn incoming(&self) -> Incoming<'_> {
-        Incoming { listener: self }
+        Incoming {
+            listener: self,
+            shutdown: None,
+        }
+    }
+
+    /// Returns an iterator over the connections being received on this
+    /// listener, which ends once a shutdown is requested through `token`.
+    ///
+    /// Connections which were already accepted are not affected by the
+    /// shutdown; use a [`ConnectionTracker`](super::ConnectionTracker) to
+    /// wait for them to finish.
+    pub fn incoming_with_shutdown(&self, token: ShutdownToken) -> Incoming<'_> {
+        Incoming {
+            listener: self,
+            shutdown: Some(token),
+        }
     }
 }
 
-/// An iterator that infinitely accepts connections on a TcpListener.
+/// An iterator that accepts connections on a TcpListener.
+///
+/// Unless it was created with a [`ShutdownToken`], this iterator never ends.
 #[derive(Debug)]
 pub struct Incoming<'a> {
     listener: &'a TcpListener,
+    shutdown: Option<ShutdownToken>,
 }
 
 impl<'a> AsyncIterator for Incoming<'a> {
     type Item = io::Result<TcpStream>;
 
     async fn next(&mut self) -> Option<Self::Item> {
-        Reactor::current()
-            .wait_for(self.listener.socket.subscribe())
-            .await;
+        let reactor = Reactor::current();
+        let mut ready = pin!(reactor.wait_for(self.listener.socket.subscribe()));
+        let accepted = future::poll_fn(|cx| {
+            if let Some(token) = &self.shutdown {
+                if token.poll_shutdown(cx).is_ready() {
+                    return Poll::Ready(false);
+                }
+            }
+            ready.as_mut().poll(cx).map(|()| true)
+        })
+        .await;
+        if !accepted {
+            return None;
+        }
         let (socket, input, output) = match self.listener.socket.accept().map_err(to_io_err) {
             Ok(accepted) => accepted,
             Err(err) => return Some(Err(err)),
@@ -91,22 +123,3 @@ impl<'a> AsyncIterator for Incoming<'a> {
         }))
     }
 }
-
-pub(super) fn to_io_err(err: ErrorCode) -> io::Error {
-    match err {
-        wasi::sockets::network::ErrorCode::Unknown => ErrorKind::Other.into(),
-        wasi::sockets::network::ErrorCode::AccessDenied => ErrorKind::PermissionDenied.into(),
-        wasi::sockets::network::ErrorCode::NotSupported => ErrorKind::Unsupported.into(),
-        wasi::sockets::network::ErrorCode::InvalidArgument => ErrorKind::InvalidInput.into(),
-        wasi::sockets::network::ErrorCode::OutOfMemory => ErrorKind::OutOfMemory.into(),
-        wasi::sockets::network::ErrorCode::Timeout => ErrorKind::TimedOut.into(),
-        wasi::sockets::network::ErrorCode::WouldBlock => ErrorKind::WouldBlock.into(),
-        wasi::sockets::network::ErrorCode::InvalidState => ErrorKind::InvalidData.into(),
-        wasi::sockets::network::ErrorCode::AddressInUse => ErrorKind::AddrInUse.into(),
-        wasi::sockets::network::ErrorCode::ConnectionRefused => ErrorKind::ConnectionRefused.into(),
-        wasi::sockets::network::ErrorCode::ConnectionReset => ErrorKind::ConnectionReset.into(),
-        wasi::sockets::network::ErrorCode::ConnectionAborted => ErrorKind::ConnectionAborted.into(),
-        wasi::sockets::network::ErrorCode::ConcurrencyConflict => ErrorKind::AlreadyExists.into(),
-        _ => ErrorKind::Other.into(),
-    }
-}
@@ -1,10 +1,9 @@
-use std::io::Error;
-
 use wasi::{
     io::streams::StreamError,
     sockets::tcp::{InputStream, OutputStream, TcpSocket},
 };
 
+use super::error::to_io_err;
 use crate::{
     io::{self, AsyncRead, AsyncWrite},
     runtime::Reactor,
@@ -20,40 +19,39 @@ pub struct TcpStream {
 impl TcpStream {
     /// Returns the socket address of the remote peer of this TCP connection.
     pub fn peer_addr(&self) -> io::Result<String> {
-        let addr = self
-            .socket
-            .remote_address()
-            .map_err(super::tcp_listener::to_io_err)?;
+        let addr = self.socket.remote_address().map_err(to_io_err)?;
         Ok(format!("{addr:?}"))
     }
 }
 
 impl AsyncRead for TcpStream {
     async fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        Reactor::current().wait_for(self.input.subscribe()).await;
-        let slice = match self.input.read(buf.len() as u64) {
-            Ok(slice) => slice,
-            Err(StreamError::Closed) => return Ok(0),
-            Err(e) => return Err(to_io_err(e)),
-        };
-        let bytes_read = slice.len();
-        buf[..bytes_read].clone_from_slice(&slice);
-        Ok(bytes_read)
+        read(&self.input, buf).await
     }
 }
 
 impl AsyncRead for &TcpStream {
     async fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        Reactor::current().wait_for(self.input.subscribe()).await;
-        let slice = match self.input.read(buf.len() as u64) {
-            Ok(slice) => slice,
+        read(&self.input, buf).await
+    }
+}
+
+/// Read from `input`, waiting until there are bytes to read or it has ended.
+async fn read(input: &InputStream, buf: &mut [u8]) -> io::Result<usize> {
+    let slice = loop {
+        Reactor::current().wait_for(input.subscribe()).await;
+        // A stream may be ready without any bytes to read, which doesn't
+        // mean it has ended.
+        match input.read(buf.len() as u64) {
+            Ok(slice) if slice.is_empty() && !buf.is_empty() => continue,
+            Ok(slice) => break slice,
             Err(StreamError::Closed) => return Ok(0),
             Err(e) => return Err(to_io_err(e)),
-        };
-        let bytes_read = slice.len();
-        buf[..bytes_read].clone_from_slice(&slice);
-        Ok(bytes_read)
-    }
+        }
+    };
+    let bytes_read = slice.len();
+    buf[..bytes_read].clone_from_slice(&slice);
+    Ok(bytes_read)
 }
 
 impl AsyncWrite for TcpStream {
@@ -79,10 +77,3 @@ impl AsyncWrite for &TcpStream {
         self.output.flush().map_err(to_io_err)
     }
 }
-
-fn to_io_err(err: StreamError) -> std::io::Error {
-    match err {
-        StreamError::LastOperationFailed(err) => Error::other(err.to_debug_string()),
-        StreamError::Closed => Error::other("Stream was closed"),
-    }
-}
@@ -0,0 +1,102 @@
+use std::cell::RefCell;
+use std::future::{self, IntoFuture};
+use std::rc::Rc;
+use std::task::{Poll, Waker};
+
+use crate::future::FutureExt;
+use crate::io;
+
+/// Keeps count of the connections which are currently being handled.
+///
+/// Call [`track`] when a connection is accepted and hold on to the returned
+/// [`ConnectionGuard`] while it is being handled. Once the server has stopped
+/// accepting connections, [`drain`] waits for the remaining handlers to
+/// finish.
+///
+/// Cloning a `ConnectionTracker` yields a handle to the same set of
+/// connections.
+///
+/// [`track`]: ConnectionTracker::track
+/// [`drain`]: ConnectionTracker::drain
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionTracker {
+    inner: Rc<RefCell<Inner>>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    active: usize,
+    wakers: Vec<Waker>,
+}
+
+impl ConnectionTracker {
+    /// Create a new `ConnectionTracker` without any active connections.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mark a connection as active until the returned guard is dropped.
+    #[must_use = "the connection is only tracked until the guard is dropped"]
+    pub fn track(&self) -> ConnectionGuard {
+        self.inner.borrow_mut().active += 1;
+        ConnectionGuard {
+            tracker: self.clone(),
+        }
+    }
+
+    /// Returns the number of connections which are currently active.
+    pub fn active(&self) -> usize {
+        self.inner.borrow().active
+    }
+
+    /// Wait until there are no more active connections.
+    pub async fn wait_idle(&self) {
+        future::poll_fn(|cx| {
+            let mut inner = self.inner.borrow_mut();
+            if inner.active == 0 {
+                return Poll::Ready(());
+            }
+            if !inner.wakers.iter().any(|w| w.will_wake(cx.waker())) {
+                inner.wakers.push(cx.waker().clone());
+            }
+            Poll::Pending
+        })
+        .await
+    }
+
+    /// Wait until there are no more active connections, or until the deadline
+    /// expires.
+    ///
+    /// Returns an error of kind [`TimedOut`](std::io::ErrorKind::TimedOut) if
+    /// connections were still active when the deadline expired.
+    pub async fn drain<D: IntoFuture>(&self, deadline: D) -> io::Result<()> {
+        self.wait_idle().timeout(deadline).await
+    }
+}
+
+/// Marks a connection as active for as long as it is alive.
+///
+/// This `struct` is created by the [`track`] method on [`ConnectionTracker`].
+/// See its documentation for more.
+///
+/// [`track`]: ConnectionTracker::track
+#[derive(Debug)]
+pub struct ConnectionGuard {
+    tracker: ConnectionTracker,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        let wakers = {
+            let mut inner = self.tracker.inner.borrow_mut();
+            inner.active -= 1;
+            if inner.active > 0 {
+                return;
+            }
+            std::mem::take(&mut inner.wakers)
+        };
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
@@ -2,9 +2,10 @@ use super::{Reactor, REACTOR};
 
 use core::future::Future;
 use core::pin::pin;
-use core::ptr;
-use core::task::Waker;
-use core::task::{Context, Poll, RawWaker, RawWakerVTable};
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::{Context, Poll};
+use std::sync::Arc;
+use std::task::Wake;
 
 /// Start the event loop
 pub fn block_on<Fut>(fut: Fut) -> Fut::Output
@@ -23,14 +24,21 @@ where
     let mut fut = pin!(fut);
 
     // Create a new context to be passed to the future.
-    let waker = noop_waker();
+    let root = Arc::new(RootWaker::default());
+    let waker = root.clone().into();
     let mut cx = Context::from_waker(&waker);
 
     // Either the future completes and we return, or some IO is happening
-    // and we wait.
+    // and we wait. If the future was woken while it was being polled (for
+    // example because one part of it triggered an event another part is
+    // waiting on), we poll it again without blocking, but still pick up the
+    // IO which is ready by then, so a future which keeps waking itself can't
+    // starve the rest.
     let res = loop {
+        root.woken.store(false, Ordering::Relaxed);
         match fut.as_mut().poll(&mut cx) {
             Poll::Ready(res) => break res,
+            Poll::Pending if root.woken.load(Ordering::Relaxed) => reactor.poll_events(),
             Poll::Pending => reactor.block_until(),
         }
     };
@@ -39,21 +47,21 @@ where
     res
 }
 
-/// Construct a new no-op waker
-// NOTE: we can remove this once <https://github.com/rust-lang/rust/issues/98286> lands
-fn noop_waker() -> Waker {
-    const VTABLE: RawWakerVTable = RawWakerVTable::new(
-        // Cloning just returns a new no-op raw waker
-        |_| RAW,
-        // `wake` does nothing
-        |_| {},
-        // `wake_by_ref` does nothing
-        |_| {},
-        // Dropping does nothing as we don't allocate anything
-        |_| {},
-    );
-    const RAW: RawWaker = RawWaker::new(ptr::null(), &VTABLE);
-
-    // SAFETY: all fields are no-ops, so this is safe
-    unsafe { Waker::from_raw(RAW) }
+/// The waker handed to the root future.
+///
+/// Waking it only records that the root future wants to be polled again;
+/// there is no other task to schedule.
+#[derive(Debug, Default)]
+struct RootWaker {
+    woken: AtomicBool,
+}
+
+impl Wake for RootWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.woken.store(true, Ordering::Relaxed);
+    }
 }
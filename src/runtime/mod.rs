@@ -15,11 +15,12 @@ mod polling;
 mod reactor;
 
 pub use block_on::block_on;
+pub(crate) use polling::EventKey;
 pub use reactor::Reactor;
 use std::cell::RefCell;
 
 // There are no threads in WASI 0.2, so this is just a safe way to thread a single reactor to all
 // use sites in the background.
 std::thread_local! {
-pub(crate) static REACTOR: RefCell<Option<Reactor>> = RefCell::new(None);
+pub(crate) static REACTOR: RefCell<Option<Reactor>> = const { RefCell::new(None) };
 }
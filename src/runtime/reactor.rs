@@ -5,10 +5,11 @@ use super::{
 
 use core::cell::RefCell;
 use core::future;
-use core::task::Poll;
 use core::task::Waker;
+use core::task::{Context, Poll};
 use std::collections::HashMap;
 use std::rc::Rc;
+use wasi::clocks::monotonic_clock::subscribe_duration;
 use wasi::io::poll::Pollable;
 
 /// Manage async system resources for WASI 0.2
@@ -31,12 +32,13 @@ impl Reactor {
     /// # Panic
     /// This will panic if called outside of `wstd::runtime::block_on`.
     pub fn current() -> Self {
-        REACTOR.with(|r| {
-            r.borrow()
-                .as_ref()
-                .expect("Reactor::current must be called within a wstd runtime")
-                .clone()
-        })
+        Self::try_current().expect("Reactor::current must be called within a wstd runtime")
+    }
+
+    /// Return a `Reactor` for the currently running `wstd::runtime::block_on`,
+    /// if there is one.
+    pub(crate) fn try_current() -> Option<Self> {
+        REACTOR.with(|r| r.borrow().clone())
     }
 
     /// Create a new instance of `Reactor`
@@ -71,32 +73,75 @@ impl Reactor {
         }
     }
 
+    /// Call the wakers of the events which are ready, without blocking.
+    pub(crate) fn poll_events(&self) {
+        let mut reactor = self.inner.borrow_mut();
+        // A pollable which is always ready keeps the poller from blocking.
+        let now = reactor.poller.insert(subscribe_duration(0));
+        let ready = reactor.poller.block_until();
+        reactor.poller.remove(now);
+        for key in ready {
+            if let Some(waker) = reactor.wakers.get(&key) {
+                waker.wake_by_ref();
+            }
+        }
+    }
+
     /// Wait for the pollable to resolve.
+    ///
+    /// If the returned future is dropped before it resolves, the pollable is
+    /// removed from the reactor again.
     pub async fn wait_for(&self, pollable: Pollable) {
-        let mut pollable = Some(pollable);
-        let mut key = None;
+        // Schedule interest in the `pollable`. The registration is removed
+        // again once we're ready, or when this future is dropped.
+        let key = self.register(pollable);
+        let _registration = Registration { reactor: self, key };
+
         // This function is the core loop of our function; it will be called
         // multiple times as the future is resolving.
-        future::poll_fn(|cx| {
-            // Start by taking a lock on the reactor. This is single-threaded
-            // and short-lived, so it will never be contended.
-            let mut reactor = self.inner.borrow_mut();
-
-            // Schedule interest in the `pollable` on the first iteration. On
-            // every iteration, register the waker with the reactor.
-            let key = key.get_or_insert_with(|| reactor.poller.insert(pollable.take().unwrap()));
-            reactor.wakers.insert(*key, cx.waker().clone());
-
-            // Check whether we're ready or need to keep waiting. If we're
-            // ready, we clean up after ourselves.
-            if reactor.poller.get(key).unwrap().ready() {
-                reactor.poller.remove(*key);
-                reactor.wakers.remove(key);
-                Poll::Ready(())
-            } else {
-                Poll::Pending
-            }
-        })
-        .await
+        future::poll_fn(|cx| self.poll_ready(key, cx)).await
+    }
+
+    /// Schedule interest in `pollable`, until it is
+    /// [deregistered](Self::deregister).
+    pub(crate) fn register(&self, pollable: Pollable) -> EventKey {
+        self.inner.borrow_mut().poller.insert(pollable)
+    }
+
+    /// Check whether the pollable registered as `key` is ready, and wake `cx`
+    /// once it is if it isn't yet.
+    pub(crate) fn poll_ready(&self, key: EventKey, cx: &mut Context<'_>) -> Poll<()> {
+        // Start by taking a lock on the reactor. This is single-threaded
+        // and short-lived, so it will never be contended.
+        let mut reactor = self.inner.borrow_mut();
+
+        // On every iteration, register the waker with the reactor.
+        reactor.wakers.insert(key, cx.waker().clone());
+
+        // Check whether we're ready or need to keep waiting.
+        if reactor.poller.get(&key).unwrap().ready() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
+    /// Remove the pollable registered as `key`.
+    pub(crate) fn deregister(&self, key: EventKey) {
+        let mut reactor = self.inner.borrow_mut();
+        reactor.poller.remove(key);
+        reactor.wakers.remove(&key);
+    }
+}
+
+/// Interest in a pollable, which is cleaned up from the reactor on drop.
+struct Registration<'a> {
+    reactor: &'a Reactor,
+    key: EventKey,
+}
+
+impl Drop for Registration<'_> {
+    fn drop(&mut self) {
+        self.reactor.deregister(self.key);
     }
 }
@@ -13,7 +13,7 @@ use crate::time::{Duration, Instant};
 pub fn sleep(dur: Duration) -> Sleep {
     Sleep {
         dur,
-        timer: AsyncTimer::after(dur.into()),
+        timer: AsyncTimer::after(dur),
         completed: false,
     }
 }
@@ -38,7 +38,7 @@ impl Future for Sleep {
         match this.timer.poll(cx) {
             Poll::Ready(instant) => {
                 *this.completed = true;
-                Poll::Ready(instant.into())
+                Poll::Ready(instant)
             }
             Poll::Pending => Poll::Pending,
         }
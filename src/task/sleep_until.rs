@@ -8,7 +8,7 @@ use pin_project_lite::pin_project;
 /// Sleeps until the specified instant.
 pub fn sleep_until(deadline: Instant) -> SleepUntil {
     SleepUntil {
-        timer: Timer::at(deadline.into()),
+        timer: Timer::at(deadline),
         completed: false,
     }
 }
@@ -32,7 +32,7 @@ impl Future for SleepUntil {
         match this.timer.poll(cx) {
             Poll::Ready(instant) => {
                 *this.completed = true;
-                Poll::Ready(instant.into())
+                Poll::Ready(instant)
             }
             Poll::Pending => Poll::Pending,
         }
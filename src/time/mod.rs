@@ -12,18 +12,81 @@ use std::pin::Pin;
 use std::task::{Context, Poll};
 use wasi::clocks::{monotonic_clock::subscribe_instant, wall_clock};
 
-use crate::{iter::AsyncIterator, runtime::Reactor};
+use crate::{
+    iter::AsyncIterator,
+    runtime::{EventKey, Reactor},
+};
 
 /// A measurement of the system clock, useful for talking to external entities
 /// like the file system or other processes.
 #[derive(Debug, Clone, Copy)]
-#[allow(dead_code)]
 pub struct SystemTime(wall_clock::Datetime);
 
 impl SystemTime {
+    /// The Unix epoch, `1970-01-01 00:00:00 UTC`.
+    pub const UNIX_EPOCH: SystemTime = SystemTime(wall_clock::Datetime {
+        seconds: 0,
+        nanoseconds: 0,
+    });
+
+    /// Returns the system time corresponding to "now".
     pub fn now() -> Self {
         Self(wall_clock::now())
     }
+
+    /// Returns the amount of time elapsed from `earlier` to this time, or
+    /// `None` if `earlier` is later than this time.
+    pub fn checked_duration_since(&self, earlier: SystemTime) -> Option<Duration> {
+        self.to_std()
+            .checked_sub(earlier.to_std())
+            .map(Duration::from)
+    }
+
+    /// Returns the time `duration` after this time, or `None` if it can't be
+    /// represented.
+    pub fn checked_add(&self, duration: Duration) -> Option<SystemTime> {
+        let time = self
+            .to_std()
+            .checked_add(std::time::Duration::from(duration))?;
+        Some(Self(wall_clock::Datetime {
+            seconds: time.as_secs(),
+            nanoseconds: time.subsec_nanos(),
+        }))
+    }
+
+    /// The time since the Unix epoch.
+    fn to_std(self) -> std::time::Duration {
+        std::time::Duration::new(self.0.seconds, self.0.nanoseconds)
+    }
+}
+
+impl std::ops::Add<Duration> for SystemTime {
+    type Output = Self;
+
+    fn add(self, rhs: Duration) -> Self::Output {
+        self.checked_add(rhs)
+            .expect("overflow when adding duration to system time")
+    }
+}
+
+impl PartialEq for SystemTime {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_std() == other.to_std()
+    }
+}
+
+impl Eq for SystemTime {}
+
+impl PartialOrd for SystemTime {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SystemTime {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.to_std().cmp(&other.to_std())
+    }
 }
 
 /// An async iterator representing notifications at fixed interval.
@@ -48,23 +111,34 @@ impl AsyncIterator for Interval {
 }
 
 #[derive(Debug)]
-pub struct Timer(Option<Instant>);
+pub struct Timer {
+    deadline: Option<Instant>,
+    // The registration with the reactor while the timer is being polled. It
+    // must outlive individual polls, or the reactor would lose track of it.
+    registration: Option<EventKey>,
+}
 
 impl Timer {
     pub fn never() -> Timer {
-        Timer(None)
+        Timer {
+            deadline: None,
+            registration: None,
+        }
     }
     pub fn at(deadline: Instant) -> Timer {
-        Timer(Some(deadline))
+        Timer {
+            deadline: Some(deadline),
+            registration: None,
+        }
     }
     pub fn after(duration: Duration) -> Timer {
-        Timer(Some(Instant::now() + duration))
+        Self::at(Instant::now() + duration)
     }
     pub fn set_after(&mut self, duration: Duration) {
         *self = Self::after(duration);
     }
     pub async fn wait(&self) {
-        match self.0 {
+        match self.deadline {
             Some(deadline) => {
                 Reactor::current()
                     .wait_for(subscribe_instant(*deadline))
@@ -78,11 +152,31 @@ impl Timer {
 impl Future for Timer {
     type Output = Instant;
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let this = self.as_ref();
-        let pinned = std::pin::pin!(this.wait());
-        match pinned.poll(cx) {
+        let this = self.get_mut();
+        let Some(deadline) = this.deadline else {
+            return Poll::Pending;
+        };
+        let reactor = Reactor::current();
+        let key = *this
+            .registration
+            .get_or_insert_with(|| reactor.register(subscribe_instant(*deadline)));
+        match reactor.poll_ready(key, cx) {
             Poll::Pending => Poll::Pending,
-            Poll::Ready(()) => Poll::Ready(Instant::now()),
+            Poll::Ready(()) => {
+                reactor.deregister(key);
+                this.registration = None;
+                Poll::Ready(Instant::now())
+            }
+        }
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        if let Some(key) = self.registration.take() {
+            if let Some(reactor) = Reactor::try_current() {
+                reactor.deregister(key);
+            }
         }
     }
 }
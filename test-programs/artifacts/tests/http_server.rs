@@ -0,0 +1,269 @@
+use std::convert::Infallible;
+
+use anyhow::{anyhow, Context, Result};
+use http_body_util::{BodyExt, Collected, Full};
+use hyper::body::Bytes;
+use wasmtime::{
+    component::{Component, Linker, ResourceTable},
+    Config, Engine, Store,
+};
+use wasmtime_wasi::{WasiCtx, WasiView};
+use wasmtime_wasi_http::{
+    bindings::http::types::{ErrorCode, Scheme},
+    bindings::Proxy,
+    WasiHttpCtx, WasiHttpView,
+};
+
+struct Ctx {
+    table: ResourceTable,
+    wasi: WasiCtx,
+    http: WasiHttpCtx,
+}
+
+impl WasiView for Ctx {
+    fn table(&mut self) -> &mut ResourceTable {
+        &mut self.table
+    }
+    fn ctx(&mut self) -> &mut WasiCtx {
+        &mut self.wasi
+    }
+}
+
+impl WasiHttpView for Ctx {
+    fn table(&mut self) -> &mut ResourceTable {
+        &mut self.table
+    }
+    fn ctx(&mut self) -> &mut WasiHttpCtx {
+        &mut self.http
+    }
+}
+
+/// Instantiate the component and have it handle a single request.
+async fn handle_in_wasmtime<B>(
+    wasm: &[u8],
+    req: hyper::Request<B>,
+) -> Result<std::result::Result<hyper::Response<Collected<Bytes>>, ErrorCode>>
+where
+    B: hyper::body::Body<Data = Bytes, Error = Infallible> + Send + Sync + 'static,
+{
+    let mut config = Config::default();
+    config.async_support(true);
+    let engine = Engine::new(&config).context("creating engine")?;
+    let component = Component::new(&engine, wasm).context("loading component")?;
+
+    let mut linker: Linker<Ctx> = Linker::new(&engine);
+    wasmtime_wasi::add_to_linker_async(&mut linker).context("add wasi to linker")?;
+    wasmtime_wasi_http::add_only_http_to_linker_async(&mut linker)
+        .context("add wasi-http to linker")?;
+
+    let mut store = Store::new(
+        &engine,
+        Ctx {
+            table: ResourceTable::new(),
+            wasi: WasiCtx::builder().inherit_stdio().build(),
+            http: WasiHttpCtx::new(),
+        },
+    );
+    let proxy = Proxy::instantiate_async(&mut store, &component, &linker).await?;
+
+    let req = req.map(|body| body.map_err(|never| match never {}));
+    let req = store.data_mut().new_incoming_request(Scheme::Http, req)?;
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    let out = store.data_mut().new_response_outparam(sender)?;
+
+    let handle = wasmtime_wasi::runtime::spawn(async move {
+        proxy
+            .wasi_http_incoming_handler()
+            .call_handle(&mut store, req, out)
+            .await
+    });
+
+    let resp = match receiver.await {
+        Ok(Ok(resp)) => {
+            let (parts, body) = resp.into_parts();
+            let collected = body.collect().await?;
+            Some(Ok(hyper::Response::from_parts(parts, collected)))
+        }
+        Ok(Err(e)) => Some(Err(e)),
+        // The component never set the response; `handle` should tell us why.
+        Err(_) => None,
+    };
+    handle.await.context("component execution")?;
+    resp.ok_or_else(|| anyhow!("component never set the response outparam"))
+}
+
+fn request(method: &str, path: &str, body: &'static str) -> Result<hyper::Request<Full<Bytes>>> {
+    Ok(hyper::Request::builder()
+        .method(method)
+        .uri(format!("http://localhost{path}"))
+        .body(Full::new(Bytes::from_static(body.as_bytes())))?)
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn http_server() -> Result<()> {
+    println!("testing {}", test_programs_artifacts::HTTP_SERVER);
+    let wasm = std::fs::read(test_programs_artifacts::HTTP_SERVER).context("read wasm")?;
+
+    let resp = handle_in_wasmtime(&wasm, request("GET", "/", "")?)
+        .await?
+        .map_err(|e| anyhow!("handler failed: {e:?}"))?;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(
+        resp.into_body().to_bytes(),
+        "Hello, wasi:http/proxy world!\n"
+    );
+
+    let resp = handle_in_wasmtime(&wasm, request("POST", "/echo", "hello, server!")?)
+        .await?
+        .map_err(|e| anyhow!("handler failed: {e:?}"))?;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.into_body().to_bytes(), "hello, server!");
+
+    let mut req = request(
+        "POST",
+        "/upload",
+        "--xyz\r\n\
+         Content-Disposition: form-data; name=\"title\"\r\n\r\n\
+         Holiday\r\n\
+         --xyz\r\n\
+         Content-Disposition: form-data; name=\"photo\"; filename=\"beach.jpg\"\r\n\
+         Content-Type: image/jpeg\r\n\r\n\
+         0123456789\r\n\
+         --xyz--\r\n",
+    )?;
+    req.headers_mut()
+        .insert("content-type", "multipart/form-data; boundary=xyz".parse()?);
+    let resp = handle_in_wasmtime(&wasm, req)
+        .await?
+        .map_err(|e| anyhow!("handler failed: {e:?}"))?;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(
+        resp.into_body().to_bytes(),
+        "title: 7 bytes\nphoto: 10 bytes\n"
+    );
+
+    let resp = handle_in_wasmtime(&wasm, request("POST", "/upload", "not a form")?)
+        .await?
+        .map_err(|e| anyhow!("handler failed: {e:?}"))?;
+    assert_eq!(resp.status(), 400);
+
+    let resp = handle_in_wasmtime(&wasm, request("GET", "/nowhere", "")?)
+        .await?
+        .map_err(|e| anyhow!("handler failed: {e:?}"))?;
+    assert_eq!(resp.status(), 404);
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn http_router() -> Result<()> {
+    println!("testing {}", test_programs_artifacts::HTTP_ROUTER);
+    let wasm = std::fs::read(test_programs_artifacts::HTTP_ROUTER).context("read wasm")?;
+
+    for (path, expected) in [
+        ("/api/greet", "Hello, world!\n"),
+        ("/api/greet/wasi", "Hello, wasi!\n"),
+        ("/static/css/site.css", "file: css/site.css\n"),
+        ("/api/version", "1\n"),
+    ] {
+        let resp = handle_in_wasmtime(&wasm, request("GET", path, "")?)
+            .await?
+            .map_err(|e| anyhow!("handler failed: {e:?}"))?;
+        assert_eq!(resp.status(), 200, "GET {path}");
+        assert_eq!(resp.into_body().to_bytes(), expected, "GET {path}");
+    }
+
+    let resp = handle_in_wasmtime(&wasm, request("GET", "/api/nowhere", "")?)
+        .await?
+        .map_err(|e| anyhow!("handler failed: {e:?}"))?;
+    assert_eq!(resp.status(), 404);
+    assert_eq!(resp.into_body().to_bytes(), "no such api\n");
+
+    let resp = handle_in_wasmtime(&wasm, request("GET", "/nowhere", "")?)
+        .await?
+        .map_err(|e| anyhow!("handler failed: {e:?}"))?;
+    assert_eq!(resp.status(), 404);
+    assert_eq!(resp.into_body().to_bytes(), "");
+
+    let resp = handle_in_wasmtime(&wasm, request("HEAD", "/api/greet", "")?)
+        .await?
+        .map_err(|e| anyhow!("handler failed: {e:?}"))?;
+    assert_eq!(resp.status(), 200);
+
+    let resp = handle_in_wasmtime(&wasm, request("POST", "/api/greet", "")?)
+        .await?
+        .map_err(|e| anyhow!("handler failed: {e:?}"))?;
+    assert_eq!(resp.status(), 405);
+    assert_eq!(
+        resp.headers().get("allow").map(|v| v.as_bytes()),
+        Some(&b"GET, HEAD"[..])
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn http_middleware() -> Result<()> {
+    println!("testing {}", test_programs_artifacts::HTTP_MIDDLEWARE);
+    let wasm = std::fs::read(test_programs_artifacts::HTTP_MIDDLEWARE).context("read wasm")?;
+
+    let resp = handle_in_wasmtime(&wasm, request("GET", "/", "")?)
+        .await?
+        .map_err(|e| anyhow!("handler failed: {e:?}"))?;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(
+        resp.headers().get("server").map(|v| v.as_bytes()),
+        Some(&b"wstd"[..])
+    );
+    let id = resp
+        .headers()
+        .get("x-request-id")
+        .context("response has a request id")?;
+    assert_eq!(id.len(), 32);
+
+    let mut req = request("GET", "/custom", "")?;
+    req.headers_mut()
+        .insert("x-request-id", "my-request".parse()?);
+    let resp = handle_in_wasmtime(&wasm, req)
+        .await?
+        .map_err(|e| anyhow!("handler failed: {e:?}"))?;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(
+        resp.headers().get("server").map(|v| v.as_bytes()),
+        Some(&b"custom"[..])
+    );
+    assert_eq!(
+        resp.headers().get("x-request-id").map(|v| v.as_bytes()),
+        Some(&b"my-request"[..])
+    );
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn http_server_trailers() -> Result<()> {
+    println!("testing {}", test_programs_artifacts::HTTP_SERVER);
+    let wasm = std::fs::read(test_programs_artifacts::HTTP_SERVER).context("read wasm")?;
+
+    let mut trailers = hyper::HeaderMap::new();
+    trailers.insert("grpc-status", "0".parse()?);
+    let expected = trailers.clone();
+    let body = Full::new(Bytes::from_static(b"hello, trailers!"))
+        .with_trailers(async move { Some(Ok(trailers)) });
+    let req = hyper::Request::builder()
+        .method("POST")
+        .uri("http://localhost/echo")
+        .body(body)?;
+
+    // The echo route streams the request body, including its trailers, back
+    // into the response.
+    let resp = handle_in_wasmtime(&wasm, req)
+        .await?
+        .map_err(|e| anyhow!("handler failed: {e:?}"))?;
+    assert_eq!(resp.status(), 200);
+    let body = resp.into_body();
+    assert_eq!(body.trailers(), Some(&expected));
+    assert_eq!(body.to_bytes(), "hello, trailers!");
+
+    Ok(())
+}
@@ -106,6 +106,11 @@ fn tcp_echo_server() -> Result<()> {
     tcpstream.write_all(MESSAGE).context("write to socket")?;
     println!("wrote to echo server");
 
+    // The echo server copies until it reads the end of the stream.
+    tcpstream
+        .shutdown(std::net::Shutdown::Write)
+        .context("shut down writes")?;
+
     let mut readback = Vec::new();
     tcpstream
         .read_to_end(&mut readback)
@@ -119,3 +124,85 @@ fn tcp_echo_server() -> Result<()> {
     }
     Ok(())
 }
+
+#[test_log::test]
+fn tcp_graceful_shutdown() -> Result<()> {
+    use std::io::{Read, Write};
+    use std::net::{Shutdown, TcpStream};
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    println!("testing {}", test_programs_artifacts::TCP_GRACEFUL_SHUTDOWN);
+    let wasm =
+        std::fs::read(test_programs_artifacts::TCP_GRACEFUL_SHUTDOWN).context("read wasm")?;
+
+    let pipe = wasmtime_wasi::pipe::MemoryOutputPipe::new(1024 * 1024);
+    let write_end = pipe.clone();
+    let wasmtime_thread = std::thread::spawn(move || run_in_wasmtime(&wasm, Some(write_end)));
+
+    'wait: loop {
+        sleep(Duration::from_millis(100));
+        for line in pipe.contents().split(|c| *c == b'\n') {
+            if line.starts_with(b"Listening on") {
+                break 'wait;
+            }
+        }
+    }
+
+    // This connection stays open while the server shuts down.
+    let mut live = TcpStream::connect("127.0.0.1:8081").context("connect to wasm server")?;
+    live.write_all(b"hello, server!\n")
+        .context("write to socket")?;
+    let mut readback = [0; 15];
+    live.read_exact(&mut readback).context("read from socket")?;
+    assert_eq!(&readback, b"hello, server!\n");
+
+    let mut tcpstream = TcpStream::connect("127.0.0.1:8081").context("connect to wasm server")?;
+    tcpstream
+        .write_all(b"shutdown\n")
+        .context("write to socket")?;
+    tcpstream
+        .shutdown(Shutdown::Write)
+        .context("shut down write half")?;
+    let mut readback = Vec::new();
+    tcpstream
+        .read_to_end(&mut readback)
+        .context("read from socket")?;
+    assert_eq!(readback, b"shutdown\n");
+
+    // The server stops accepting, but waits for the live connection.
+    while !String::from_utf8_lossy(&pipe.contents()).contains("Draining") {
+        sleep(Duration::from_millis(100));
+    }
+    sleep(Duration::from_millis(500));
+    let stdout = String::from_utf8(pipe.contents().to_vec())?;
+    assert!(
+        stdout.contains("Draining 1 connections"),
+        "expected one live connection, got: {stdout}"
+    );
+    assert!(
+        !wasmtime_thread.is_finished(),
+        "server didn't wait: {stdout}"
+    );
+    assert!(
+        !stdout.contains("Shut down"),
+        "server didn't wait: {stdout}"
+    );
+
+    live.write_all(b"goodbye\n").context("write to socket")?;
+    live.shutdown(Shutdown::Write)
+        .context("shut down write half")?;
+    let mut readback = Vec::new();
+    live.read_to_end(&mut readback)
+        .context("read from socket")?;
+    assert_eq!(readback, b"goodbye\n");
+
+    // The server should stop accepting and exit on its own.
+    wasmtime_thread.join().expect("wasmtime panicked")?;
+    let stdout = String::from_utf8(pipe.contents().to_vec())?;
+    assert!(
+        stdout.contains("Shut down"),
+        "expected server to shut down, got: {stdout}"
+    );
+    Ok(())
+}
@@ -0,0 +1 @@
+include!("../../../examples/http_router.rs");
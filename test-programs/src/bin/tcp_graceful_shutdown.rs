@@ -0,0 +1 @@
+include!("../../../examples/tcp_graceful_shutdown.rs");
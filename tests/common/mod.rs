@@ -0,0 +1,175 @@
+//! Helpers shared by the HTTP tests, mainly a stand-in HTTP/1.1 server which
+//! reads one request from each connection it accepts.
+
+// Each test uses only some of these.
+#![allow(dead_code)]
+
+use std::io;
+use wstd::io::{AsyncRead, AsyncWrite};
+use wstd::iter::AsyncIterator;
+use wstd::net::{TcpListener, TcpStream};
+
+/// An empty `200 OK` response.
+pub const OK: &str = "HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n";
+
+/// A request received by the stand-in server.
+#[derive(Debug)]
+pub struct Received {
+    /// The head of the request.
+    pub head: String,
+    /// The body of the request, decoded if it was chunked.
+    pub body: Vec<u8>,
+}
+
+impl Received {
+    /// Returns the value of the header `name`.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.head.lines().find_map(|line| {
+            let (line_name, value) = line.split_once(':')?;
+            line_name.eq_ignore_ascii_case(name).then(|| value.trim())
+        })
+    }
+}
+
+/// Accept the next connection on `listener`.
+pub async fn accept(listener: &TcpListener) -> io::Result<TcpStream> {
+    listener.incoming().next().await.expect("one connection")
+}
+
+/// Read from `stream` until the end of the request head. Returns the head, and
+/// any bytes of the body which were read along with it.
+pub async fn read_head(stream: &mut TcpStream) -> io::Result<(String, Vec<u8>)> {
+    let mut data = Vec::new();
+    let mut buf = [0; 1024];
+    loop {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        data.extend_from_slice(&buf[..n]);
+        if let Some(end) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+            let body = data.split_off(end + 4);
+            data.truncate(end);
+            return Ok((String::from_utf8_lossy(&data).into_owned(), body));
+        }
+    }
+}
+
+/// Read a request from `stream`, whose body is either sized, chunked, or
+/// empty.
+pub async fn read_request(stream: &mut TcpStream) -> io::Result<Received> {
+    let (head, mut data) = read_head(stream).await?;
+    let mut request = Received {
+        head,
+        body: Vec::new(),
+    };
+    let length = request
+        .header("content-length")
+        .map(|len| len.parse::<usize>().expect("valid content-length"));
+    let chunked = request
+        .header("transfer-encoding")
+        .is_some_and(|value| value.eq_ignore_ascii_case("chunked"));
+    let mut buf = [0; 1024];
+    loop {
+        match length {
+            Some(length) if data.len() >= length => {
+                data.truncate(length);
+                request.body = data;
+                return Ok(request);
+            }
+            None if !chunked => return Ok(request),
+            None => {
+                if let Some(body) = dechunk(&data) {
+                    request.body = body;
+                    return Ok(request);
+                }
+            }
+            _ => {}
+        }
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        data.extend_from_slice(&buf[..n]);
+    }
+}
+
+/// Decode a chunked body, or return `None` if it isn't complete yet.
+pub fn dechunk(mut data: &[u8]) -> Option<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let line_end = data.windows(2).position(|w| w == b"\r\n")?;
+        let size = std::str::from_utf8(&data[..line_end]).expect("utf-8 chunk size");
+        let size = usize::from_str_radix(size, 16).expect("valid chunk size");
+        let chunk = &data[line_end + 2..];
+        if size == 0 {
+            // The last chunk is followed by optional trailers and a blank line.
+            let ended = chunk.starts_with(b"\r\n") || chunk.windows(4).any(|w| w == b"\r\n\r\n");
+            return ended.then_some(body);
+        }
+        if chunk.len() < size + 2 {
+            return None;
+        }
+        body.extend_from_slice(&chunk[..size]);
+        data = &chunk[size + 2..];
+    }
+}
+
+/// Accept one connection on `listener`, read a request from it, and send
+/// `response`. Returns the request, and the connection, which stays open
+/// until it is dropped.
+pub async fn respond(
+    listener: &TcpListener,
+    response: impl AsRef<[u8]>,
+) -> io::Result<(Received, TcpStream)> {
+    let mut stream = accept(listener).await?;
+    let request = read_request(&mut stream).await?;
+    stream.write_all(response.as_ref()).await?;
+    stream.flush().await?;
+    Ok((request, stream))
+}
+
+/// Accept one connection on `listener`, read a request from it, send
+/// `response`, and close the connection.
+pub async fn serve_one(listener: &TcpListener, response: impl AsRef<[u8]>) -> io::Result<Received> {
+    let (request, _stream) = respond(listener, response).await?;
+    Ok(request)
+}
+
+/// Respond to one connection on `listener` for each of `responses`, in order,
+/// returning the requests.
+pub async fn serve(listener: &TcpListener, responses: &[&str]) -> io::Result<Vec<Received>> {
+    let mut requests = Vec::new();
+    for response in responses {
+        requests.push(serve_one(listener, response).await?);
+    }
+    Ok(requests)
+}
+
+/// A reader which returns at most `step` bytes at a time.
+pub struct Trickle {
+    data: Vec<u8>,
+    offset: usize,
+    step: usize,
+}
+
+impl Trickle {
+    pub fn new(data: impl Into<Vec<u8>>, step: usize) -> Self {
+        Self {
+            data: data.into(),
+            offset: 0,
+            step,
+        }
+    }
+}
+
+impl AsyncRead for Trickle {
+    async fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = (self.data.len() - self.offset)
+            .min(buf.len())
+            .min(self.step);
+        buf[..len].copy_from_slice(&self.data[self.offset..self.offset + len]);
+        self.offset += len;
+        Ok(len)
+    }
+}
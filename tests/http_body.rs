@@ -0,0 +1,86 @@
+use std::error::Error;
+use wstd::http::{Client, Method, Request};
+use wstd::net::TcpListener;
+
+mod common;
+use common::respond;
+
+#[wstd::test]
+async fn text_charset() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8086").await?;
+    let server = respond(
+        &listener,
+        b"HTTP/1.1 200 OK\r\n\
+          content-type: text/plain; charset=\"ISO-8859-1\"\r\n\
+          content-length: 4\r\n\r\n\
+          caf\xe9",
+    );
+
+    let client = async {
+        let request = Request::new(Method::GET, "http://127.0.0.1:8086/".parse()?);
+        let mut response = Client::new().send(request).await?;
+        Result::<_, Box<dyn Error>>::Ok(response.body().text().await?)
+    };
+
+    let (stream, text) = futures_lite::future::zip(server, client).await;
+    let _stream = stream?;
+    assert_eq!(text?, "café");
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn max_size() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8087").await?;
+    // The content-length is over the limit, so nothing is read.
+    let server = respond(
+        &listener,
+        b"HTTP/1.1 200 OK\r\ncontent-length: 16\r\n\r\n0123456789abcdef",
+    );
+
+    let client = async {
+        let request = Request::new(Method::GET, "http://127.0.0.1:8087/".parse()?);
+        let mut response = Client::new().send(request).await?;
+        let body = response.body();
+        assert_eq!(
+            body.max_size(),
+            Some(wstd::http::body::IncomingBody::DEFAULT_MAX_SIZE)
+        );
+        body.set_max_size(Some(10));
+        Result::<_, Box<dyn Error>>::Ok(body.bytes().await)
+    };
+
+    let (stream, bytes) = futures_lite::future::zip(server, client).await;
+    let _stream = stream?;
+    let err = bytes?.expect_err("body is over the limit");
+    assert!(err.to_string().contains("larger than 10 bytes"), "{err}");
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn chunked_max_size() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8088").await?;
+    // Without a content-length, the limit is hit while reading.
+    let server = respond(
+        &listener,
+        b"HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\n\
+          8\r\n01234567\r\n8\r\n89abcdef\r\n0\r\n\r\n",
+    );
+
+    let client = async {
+        let request = Request::new(Method::GET, "http://127.0.0.1:8088/".parse()?);
+        let mut response = Client::new().send(request).await?;
+        let body = response.body();
+        body.set_max_size(Some(10));
+        let limited = body.bytes().await;
+        Result::<_, Box<dyn Error>>::Ok(limited)
+    };
+
+    let (stream, bytes) = futures_lite::future::zip(server, client).await;
+    let _stream = stream?;
+    let err = bytes?.expect_err("body is over the limit");
+    assert!(err.to_string().contains("larger than 10 bytes"), "{err}");
+
+    Ok(())
+}
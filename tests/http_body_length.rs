@@ -0,0 +1,124 @@
+use std::error::Error;
+use wstd::http::body::StreamingBody;
+use wstd::http::error::ErrorVariant;
+use wstd::http::{Client, Method, Request};
+use wstd::io::{AsyncRead, Cursor};
+use wstd::net::TcpListener;
+
+mod common;
+use common::{accept, serve, serve_one};
+
+/// Accept one connection on `listener`, and read from it until the client
+/// hangs up, without responding.
+async fn drain(listener: &TcpListener) -> std::io::Result<Vec<u8>> {
+    let mut stream = accept(listener).await?;
+    let mut data = Vec::new();
+    let mut buf = [0; 1024];
+    loop {
+        match stream.read(&mut buf).await {
+            Ok(0) | Err(_) => return Ok(data),
+            Ok(n) => data.extend_from_slice(&buf[..n]),
+        }
+    }
+}
+
+#[wstd::test]
+async fn truncated_response() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8092").await?;
+    // Close the connection before the whole body was sent.
+    let server = serve_one(
+        &listener,
+        b"HTTP/1.1 200 OK\r\ncontent-length: 10\r\n\r\nabcd",
+    );
+
+    let client = async {
+        let request = Request::new(Method::GET, "http://127.0.0.1:8092/".parse()?);
+        let mut response = Client::new().send(request).await?;
+        Result::<_, Box<dyn Error>>::Ok(response.body().bytes().await)
+    };
+
+    let (served, bytes) = futures_lite::future::zip(server, client).await;
+    served?;
+    let err = bytes?.expect_err("body is truncated");
+    assert!(
+        matches!(
+            err.variant(),
+            ErrorVariant::BodyTruncated {
+                expected: 10,
+                received: 4
+            }
+        ),
+        "{err:?}"
+    );
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn request_body_too_long() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8093").await?;
+
+    let body = StreamingBody::from_reader(Cursor::new(b"0123456789".to_vec()), Some(5));
+    let request = Request::new(Method::PUT, "http://127.0.0.1:8093/".parse()?).set_body(body);
+    let (received, response) =
+        futures_lite::future::zip(drain(&listener), Client::new().send(request)).await;
+
+    let err = response.expect_err("body is longer than declared");
+    assert!(
+        err.to_string().contains("longer than its content-length"),
+        "{err:?}"
+    );
+    let received = String::from_utf8(received?)?;
+    assert!(!received.contains("56789"), "{received}");
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn request_body_too_short() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8094").await?;
+
+    let body = StreamingBody::from_reader(Cursor::new(b"012".to_vec()), Some(5));
+    let request = Request::new(Method::PUT, "http://127.0.0.1:8094/".parse()?).set_body(body);
+    let (received, response) =
+        futures_lite::future::zip(drain(&listener), Client::new().send(request)).await;
+
+    let err = response.expect_err("body is shorter than declared");
+    assert!(
+        err.to_string().contains("shorter than its content-length"),
+        "{err:?}"
+    );
+    received?;
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn responses_without_body() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8122").await?;
+    // Both responses declare a length, but neither has a body.
+    let server = serve(
+        &listener,
+        &[
+            "HTTP/1.1 200 OK\r\ncontent-length: 10\r\n\r\n",
+            "HTTP/1.1 304 Not Modified\r\ncontent-length: 10\r\n\r\n",
+        ],
+    );
+
+    let client = async {
+        let mut bodies = Vec::new();
+        for method in [Method::HEAD, Method::GET] {
+            let request = Request::new(method, "http://127.0.0.1:8122/".parse()?);
+            let mut response = Client::new().send(request).await?;
+            assert_eq!(response.content_length()?, Some(10));
+            bodies.push(response.body().bytes().await?);
+        }
+        Result::<_, Box<dyn Error>>::Ok(bodies)
+    };
+
+    let (served, bodies) = futures_lite::future::zip(server, client).await;
+    served?;
+    assert_eq!(bodies?, [Vec::<u8>::new(), Vec::new()]);
+
+    Ok(())
+}
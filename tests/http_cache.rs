@@ -0,0 +1,172 @@
+use std::error::Error;
+use wstd::http::{Cache, Client, Method, Request, StatusCode};
+use wstd::net::TcpListener;
+
+mod common;
+use common::serve;
+
+#[wstd::test]
+async fn freshness_and_revalidation() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8114").await?;
+    let server = serve(
+        &listener,
+        &[
+            "HTTP/1.1 200 OK\r\ncache-control: max-age=60\r\netag: \"a\"\r\n\
+             content-length: 3\r\nconnection: close\r\n\r\none",
+            "HTTP/1.1 304 Not Modified\r\ncache-control: max-age=60\r\netag: \"a\"\r\n\
+             x-revalidated: yes\r\nconnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\ncontent-length: 6\r\nconnection: close\r\n\r\nposted",
+            "HTTP/1.1 200 OK\r\ncache-control: no-store\r\ncontent-length: 3\r\n\
+             connection: close\r\n\r\ntwo",
+            "HTTP/1.1 200 OK\r\ncontent-length: 5\r\nconnection: close\r\n\r\nthree",
+        ],
+    );
+
+    let cache = Cache::default();
+    let client = async {
+        let mut client = Client::new();
+        client.set_cache(cache.clone());
+        let url = "http://127.0.0.1:8114/config";
+        let get = |cache_control: Option<&str>| {
+            let mut request = Request::new(Method::GET, url.parse().unwrap());
+            if let Some(value) = cache_control {
+                request
+                    .headers_mut()
+                    .insert("cache-control", value.parse().unwrap());
+            }
+            request
+        };
+
+        let mut bodies = Vec::new();
+        // Received, and then served from the cache.
+        for _ in 0..2 {
+            let mut response = client.send(get(None)).await?;
+            assert_eq!(response.status_code(), StatusCode::Ok);
+            assert!(response.headers().contains_key("age"));
+            bodies.push(response.body().text().await?);
+        }
+        assert_eq!(cache.len(), 1);
+        // Revalidated.
+        let mut response = client.send(get(Some("no-cache"))).await?;
+        assert_eq!(response.status_code(), StatusCode::Ok);
+        assert_eq!(response.headers()["x-revalidated"], "yes");
+        bodies.push(response.body().text().await?);
+        // Invalidated by a POST.
+        let request = Request::new(Method::POST, url.parse()?).set_body("update");
+        client.send(request).await?.body().bytes().await?;
+        assert!(cache.is_empty());
+        // Not stored.
+        for _ in 0..2 {
+            bodies.push(client.send(get(None)).await?.body().text().await?);
+        }
+        Result::<_, Box<dyn Error>>::Ok(bodies)
+    };
+
+    let (requests, bodies) = futures_lite::future::zip(server, client).await;
+    assert_eq!(bodies?, ["one", "one", "one", "two", "three"]);
+    let requests = requests?;
+    assert_eq!(requests[0].header("if-none-match"), None);
+    assert_eq!(requests[1].header("if-none-match"), Some("\"a\""));
+    assert!(
+        requests[2].head.starts_with("POST "),
+        "{}",
+        requests[2].head
+    );
+    assert_eq!(requests[3].header("if-none-match"), None);
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn vary_and_eviction() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8115").await?;
+    let large = format!(
+        "HTTP/1.1 200 OK\r\ncache-control: max-age=60\r\n\
+         content-length: 200\r\nconnection: close\r\n\r\n{}",
+        "x".repeat(200)
+    );
+    let responses = [
+        "HTTP/1.1 200 OK\r\ncache-control: max-age=60\r\nvary: accept-language\r\n\
+             content-length: 5\r\nconnection: close\r\n\r\nhello",
+        "HTTP/1.1 200 OK\r\ncache-control: max-age=60\r\nvary: accept-language\r\n\
+             content-length: 7\r\nconnection: close\r\n\r\nbonjour",
+        &large,
+    ];
+    let server = serve(&listener, &responses);
+
+    let cache = Cache::new(300);
+    let client = async {
+        let mut client = Client::new();
+        client.set_cache(cache.clone());
+        let get = |path: &str, language: Option<&str>| {
+            let url = format!("http://127.0.0.1:8115{path}");
+            let mut request = Request::new(Method::GET, url.parse().unwrap());
+            if let Some(language) = language {
+                request
+                    .headers_mut()
+                    .insert("accept-language", language.parse().unwrap());
+            }
+            request
+        };
+
+        let mut bodies = Vec::new();
+        for language in ["en", "fr", "en", "fr"] {
+            let mut response = client.send(get("/greeting", Some(language))).await?;
+            bodies.push(response.body().text().await?);
+        }
+        assert_eq!(cache.len(), 2);
+
+        let mut only_if_cached = get("/greeting", Some("de"));
+        only_if_cached
+            .headers_mut()
+            .insert("cache-control", "only-if-cached".parse()?);
+        let response = client.send(only_if_cached).await?;
+        assert_eq!(response.status_code(), StatusCode::GatewayTimeout);
+
+        // A larger response evicts the least recently used ones.
+        let mut response = client.send(get("/large", None)).await?;
+        assert_eq!(response.body().bytes().await?.len(), 200);
+        assert_eq!(cache.len(), 1);
+        assert!(cache.size() <= cache.max_size());
+        Result::<_, Box<dyn Error>>::Ok(bodies)
+    };
+
+    let (requests, bodies) = futures_lite::future::zip(server, client).await;
+    assert_eq!(bodies?, ["hello", "bonjour", "hello", "bonjour"]);
+    assert_eq!(requests?.len(), 3);
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn vary_star_is_not_stored() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8124").await?;
+    let server = serve(
+        &listener,
+        &[
+            "HTTP/1.1 200 OK\r\ncache-control: max-age=60\r\nvary: *\r\n\
+             content-length: 3\r\nconnection: close\r\n\r\none",
+            "HTTP/1.1 200 OK\r\ncache-control: max-age=60\r\nvary: *\r\n\
+             content-length: 3\r\nconnection: close\r\n\r\ntwo",
+        ],
+    );
+
+    let cache = Cache::default();
+    let client = async {
+        let mut client = Client::new();
+        client.set_cache(cache.clone());
+        let mut bodies = Vec::new();
+        for _ in 0..2 {
+            let request = Request::new(Method::GET, "http://127.0.0.1:8124/".parse()?);
+            bodies.push(client.send(request).await?.body().text().await?);
+        }
+        assert!(cache.is_empty());
+        Result::<_, Box<dyn Error>>::Ok(bodies)
+    };
+
+    let (requests, bodies) = futures_lite::future::zip(server, client).await;
+    assert_eq!(bodies?, ["one", "two"]);
+    assert_eq!(requests?.len(), 2);
+
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use std::error::Error;
+use std::io::Read;
+use wstd::http::body::{ContentEncoding, StreamingBody};
+use wstd::http::{Client, Method, Request, StatusCode};
+use wstd::io::Cursor;
+use wstd::net::TcpListener;
+
+mod common;
+use common::{serve_one, OK};
+
+fn telemetry() -> String {
+    let mut batch = String::from("[");
+    for i in 0..5000 {
+        batch.push_str(&format!(r#"{{"event":"request","id":{i},"ok":true}},"#));
+    }
+    batch.push_str("{}]");
+    batch
+}
+
+#[wstd::test]
+async fn gzip() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8108").await?;
+    let data = telemetry();
+
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("http://127.0.0.1:8108/")
+        .header("content-type", "application/json")
+        .body(data.clone())?
+        .compress(ContentEncoding::Gzip);
+    let (request, response) =
+        futures_lite::future::zip(serve_one(&listener, OK), Client::new().send(request)).await;
+    assert_eq!(response?.status_code(), StatusCode::Ok);
+
+    let request = request?;
+    assert_eq!(request.header("content-encoding"), Some("gzip"));
+    assert_eq!(request.header("content-type"), Some("application/json"));
+    assert_eq!(request.header("content-length"), None);
+    assert!(
+        request.body.len() < data.len() / 4,
+        "body was not compressed"
+    );
+    let mut decoded = String::new();
+    flate2::read::GzDecoder::new(&request.body[..]).read_to_string(&mut decoded)?;
+    assert!(decoded == data, "body did not round trip");
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn deflate_stream() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8109").await?;
+    let data: Vec<u8> = (0..50_000u32)
+        .map(|i| b'a' + (i / 1000 % 26) as u8)
+        .collect();
+
+    // The length of the uncompressed body isn't sent.
+    let body = StreamingBody::from_reader(Cursor::new(data.clone()), Some(data.len() as u64));
+    let mut request = Request::new(Method::PUT, "http://127.0.0.1:8109/".parse()?).set_body(body);
+    request
+        .headers_mut()
+        .insert("content-length", data.len().into());
+    let request = request.compress(ContentEncoding::Deflate);
+    let (request, response) =
+        futures_lite::future::zip(serve_one(&listener, OK), Client::new().send(request)).await;
+    assert_eq!(response?.status_code(), StatusCode::Ok);
+
+    let request = request?;
+    assert_eq!(request.header("content-encoding"), Some("deflate"));
+    assert_eq!(request.header("content-length"), None);
+    let mut decoded = Vec::new();
+    flate2::read::ZlibDecoder::new(&request.body[..]).read_to_end(&mut decoded)?;
+    assert_eq!(decoded, data);
+
+    Ok(())
+}
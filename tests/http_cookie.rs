@@ -0,0 +1,127 @@
+use std::error::Error;
+use wstd::http::{Client, CookieJar, Method, Request, Uri};
+use wstd::net::TcpListener;
+
+mod common;
+use common::serve;
+
+fn uri(s: &str) -> Uri {
+    s.parse().unwrap()
+}
+
+#[test]
+fn domain_and_path_matching() {
+    let jar = CookieJar::new();
+    let from = uri("https://www.example.com/account/login");
+    jar.set_cookie(&from, "host=1");
+    jar.set_cookie(&from, "domain=2; Domain=example.com; Path=/");
+    jar.set_cookie(&from, "account=3; Path=/account");
+    // Neither a sibling domain nor a top-level domain may be set.
+    jar.set_cookie(&from, "sibling=4; Domain=other.example.com");
+    jar.set_cookie(&from, "tld=5; Domain=com");
+
+    let header = |url: &str| {
+        jar.cookie_header(&uri(url))
+            .map(|h| h.to_str().unwrap().to_owned())
+    };
+    assert_eq!(
+        header("https://www.example.com/account/settings").as_deref(),
+        Some("host=1; account=3; domain=2")
+    );
+    assert_eq!(
+        header("https://www.example.com/accounting").as_deref(),
+        Some("domain=2")
+    );
+    assert_eq!(
+        header("https://api.example.com/").as_deref(),
+        Some("domain=2")
+    );
+    assert_eq!(
+        header("https://other.example.com/"),
+        Some("domain=2".to_owned())
+    );
+    assert_eq!(header("https://example.org/"), None);
+}
+
+#[test]
+fn expiry() {
+    let jar = CookieJar::new();
+    let url = uri("https://example.com/");
+    jar.set_cookie(&url, "a=1; Max-Age=3600");
+    jar.set_cookie(&url, "b=2; Expires=Wed, 21 Oct 2015 07:28:00 GMT");
+    jar.set_cookie(&url, "c=3; Expires=Fri, 01-Jan-2100 00:00:00 GMT");
+    jar.set_cookie(&url, "d=4");
+    assert_eq!(jar.get(&url, "a").as_deref(), Some("1"));
+    assert_eq!(jar.get(&url, "b"), None);
+    assert_eq!(jar.get(&url, "c").as_deref(), Some("3"));
+
+    // Max-Age takes precedence over Expires, and expiring a cookie removes it.
+    jar.set_cookie(
+        &url,
+        "c=3; Max-Age=0; Expires=Fri, 01-Jan-2100 00:00:00 GMT",
+    );
+    assert_eq!(jar.get(&url, "c"), None);
+    // Setting a cookie again replaces it.
+    jar.set_cookie(&url, "d=5");
+    assert_eq!(jar.get(&url, "d").as_deref(), Some("5"));
+}
+
+#[test]
+fn secure_and_http_only() {
+    let jar = CookieJar::new();
+    let https = uri("https://example.com/");
+    let http = uri("http://example.com/");
+    jar.set_cookie(&http, "insecure=1; Secure");
+    jar.set_cookie(&https, "secure=2; Secure; SameSite=None");
+    jar.set_cookie(&https, "lax=3; SameSite=None");
+    jar.set_cookie(&https, "session=4; HttpOnly");
+    jar.set_cookie(&https, "__Host-id=5; Secure; Path=/");
+    jar.set_cookie(&https, "__Host-bad=6; Secure; Domain=example.com");
+
+    assert_eq!(
+        jar.cookie_header(&https).unwrap(),
+        "secure=2; session=4; __Host-id=5"
+    );
+    assert_eq!(jar.cookie_header(&http).unwrap(), "session=4");
+    assert_eq!(jar.get(&https, "session"), None);
+}
+
+#[wstd::test]
+async fn client_sends_cookies() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8103").await?;
+    let server = serve(
+        &listener,
+        &[
+            "HTTP/1.1 200 OK\r\nset-cookie: session=abc; Path=/; HttpOnly\r\n\
+             set-cookie: theme=dark\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+        ],
+    );
+
+    let jar = CookieJar::new();
+    let client = async {
+        let mut client = Client::new();
+        client.set_cookie_jar(jar.clone());
+        let request = Request::new(Method::GET, "http://127.0.0.1:8103/login".parse()?);
+        client.send(request).await?;
+        let request = Request::new(Method::GET, "http://127.0.0.1:8103/app".parse()?);
+        client.send(request).await?;
+        Result::<_, Box<dyn Error>>::Ok(())
+    };
+
+    let (requests, sent) = futures_lite::future::zip(server, client).await;
+    sent?;
+    let requests = requests?;
+    assert_eq!(requests[0].header("cookie"), None);
+    assert_eq!(
+        requests[1].header("cookie"),
+        Some("session=abc; theme=dark")
+    );
+    assert_eq!(
+        jar.get(&"http://127.0.0.1:8103/".parse()?, "theme")
+            .as_deref(),
+        Some("dark")
+    );
+
+    Ok(())
+}
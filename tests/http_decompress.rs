@@ -0,0 +1,156 @@
+use std::error::Error;
+use std::io::Write;
+use wstd::http::{Body, Client, Method, Request};
+use wstd::net::TcpListener;
+
+mod common;
+use common::serve_one;
+
+fn compressed_response(encoding: &str, body: &[u8]) -> Vec<u8> {
+    let mut response = format!(
+        "HTTP/1.1 200 OK\r\ncontent-encoding: {encoding}\r\ncontent-length: {}\r\n\
+         connection: close\r\n\r\n",
+        body.len()
+    )
+    .into_bytes();
+    response.extend_from_slice(body);
+    response
+}
+
+fn text() -> String {
+    let mut text = String::new();
+    for i in 0..2000 {
+        text.push_str(&format!("line {i} of a compressible body\n"));
+    }
+    text
+}
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn zlib(data: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn raw_deflate(data: &[u8]) -> Vec<u8> {
+    let mut encoder =
+        flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn brotli(data: &[u8]) -> Vec<u8> {
+    let mut compressed = Vec::new();
+    {
+        let mut encoder = brotli::CompressorWriter::new(&mut compressed, 4096, 5, 22);
+        encoder.write_all(data).unwrap();
+    }
+    compressed
+}
+
+#[wstd::test]
+async fn decodes_encodings() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8104").await?;
+    let text = text();
+    let bodies = [
+        ("gzip", gzip(text.as_bytes())),
+        ("deflate", zlib(text.as_bytes())),
+        ("deflate", raw_deflate(text.as_bytes())),
+        ("br", brotli(text.as_bytes())),
+    ];
+
+    for (encoding, body) in bodies {
+        let server = serve_one(&listener, compressed_response(encoding, &body));
+        let client = async {
+            let request = Request::new(Method::GET, "http://127.0.0.1:8104/".parse()?);
+            let mut response = Client::new().send(request).await?;
+            let headers = response.headers().clone();
+            let len = response.body().len();
+            let text = response.body().text().await?;
+            Result::<_, Box<dyn Error>>::Ok((headers, len, text))
+        };
+        let (request, response) = futures_lite::future::zip(server, client).await;
+        let (headers, len, decoded) = response?;
+        assert_eq!(
+            request?.header("accept-encoding"),
+            Some("gzip, deflate, br")
+        );
+        assert!(!headers.contains_key("content-encoding"), "{encoding}");
+        assert!(!headers.contains_key("content-length"), "{encoding}");
+        assert_eq!(len, None);
+        assert!(decoded == text, "{encoding} body was not decoded");
+    }
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn opt_out() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8105").await?;
+    let compressed = gzip(b"hello");
+    let server = serve_one(&listener, compressed_response("gzip", &compressed));
+    let client = async {
+        let mut request = Request::new(Method::GET, "http://127.0.0.1:8105/".parse()?);
+        request.set_decompress(false);
+        let mut response = Client::new().send(request).await?;
+        let encoding = response.headers().get("content-encoding").cloned();
+        let body = response.body().bytes().await?;
+        Result::<_, Box<dyn Error>>::Ok((encoding, body))
+    };
+    let (request, response) = futures_lite::future::zip(server, client).await;
+    let (encoding, body) = response?;
+    assert_eq!(request?.header("accept-encoding"), None);
+    assert_eq!(encoding.unwrap(), "gzip");
+    assert_eq!(body, compressed);
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn explicit_accept_encoding() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8106").await?;
+    let compressed = gzip(b"hello");
+    let server = serve_one(&listener, compressed_response("gzip", &compressed));
+    let client = async {
+        let request = Request::builder()
+            .uri("http://127.0.0.1:8106/")
+            .header("accept-encoding", "gzip")
+            .build()?;
+        let mut response = Client::new().send(request).await?;
+        Result::<_, Box<dyn Error>>::Ok(response.body().bytes().await?)
+    };
+    let (request, body) = futures_lite::future::zip(server, client).await;
+    assert_eq!(request?.header("accept-encoding"), Some("gzip"));
+    // The caller asked for the encoding, so they get the encoded body.
+    assert_eq!(body?, compressed);
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn truncated() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8107").await?;
+    let text = text();
+    let compressed = gzip(text.as_bytes());
+    let truncated = &compressed[..compressed.len() - 4];
+    let server = serve_one(&listener, compressed_response("gzip", truncated));
+    let client = async {
+        let request = Request::new(Method::GET, "http://127.0.0.1:8107/".parse()?);
+        let mut response = Client::new().send(request).await?;
+        Result::<_, Box<dyn Error>>::Ok(response.body().bytes().await)
+    };
+    let (request, result) = futures_lite::future::zip(server, client).await;
+    request?;
+    let err = result?.expect_err("truncated body should fail");
+    assert!(
+        format!("{err:?}").contains("compressed body ended early"),
+        "{err:?}"
+    );
+
+    Ok(())
+}
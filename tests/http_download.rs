@@ -0,0 +1,104 @@
+use std::error::Error;
+use wstd::http::download::Download;
+use wstd::http::{Client, Method, Request, RetryPolicy};
+use wstd::io::Cursor;
+use wstd::net::TcpListener;
+use wstd::time::Duration;
+
+mod common;
+use common::serve;
+
+fn download(url: &str) -> Download {
+    let request = Request::new(Method::GET, url.parse().unwrap());
+    Download::new(request).with_retry_policy(
+        RetryPolicy::new(3).with_backoff(Duration::from_millis(1), Duration::from_millis(10)),
+    )
+}
+
+#[wstd::test]
+async fn resume() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8116").await?;
+    let server = serve(
+        &listener,
+        &[
+            // The connection is closed after the first four bytes.
+            "HTTP/1.1 200 OK\r\netag: \"v1\"\r\naccept-ranges: bytes\r\n\
+             content-length: 10\r\nconnection: close\r\n\r\n0123",
+            "HTTP/1.1 206 Partial Content\r\netag: \"v1\"\r\ncontent-range: bytes 4-9/10\r\n\
+             content-length: 6\r\nconnection: close\r\n\r\n456789",
+        ],
+    );
+
+    let mut file = Cursor::new(b"header:".to_vec());
+    file.set_position(7);
+    let client = Client::new();
+    let download = download("http://127.0.0.1:8116/artifact");
+    let len = download.send(&client, &mut file);
+
+    let (requests, len) = futures_lite::future::zip(server, len).await;
+    assert_eq!(len?, 10);
+    assert_eq!(file.into_inner(), b"header:0123456789");
+    let requests = requests?;
+    assert_eq!(requests[0].header("accept-encoding"), Some("identity"));
+    assert_eq!(requests[0].header("range"), None);
+    assert_eq!(requests[1].header("range"), Some("bytes=4-"));
+    assert_eq!(requests[1].header("if-range"), Some("\"v1\""));
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn resource_changed() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8117").await?;
+    let server = serve(
+        &listener,
+        &[
+            "HTTP/1.1 200 OK\r\nlast-modified: Sun, 06 Nov 1994 08:49:37 GMT\r\n\
+             content-length: 10\r\nconnection: close\r\n\r\n0123",
+            // `If-Range` didn't match, so the whole new body is sent.
+            "HTTP/1.1 200 OK\r\netag: \"v2\"\r\ncontent-length: 8\r\n\
+             connection: close\r\n\r\nabcdefgh",
+        ],
+    );
+
+    let mut file = Cursor::new(Vec::new());
+    let client = Client::new();
+    let download = download("http://127.0.0.1:8117/artifact");
+    let len = download.send(&client, &mut file);
+
+    let (requests, len) = futures_lite::future::zip(server, len).await;
+    assert_eq!(len?, 8);
+    assert_eq!(file.into_inner(), b"abcdefgh");
+    assert_eq!(
+        requests?[1].header("if-range"),
+        Some("Sun, 06 Nov 1994 08:49:37 GMT")
+    );
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn invalid_range() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8118").await?;
+    let server = serve(
+        &listener,
+        &[
+            "HTTP/1.1 200 OK\r\netag: \"v1\"\r\ncontent-length: 10\r\n\
+             connection: close\r\n\r\n0123",
+            "HTTP/1.1 206 Partial Content\r\netag: \"v1\"\r\ncontent-range: bytes 2-9/10\r\n\
+             content-length: 8\r\nconnection: close\r\n\r\n23456789",
+        ],
+    );
+
+    let mut file = Cursor::new(Vec::new());
+    let client = Client::new();
+    let download = download("http://127.0.0.1:8118/artifact");
+    let len = download.send(&client, &mut file);
+
+    let (requests, len) = futures_lite::future::zip(server, len).await;
+    requests?;
+    let err = len.unwrap_err();
+    assert!(err.to_string().contains("starts at byte 2, not 4"), "{err}");
+
+    Ok(())
+}
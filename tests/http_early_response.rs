@@ -0,0 +1,84 @@
+use std::error::Error;
+use wstd::http::{Client, HeaderValue, Method, Request, StatusCode};
+use wstd::io::{AsyncRead, AsyncWrite};
+use wstd::net::TcpListener;
+
+mod common;
+use common::{accept, read_head};
+
+#[wstd::test]
+async fn rejected_upload() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8084").await?;
+    // Reject the upload without reading its body.
+    let server = async {
+        let mut stream = accept(&listener).await?;
+        read_head(&mut stream).await?;
+        stream
+            .write_all(b"HTTP/1.1 413 Content Too Large\r\ncontent-length: 0\r\n\r\n")
+            .await?;
+        stream.flush().await?;
+        std::io::Result::Ok(stream)
+    };
+
+    let body = vec![b'x'; 64 * 1024 * 1024];
+    let request =
+        Request::new(Method::POST, "http://127.0.0.1:8084/upload".parse()?).set_body(body);
+    let client = async { Client::new().send(request).await };
+
+    let (stream, response) = futures_lite::future::zip(server, client).await;
+    let _stream = stream?;
+    assert_eq!(response?.status_code(), StatusCode::ContentTooLarge);
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn response_before_body() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8085").await?;
+    // Send the response head before reading the body, then echo the body.
+    let server = async {
+        let mut stream = accept(&listener).await?;
+        let (_, mut body) = read_head(&mut stream).await?;
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\n")
+            .await?;
+        stream.flush().await?;
+        let mut buf = [0; 1024];
+        while body.len() < 5 {
+            match stream.read(&mut buf).await? {
+                0 => break,
+                n => body.extend_from_slice(&buf[..n]),
+            }
+        }
+        stream
+            .write_all(format!("{:x}\r\n", body.len()).as_bytes())
+            .await?;
+        stream.write_all(&body).await?;
+        stream.write_all(b"\r\n0\r\n\r\n").await?;
+        stream.flush().await?;
+        std::io::Result::Ok(stream)
+    };
+
+    let client = async {
+        let mut request = Request::new(Method::POST, "http://127.0.0.1:8085/".parse()?);
+        request
+            .headers_mut()
+            .insert("content-length", HeaderValue::from_static("5"));
+        let (mut body, response) = Client::new().start_request(request)?;
+        let mut response = response.await?;
+        body.write_all(b"hello").await?;
+        body.finish()?;
+
+        let mut response_body = Vec::new();
+        response.body().read_to_end(&mut response_body).await?;
+        Result::<_, Box<dyn Error>>::Ok((response.status_code(), response_body))
+    };
+
+    let (stream, response) = futures_lite::future::zip(server, client).await;
+    let _stream = stream?;
+    let (status, response_body) = response?;
+    assert_eq!(status, StatusCode::Ok);
+    assert_eq!(response_body, b"hello");
+
+    Ok(())
+}
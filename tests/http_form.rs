@@ -0,0 +1,174 @@
+use std::error::Error;
+use wstd::http::body::{Body, Form, IntoBody, Multipart, MultipartReader, Part};
+use wstd::http::{Client, HeaderValue, Method, Request, StatusCode};
+use wstd::io::{AsyncRead, Cursor};
+use wstd::net::TcpListener;
+
+mod common;
+use common::{serve_one, Trickle, OK};
+
+#[wstd::test]
+async fn form() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8110").await?;
+    let form: Form = [("q", "wasi components"), ("lang", "en&fr")]
+        .into_iter()
+        .collect();
+    let request = Request::new(Method::POST, "http://127.0.0.1:8110/".parse()?).form(form);
+    let (request, response) =
+        futures_lite::future::zip(serve_one(&listener, OK), Client::new().send(request)).await;
+    assert_eq!(response?.status_code(), StatusCode::Ok);
+
+    let request = request?;
+    assert_eq!(
+        request.header("content-type"),
+        Some("application/x-www-form-urlencoded")
+    );
+    assert_eq!(request.header("content-length"), Some("30"));
+    assert_eq!(request.body, b"q=wasi+components&lang=en%26fr");
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn multipart_round_trip() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8111").await?;
+    let file: Vec<u8> = (0..20_000u32).map(|i| i as u8).collect();
+    let form = Multipart::new()
+        .text("title", "Holiday \"photos\"")
+        .file(
+            "photo",
+            "beach.jpg",
+            "image/jpeg",
+            Cursor::new(file.clone()),
+        )
+        .part(Part::text("note", "café").content_type("text/plain; charset=utf-8"));
+    let boundary = form.boundary().to_owned();
+    let request = Request::new(Method::POST, "http://127.0.0.1:8111/".parse()?).multipart(form);
+    let (request, response) =
+        futures_lite::future::zip(serve_one(&listener, OK), Client::new().send(request)).await;
+    assert_eq!(response?.status_code(), StatusCode::Ok);
+
+    let request = request?;
+    let content_type = request.header("content-type").expect("content-type header");
+    assert_eq!(
+        content_type,
+        format!("multipart/form-data; boundary={boundary}")
+    );
+    // The length of the file isn't known, so the body is chunked.
+    assert_eq!(request.header("transfer-encoding"), Some("chunked"));
+
+    let content_type = HeaderValue::from_str(content_type)?;
+    let mut reader = MultipartReader::from_content_type(Cursor::new(request.body), &content_type)?;
+
+    let mut field = reader.next_field().await?.expect("title field");
+    assert_eq!(field.name(), Some("title"));
+    assert_eq!(field.file_name(), None);
+    assert_eq!(field.text().await?, "Holiday \"photos\"");
+
+    let mut field = reader.next_field().await?.expect("photo field");
+    assert_eq!(field.name(), Some("photo"));
+    assert_eq!(field.file_name(), Some("beach.jpg"));
+    assert_eq!(field.content_type(), Some("image/jpeg"));
+    assert_eq!(field.bytes().await?, file);
+
+    let mut field = reader.next_field().await?.expect("note field");
+    assert_eq!(field.name(), Some("note"));
+    assert_eq!(field.text().await?, "café");
+
+    assert!(reader.next_field().await?.is_none());
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn multipart_length() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8112").await?;
+    let form = Multipart::new()
+        .text("a", "1")
+        .part(Part::bytes("b", vec![0; 100]).file_name("b.bin"));
+    let request = Request::new(Method::POST, "http://127.0.0.1:8112/".parse()?).multipart(form);
+    let (request, response) =
+        futures_lite::future::zip(serve_one(&listener, OK), Client::new().send(request)).await;
+    assert_eq!(response?.status_code(), StatusCode::Ok);
+
+    // Every part has a known length, so the body has a Content-Length.
+    let request = request?;
+    assert_eq!(
+        request.header("content-length"),
+        Some(request.body.len().to_string().as_str())
+    );
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn multipart_len_is_declared_length() -> Result<(), Box<dyn Error>> {
+    let mut body = Multipart::new().text("a", "1").into_body();
+    let len = body.len().expect("every part has a known length");
+    let mut data = Vec::new();
+    body.read_to_end(&mut data).await?;
+    assert_eq!(data.len(), len);
+    assert_eq!(body.len(), Some(len));
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn parse_multipart() -> Result<(), Box<dyn Error>> {
+    let body = b"This is a preamble.\r\n\
+        --xyz\r\n\
+        Content-Disposition: form-data; name=\"skipped\"\r\n\
+        \r\n\
+        this field is not read\r\n\
+        --xyz\r\n\
+        Content-Disposition: form-data; inline; name=\"upload\"; filename=\"a \\\"b\\\";.txt\"\r\n\
+        Content-Type: text/plain\r\n\
+        \r\n\
+        line one\r\n--xy not a boundary\r\nline three\r\n\
+        --xyz\r\n\
+        Content-Disposition: form-data; name=empty\r\n\
+        \r\n\
+        \r\n\
+        --xyz--\r\n\
+        This is an epilogue.";
+
+    for step in [1, 3, 7, 1024] {
+        let reader = Trickle::new(body.to_vec(), step);
+        let mut reader = MultipartReader::new(reader, "xyz");
+
+        let field = reader.next_field().await?.expect("skipped field");
+        assert_eq!(field.name(), Some("skipped"));
+
+        let mut field = reader.next_field().await?.expect("upload field");
+        assert_eq!(field.name(), Some("upload"));
+        assert_eq!(field.file_name(), Some("a \"b\";.txt"));
+        assert_eq!(field.content_type(), Some("text/plain"));
+        assert_eq!(
+            field.text().await?,
+            "line one\r\n--xy not a boundary\r\nline three"
+        );
+
+        let mut field = reader.next_field().await?.expect("empty field");
+        assert_eq!(field.name(), Some("empty"));
+        assert_eq!(field.bytes().await?, b"");
+
+        assert!(reader.next_field().await?.is_none());
+        assert!(reader.next_field().await?.is_none());
+    }
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn parse_truncated() -> Result<(), Box<dyn Error>> {
+    let body = b"--xyz\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\nno end".to_vec();
+    let mut reader = MultipartReader::new(Cursor::new(body), "xyz");
+    let mut field = reader.next_field().await?.expect("field");
+    let err = field.bytes().await.expect_err("body ended early");
+    assert!(format!("{err:?}").contains("ended early"), "{err:?}");
+
+    let content_type = HeaderValue::from_static("text/plain");
+    assert!(MultipartReader::from_content_type(Cursor::new(Vec::new()), &content_type).is_err());
+
+    Ok(())
+}
@@ -1,56 +1,39 @@
 use std::error::Error;
-use wstd::http::{Body, Client, HeaderValue, Method, Request};
-use wstd::io::AsyncRead;
+use wstd::http::transport::{Mock, MockResponse, MockTransport};
+use wstd::http::{Client, HeaderValue, Method, Request, StatusCode};
 
 #[wstd::test]
 async fn main() -> Result<(), Box<dyn Error>> {
+    let mock = MockTransport::new();
+    mock.add(
+        Mock::new().method(Method::GET).path("/get").respond(
+            MockResponse::new(StatusCode::Ok)
+                .header("content-type", "text/plain; charset=iso-8859-1")
+                .body(&b"caf\xe9"[..]),
+        ),
+    );
+    let mut client = Client::new();
+    client.set_transport(mock.clone());
+
     let mut request = Request::new(Method::GET, "https://postman-echo.com/get".parse()?);
     request
         .headers_mut()
         .insert("my-header", HeaderValue::from_str("my-value")?);
 
-    let mut response = Client::new().send(request).await?;
-
-    let content_type = response
-        .headers()
-        .get("Content-Type")
-        .ok_or_else(|| "response expected to have Content-Type header")?;
-    assert_eq!(content_type, "application/json; charset=utf-8");
-
-    let body = response.body();
-    let body_len = body
-        .len()
-        .ok_or_else(|| "GET postman-echo.com/get is supposed to provide a content-length")?;
-
-    let mut body_buf = Vec::new();
-    body.read_to_end(&mut body_buf).await?;
-
-    assert_eq!(
-        body_buf.len(),
-        body_len,
-        "read_to_end length should match content-length"
-    );
-
-    let val: serde_json::Value = serde_json::from_slice(&body_buf)?;
-    let body_url = val
-        .get("url")
-        .ok_or_else(|| "body json has url")?
-        .as_str()
-        .ok_or_else(|| "body json url is str")?;
-    assert!(
-        body_url.contains("postman-echo.com/get"),
-        "expected body url to contain the authority and path, got: {body_url}"
-    );
+    let mut response = client.send(request).await?;
+    assert_eq!(response.status_code(), StatusCode::Ok);
+    // The body is decoded according to its charset.
+    assert_eq!(response.body().text().await?, "café");
 
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].method(), Method::GET);
     assert_eq!(
-        val.get("headers")
-            .ok_or_else(|| "body json has headers")?
-            .get("my-header")
-            .ok_or_else(|| "headers contains my-header")?
-            .as_str()
-            .ok_or_else(|| "my-header is a str")?,
-        "my-value"
+        requests[0].uri().to_string(),
+        "https://postman-echo.com/get"
     );
+    assert_eq!(requests[0].headers()["my-header"], "my-value");
+    assert!(requests[0].body().is_empty());
 
     Ok(())
 }
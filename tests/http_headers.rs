@@ -0,0 +1,120 @@
+use std::error::Error;
+use wstd::http::{ETag, Method, Mime, Request, Response, StatusCode};
+use wstd::io::empty;
+use wstd::time::{Duration, SystemTime};
+
+#[test]
+fn mime() -> Result<(), Box<dyn Error>> {
+    let mime: Mime = "application/problem+json; charset=UTF-8; profile=\"a b\"".parse()?;
+    assert_eq!(mime.type_(), "application");
+    assert_eq!(mime.subtype(), "problem+json");
+    assert_eq!(mime.suffix(), Some("json"));
+    assert_eq!(mime.charset(), Some("UTF-8"));
+    assert_eq!(mime.param("Profile"), Some("a b"));
+    assert_eq!(
+        mime.to_string(),
+        "application/problem+json; charset=UTF-8; profile=\"a b\""
+    );
+    assert_eq!(mime.to_string().parse::<Mime>()?, mime);
+
+    assert!("text/*".parse::<Mime>()?.matches(&"text/plain".parse()?));
+    assert!(!"text/*".parse::<Mime>()?.matches(&"image/png".parse()?));
+    for invalid in ["", "text", "text/", "text/plain; charset", "a b/c"] {
+        assert!(invalid.parse::<Mime>().is_err(), "{invalid}");
+    }
+    Ok(())
+}
+
+#[test]
+fn request_headers() -> Result<(), Box<dyn Error>> {
+    let mut request = Request::new(Method::GET, "http://example.com/".parse()?);
+    request.headers_mut().insert(
+        "accept",
+        "text/html, application/json;q=0.9, */*;q=0.1".parse()?,
+    );
+    let accept = request.accept()?;
+    assert_eq!(accept.len(), 3);
+    assert_eq!(accept[1].essence(), "application/json");
+    assert_eq!(accept[1].param("q"), Some("0.9"));
+    assert!(accept[2].matches(&Mime::new("image", "png")?));
+
+    request.set_user_agent("wstd-test/1.0")?;
+    assert_eq!(request.user_agent()?, Some("wstd-test/1.0"));
+    request.set_content_type(&Mime::new("text", "plain")?.with_param("charset", "utf-8")?);
+    assert_eq!(
+        request.headers()["content-type"],
+        "text/plain; charset=utf-8"
+    );
+
+    request.set_basic_auth("user", Some("pass"));
+    assert_eq!(request.headers()["authorization"], "Basic dXNlcjpwYXNz");
+    assert!(request.headers()["authorization"].is_sensitive());
+    request.set_bearer_auth("token")?;
+    assert_eq!(request.headers()["authorization"], "Bearer token");
+    assert!(request.set_bearer_auth("bad\ntoken").is_err());
+
+    let request = Request::builder()
+        .uri("http://example.com/")
+        .basic_auth("Aladdin", Some("open sesame"))
+        .body(empty())?;
+    assert_eq!(
+        request.headers()["authorization"],
+        "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ=="
+    );
+    assert!(Request::builder()
+        .uri("http://example.com/")
+        .bearer_auth("bad\ntoken")
+        .body(empty())
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn response_headers() -> Result<(), Box<dyn Error>> {
+    let mut response = Response::new(StatusCode::Ok);
+    assert_eq!(response.content_type()?, None);
+    assert_eq!(response.etag()?, None);
+
+    let date = SystemTime::UNIX_EPOCH + Duration::from_secs(784111777);
+    response.set_date(date);
+    assert_eq!(response.headers()["date"], "Sun, 06 Nov 1994 08:49:37 GMT");
+    assert_eq!(response.date()?, Some(date));
+
+    response.set_etag(&ETag::weak("v1")?);
+    assert_eq!(response.headers()["etag"], "W/\"v1\"");
+    let etag = response.etag()?.unwrap();
+    assert!(etag.weak_eq(&ETag::strong("v1")?));
+    assert!(!etag.strong_eq(&ETag::strong("v1")?));
+    assert!("v1".parse::<ETag>().is_err());
+
+    response
+        .headers_mut()
+        .insert("content-length", "12".parse()?);
+    assert_eq!(response.content_length()?, Some(12));
+    // Invalid headers are errors, not panics.
+    response
+        .headers_mut()
+        .insert("content-length", "-1".parse()?);
+    let err = response.content_length().unwrap_err();
+    assert!(err.to_string().contains("content-length"), "{err}");
+    response
+        .headers_mut()
+        .insert("content-length", "+5".parse()?);
+    assert!(response.content_length().is_err());
+    response
+        .headers_mut()
+        .insert("content-type", "text".parse()?);
+    assert!(response.content_type().is_err());
+    response.headers_mut().insert("date", "yesterday".parse()?);
+    assert!(response.date().is_err());
+    for (date, valid) in [
+        ("Thu, 29 Feb 2024 00:00:00 GMT", true),
+        ("Wed, 29 Feb 2023 00:00:00 GMT", false),
+        ("Sat, 31 Feb 2024 00:00:00 GMT", false),
+        ("Thu, 31 Apr 2024 00:00:00 GMT", false),
+    ] {
+        response.headers_mut().insert("date", date.parse()?);
+        assert_eq!(response.date().is_ok(), valid, "{date}");
+    }
+    Ok(())
+}
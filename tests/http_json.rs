@@ -0,0 +1,48 @@
+use std::error::Error;
+use wstd::http::{Client, Method, Request};
+use wstd::io::AsyncWrite;
+use wstd::net::TcpListener;
+
+mod common;
+use common::{accept, read_request};
+
+#[wstd::test]
+async fn json_round_trip() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8089").await?;
+    // Echo the request body back, along with its content-type.
+    let server = async {
+        let mut stream = accept(&listener).await?;
+        let request = read_request(&mut stream).await?;
+        assert!(
+            request.header("content-length").is_some(),
+            "{}",
+            request.head
+        );
+        let content_type = request.header("content-type").unwrap_or_default();
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: {content_type}\r\ncontent-length: {}\r\n\r\n",
+            request.body.len()
+        );
+        stream.write_all(response.as_bytes()).await?;
+        stream.write_all(&request.body).await?;
+        stream.flush().await?;
+        std::io::Result::Ok(stream)
+    };
+
+    let client = async {
+        let request = Request::new(Method::POST, "http://127.0.0.1:8089/".parse()?)
+            .json(&serde_json::json!({ "test": "data" }))?;
+        let mut response = Client::new().send(request).await?;
+        let content_type = response.headers().get("content-type").cloned();
+        let value: serde_json::Value = response.body().json().await?;
+        Result::<_, Box<dyn Error>>::Ok((content_type, value))
+    };
+
+    let (stream, response) = futures_lite::future::zip(server, client).await;
+    let _stream = stream?;
+    let (content_type, value) = response?;
+    assert_eq!(content_type.unwrap(), "application/json");
+    assert_eq!(value, serde_json::json!({ "test": "data" }));
+
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use std::error::Error;
+use wstd::http::middleware::{DefaultHeadersLayer, RequestIdLayer};
+use wstd::http::transport::{Mock, MockResponse, MockTransport};
+use wstd::http::{Client, HeaderMap, HeaderValue, Method, Request, Service, StatusCode};
+
+#[wstd::test]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let mock = MockTransport::new();
+    mock.add(Mock::new().respond(MockResponse::new(StatusCode::Ok)));
+    let mut client = Client::new();
+    client.set_transport(mock.clone());
+
+    let mut headers = HeaderMap::new();
+    headers.insert("my-header", HeaderValue::from_static("default-value"));
+    let client = client
+        .layer(DefaultHeadersLayer::new(headers))
+        .layer(RequestIdLayer::new());
+
+    let request = Request::new(Method::GET, "https://postman-echo.com/get".parse()?);
+    let response = client.call(request).await?;
+    assert_eq!(response.status_code(), StatusCode::Ok);
+
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 1);
+    let headers = requests[0].headers();
+    assert_eq!(headers["my-header"], "default-value");
+    let request_id = headers
+        .get("x-request-id")
+        .ok_or("headers contains x-request-id")?
+        .to_str()?;
+    assert_eq!(request_id.len(), 32);
+
+    Ok(())
+}
@@ -0,0 +1,170 @@
+use std::error::Error;
+use wstd::http::error::{ErrorVariant, WasiHttpErrorCode};
+use wstd::http::transport::{Mock, MockResponse, MockTransport};
+use wstd::http::{Client, Method, Request, RetryPolicy, StatusCode};
+use wstd::io::AsyncRead;
+use wstd::time::Duration;
+
+fn client(mock: &MockTransport) -> Client {
+    let mut client = Client::new();
+    client.set_transport(mock.clone());
+    client
+}
+
+#[wstd::test]
+async fn canned_response() -> Result<(), Box<dyn Error>> {
+    let mock = MockTransport::new();
+    mock.add(
+        Mock::new()
+            .method(Method::GET)
+            .path("/get")
+            .header("my-header", "my-value")
+            .times(1)
+            .respond(
+                MockResponse::new(StatusCode::Ok)
+                    .header("content-type", "application/json; charset=utf-8")
+                    .body(r#"{"url":"https://example.com/get"}"#),
+            ),
+    );
+
+    let mut request = Request::new(Method::GET, "https://example.com/get".parse()?);
+    request
+        .headers_mut()
+        .insert("my-header", "my-value".parse()?);
+    let mut response = client(&mock).send(request).await?;
+    assert_eq!(
+        response.headers()["content-type"],
+        "application/json; charset=utf-8"
+    );
+    assert_eq!(response.content_length()?, Some(33));
+    let body = response.body().text().await?;
+    assert_eq!(body, r#"{"url":"https://example.com/get"}"#);
+
+    mock.verify();
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].uri().to_string(), "https://example.com/get");
+    assert_eq!(requests[0].headers()["my-header"], "my-value");
+
+    // The mock only answers one request, so this one matches nothing.
+    let request = Request::new(Method::GET, "https://example.com/get".parse()?);
+    let err = client(&mock).send(request).await.unwrap_err();
+    assert!(err.to_string().contains("no mock matches"), "{err}");
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn received_body() -> Result<(), Box<dyn Error>> {
+    let mock = MockTransport::new();
+    mock.add(
+        Mock::new()
+            .method(Method::GET)
+            .respond(MockResponse::new(StatusCode::MethodNotAllowed)),
+    );
+    mock.add(
+        Mock::new()
+            .path("/echo?lang=en")
+            .respond(MockResponse::new(StatusCode::Created)),
+    );
+
+    let request =
+        Request::new(Method::POST, "http://example.com/echo?lang=en".parse()?).set_body("hello");
+    let response = client(&mock).send(request).await?;
+    assert_eq!(response.status_code(), StatusCode::Created);
+    assert_eq!(mock.requests()[0].body(), b"hello");
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn scripted_retry() -> Result<(), Box<dyn Error>> {
+    let mock = MockTransport::new();
+    mock.add(
+        Mock::new()
+            .times(1)
+            .fail(WasiHttpErrorCode::ConnectionRefused),
+    );
+    mock.add(
+        Mock::new()
+            .times(1)
+            .respond(MockResponse::new(StatusCode::ServiceUnavailable)),
+    );
+    mock.add(Mock::new().respond(MockResponse::new(StatusCode::Ok).body("ok")));
+
+    let mut client = client(&mock);
+    client.set_retry_policy(
+        RetryPolicy::new(3).with_backoff(Duration::from_millis(1), Duration::from_millis(1)),
+    );
+    let request = Request::new(Method::GET, "http://example.com/flaky".parse()?);
+    let mut response = client.send(request).await?;
+    assert_eq!(response.body().text().await?, "ok");
+    assert_eq!(mock.requests().len(), 3);
+    mock.verify();
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn streaming_body() -> Result<(), Box<dyn Error>> {
+    let mock = MockTransport::new();
+    mock.add(
+        Mock::new().path("/stream").respond(
+            MockResponse::new(StatusCode::Ok)
+                .chunk("one,")
+                .delay(Duration::from_millis(10))
+                .chunk("two")
+                .trailer("checksum", "abc"),
+        ),
+    );
+    mock.add(
+        Mock::new().path("/broken").respond(
+            MockResponse::new(StatusCode::Ok)
+                .chunk("one,")
+                .fail(WasiHttpErrorCode::ConnectionTerminated),
+        ),
+    );
+    let client = client(&mock);
+
+    let request = Request::new(Method::GET, "http://example.com/stream".parse()?);
+    let mut response = client.send(request).await?;
+    assert_eq!(response.content_length()?, None);
+    let body = response.body();
+    let mut chunk = [0; 16];
+    assert_eq!(body.read(&mut chunk).await?, 4);
+    assert_eq!(&chunk[..4], b"one,");
+    assert_eq!(body.text().await?, "two");
+    let trailers = body.trailers().await?.ok_or("trailers")?;
+    assert_eq!(trailers["checksum"], "abc");
+
+    let request = Request::new(Method::GET, "http://example.com/broken".parse()?);
+    let mut response = client.send(request).await?;
+    let err = response.body().bytes().await.unwrap_err();
+    assert!(
+        matches!(
+            err.variant(),
+            ErrorVariant::WasiHttp(WasiHttpErrorCode::ConnectionTerminated)
+        ),
+        "{err:?}"
+    );
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn timeout() -> Result<(), Box<dyn Error>> {
+    let mock = MockTransport::new();
+    mock.add(
+        Mock::new()
+            .delay(Duration::from_millis(500))
+            .respond(MockResponse::new(StatusCode::Ok)),
+    );
+
+    let mut client = client(&mock);
+    client.set_timeout(Duration::from_millis(50));
+    let request = Request::new(Method::GET, "http://example.com/slow".parse()?);
+    let err = client.send(request).await.unwrap_err();
+    assert!(matches!(err.variant(), ErrorVariant::Timeout), "{err:?}");
+
+    Ok(())
+}
@@ -1,52 +1,50 @@
 use std::error::Error;
-use wstd::http::{Client, HeaderValue, Method, Request};
-use wstd::io::AsyncRead;
+use wstd::http::transport::{Mock, MockResponse, MockTransport};
+use wstd::http::{Client, HeaderValue, Method, Request, StatusCode};
 
 #[wstd::test]
 async fn main() -> Result<(), Box<dyn Error>> {
+    let mock = MockTransport::new();
+    mock.add(
+        Mock::new()
+            .method(Method::POST)
+            .path("/post")
+            .header("content-type", "application/json; charset=utf-8")
+            .respond(MockResponse::new(StatusCode::Ok)),
+    );
+    let mut client = Client::new();
+    client.set_transport(mock.clone());
+
     let mut request = Request::new(Method::POST, "https://postman-echo.com/post".parse()?);
     request.headers_mut().insert(
         "content-type",
         HeaderValue::from_str("application/json; charset=utf-8")?,
     );
 
-    let mut response = Client::new()
+    let response = client
         .send(request.set_body("{\"test\": \"data\"}"))
         .await?;
+    assert_eq!(response.status_code(), StatusCode::Ok);
 
-    let content_type = response
-        .headers()
-        .get("Content-Type")
-        .ok_or_else(|| "response expected to have Content-Type header")?;
-    assert_eq!(content_type, "application/json; charset=utf-8");
-
-    let mut body_buf = Vec::new();
-    response.body().read_to_end(&mut body_buf).await?;
-
-    let val: serde_json::Value = serde_json::from_slice(&body_buf)?;
-    let body_url = val
-        .get("url")
-        .ok_or_else(|| "body json has url")?
-        .as_str()
-        .ok_or_else(|| "body json url is str")?;
-    assert!(
-        body_url.contains("postman-echo.com/post"),
-        "expected body url to contain the authority and path, got: {body_url}"
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].method(), Method::POST);
+    assert_eq!(
+        requests[0].headers()["content-type"],
+        "application/json; charset=utf-8"
     );
-
-    let posted_json = val
-        .get("json")
-        .ok_or_else(|| "body json has 'json' key")?
+    let posted: serde_json::Value = serde_json::from_slice(requests[0].body())?;
+    let posted_json = posted
         .as_object()
-        .ok_or_else(|| format!("body json 'json' is object. got {val:?}"))?;
+        .ok_or_else(|| format!("posted json is object. got {posted:?}"))?;
 
     assert_eq!(posted_json.len(), 1);
     assert_eq!(
         posted_json
             .get("test")
-            .ok_or_else(|| "returned json has 'test' key")?
+            .ok_or("posted json has 'test' key")?
             .as_str()
-            .ok_or_else(|| "returned json 'test' key should be str value")?,
+            .ok_or("posted json 'test' key should be str value")?,
         "data"
     );
 
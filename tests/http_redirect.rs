@@ -0,0 +1,124 @@
+use std::error::Error;
+use wstd::http::{Client, HeaderValue, Method, RedirectPolicy, Request, StatusCode};
+use wstd::net::TcpListener;
+
+mod common;
+use common::serve_one;
+
+fn redirect(status: &str, location: &str) -> String {
+    format!(
+        "HTTP/1.1 {status}\r\nlocation: {location}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+    )
+}
+
+#[wstd::test]
+async fn follows_redirects() -> Result<(), Box<dyn Error>> {
+    let first = TcpListener::bind("127.0.0.1:8095").await?;
+    let second = TcpListener::bind("127.0.0.1:8096").await?;
+    let server = async {
+        // A 307 keeps the method and body.
+        let a = serve_one(&first, &redirect("307 Temporary Redirect", "b")).await?;
+        // A 303 turns the request into a GET on another origin.
+        let b = serve_one(
+            &first,
+            &redirect("303 See Other", "http://127.0.0.1:8096/c?x=1"),
+        )
+        .await?;
+        let c = serve_one(
+            &second,
+            "HTTP/1.1 200 OK\r\ncontent-length: 4\r\nconnection: close\r\n\r\ndone",
+        )
+        .await?;
+        std::io::Result::Ok([a, b, c])
+    };
+
+    let client = async {
+        let mut request =
+            Request::new(Method::POST, "http://127.0.0.1:8095/dir/a".parse()?).set_body("hello");
+        request
+            .headers_mut()
+            .insert("authorization", HeaderValue::from_static("secret"));
+        let mut client = Client::new();
+        client.set_redirect_policy(RedirectPolicy::limited(5));
+        let mut response = client.send(request).await?;
+        let url = response.url().cloned();
+        let body = response.body().bytes().await?;
+        Result::<_, Box<dyn Error>>::Ok((response.status_code(), url, body))
+    };
+
+    let (received, response) = futures_lite::future::zip(server, client).await;
+    let (status, url, body) = response?;
+    assert_eq!(status, StatusCode::Ok);
+    assert_eq!(url.unwrap(), "http://127.0.0.1:8096/c?x=1");
+    assert_eq!(body, b"done");
+
+    let [a, b, c] = received?;
+    assert!(a.head.starts_with("POST /dir/a "), "{}", a.head);
+    assert_eq!(a.body, b"hello");
+    assert!(b.head.starts_with("POST /dir/b "), "{}", b.head);
+    assert_eq!(b.header("authorization"), Some("secret"));
+    assert_eq!(b.body, b"hello");
+    assert!(c.head.starts_with("GET /c?x=1 "), "{}", c.head);
+    assert_eq!(c.header("authorization"), None);
+    assert_eq!(c.header("content-length"), None);
+    assert!(c.body.is_empty());
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn too_many_redirects() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8097").await?;
+    let server = async {
+        for _ in 0..3 {
+            serve_one(&listener, &redirect("302 Found", "/again")).await?;
+        }
+        std::io::Result::Ok(())
+    };
+
+    let client = async {
+        let request = Request::new(Method::GET, "http://127.0.0.1:8097/".parse()?);
+        let mut client = Client::new();
+        client.set_redirect_policy(RedirectPolicy::limited(2));
+        Result::<_, Box<dyn Error>>::Ok(client.send(request).await)
+    };
+
+    let (served, response) = futures_lite::future::zip(server, client).await;
+    served?;
+    let err = response?.expect_err("too many redirects");
+    assert!(err.to_string().contains("too many redirects"), "{err}");
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn custom_policy() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8098").await?;
+    let server = async {
+        serve_one(&listener, &redirect("301 Moved Permanently", "/next")).await?;
+        serve_one(&listener, &redirect("308 Permanent Redirect", "/stop")).await?;
+        std::io::Result::Ok(())
+    };
+
+    let client = async {
+        let request = Request::new(Method::GET, "http://127.0.0.1:8098/".parse()?);
+        let mut client = Client::new();
+        client.set_redirect_policy(RedirectPolicy::custom(|attempt| {
+            if attempt.url().path() == "/stop" {
+                attempt.stop()
+            } else {
+                attempt.follow()
+            }
+        }));
+        let response = client.send(request).await?;
+        Result::<_, Box<dyn Error>>::Ok((response.status_code(), response.url().cloned()))
+    };
+
+    let (served, response) = futures_lite::future::zip(server, client).await;
+    served?;
+    let (status, url) = response?;
+    assert_eq!(status, StatusCode::PermanentRedirect);
+    assert_eq!(url.unwrap(), "http://127.0.0.1:8098/next");
+
+    Ok(())
+}
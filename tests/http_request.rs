@@ -0,0 +1,57 @@
+use std::error::Error;
+use wstd::http::{Body, Method, Request, Version};
+
+#[wstd::test]
+async fn builder() -> Result<(), Box<dyn Error>> {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("https://example.com/search?lang=en")
+        .query([("q", "wasi & components"), ("page", "2")])
+        .version(Version::HTTP_11)
+        .header("content-type", "text/plain")
+        .header("x-multi", "a")
+        .header("x-multi", "b")
+        .body("hello")?;
+
+    assert_eq!(request.method(), Method::POST);
+    assert_eq!(
+        request.uri().to_string(),
+        "https://example.com/search?lang=en&q=wasi+%26+components&page=2"
+    );
+    assert_eq!(request.version(), Version::HTTP_11);
+    assert_eq!(request.headers()["content-type"], "text/plain");
+    assert_eq!(request.headers().get_all("x-multi").iter().count(), 2);
+    assert_eq!(request.body().len(), Some(5));
+
+    let err = Request::builder()
+        .uri("https://example.com")
+        .header("bad header", "value")
+        .build()
+        .expect_err("invalid header names are rejected");
+    assert!(err.to_string().contains("header"), "got: {err}");
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn parts_and_conversions() -> Result<(), Box<dyn Error>> {
+    let mut request = Request::new(Method::GET, "https://example.com/".parse()?);
+    *request.method_mut() = Method::PUT;
+    *request.uri_mut() = "https://example.com/item".parse()?;
+    request.extensions_mut().insert(7u32);
+
+    let (parts, body) = request.into_parts();
+    assert_eq!(parts.method, Method::PUT);
+    assert_eq!(parts.extensions.get::<u32>(), Some(&7));
+
+    let request = Request::from_parts(parts, body).set_body("data");
+    let http_request: http::Request<_> = request.into();
+    assert_eq!(http_request.uri().path(), "/item");
+
+    let request = Request::from(http_request);
+    assert_eq!(request.method(), Method::PUT);
+    assert_eq!(request.extensions().get::<u32>(), Some(&7));
+    assert_eq!(request.body().len(), Some(4));
+
+    Ok(())
+}
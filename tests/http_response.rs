@@ -0,0 +1,69 @@
+use std::error::Error;
+use wstd::http::{Body, Response, StatusCode, Version};
+use wstd::io::AsyncRead;
+
+#[wstd::test]
+async fn builder() -> Result<(), Box<dyn Error>> {
+    let mut response = Response::builder()
+        .status(StatusCode::Created)
+        .version(Version::HTTP_2)
+        .header("content-type", "text/plain")
+        .body("created")?;
+
+    assert_eq!(response.status_code(), StatusCode::Created);
+    assert_eq!(response.version(), Version::HTTP_2);
+    assert_eq!(response.headers()["content-type"], "text/plain");
+
+    let mut body = Vec::new();
+    response.body().read_to_end(&mut body).await?;
+    assert_eq!(body, b"created");
+
+    let err = Response::builder()
+        .header("content-type", "bad\nvalue")
+        .build()
+        .expect_err("invalid header values are rejected");
+    assert!(err.to_string().contains("header"), "got: {err}");
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn parts_and_conversions() -> Result<(), Box<dyn Error>> {
+    let mut response = Response::new(StatusCode::Ok).set_body("data");
+    *response.status_code_mut() = StatusCode::Accepted;
+    response.extensions_mut().insert("marker");
+
+    let (parts, body) = response.into_parts();
+    assert_eq!(parts.status, StatusCode::Accepted);
+    let response = Response::from_parts(parts, body).map_body(|body| {
+        assert_eq!(body.len(), Some(4));
+        "longer data"
+    });
+    assert_eq!(response.into_body().len(), Some(11));
+
+    let http_response = http::Response::builder()
+        .status(http::StatusCode::IM_A_TEAPOT)
+        .header("x-tea", "earl grey")
+        .body("short and stout")?;
+    let response = Response::from(http_response);
+    assert_eq!(response.status_code(), StatusCode::Other(418));
+    assert_eq!(response.headers()["x-tea"], "earl grey");
+
+    let http_response = http::Response::try_from(response)?;
+    assert_eq!(http_response.status(), http::StatusCode::IM_A_TEAPOT);
+    assert_eq!(http_response.into_body().len(), Some(15));
+
+    let response = Response::new(StatusCode::Other(1000));
+    assert!(http::Response::try_from(response).is_err());
+
+    assert_eq!(
+        StatusCode::from(http::StatusCode::NOT_FOUND),
+        StatusCode::NotFound
+    );
+    assert_eq!(
+        http::StatusCode::try_from(StatusCode::BadGateway)?,
+        http::StatusCode::BAD_GATEWAY
+    );
+
+    Ok(())
+}
@@ -0,0 +1,183 @@
+use std::error::Error;
+use wstd::http::body::StreamingBody;
+use wstd::http::{Client, Method, Request, RetryPolicy, StatusCode};
+use wstd::io::{AsyncRead, AsyncWrite};
+use wstd::net::TcpListener;
+use wstd::time::Duration;
+
+mod common;
+use common::{accept, read_head, serve, serve_one};
+
+const UNAVAILABLE: &str =
+    "HTTP/1.1 503 Service Unavailable\r\ncontent-length: 0\r\nconnection: close\r\n\r\n";
+
+fn retry_policy() -> RetryPolicy {
+    RetryPolicy::new(3).with_backoff(Duration::from_millis(1), Duration::from_millis(10))
+}
+
+#[wstd::test]
+async fn retries_unavailable() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8099").await?;
+    let server = async {
+        serve(
+            &listener,
+            &[
+                "HTTP/1.1 503 Service Unavailable\r\nretry-after: 0\r\n\
+                 content-length: 0\r\nconnection: close\r\n\r\n",
+                UNAVAILABLE,
+                "HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok",
+            ],
+        )
+        .await
+    };
+
+    let client = async {
+        let request = Request::new(Method::PUT, "http://127.0.0.1:8099/".parse()?).set_body("data");
+        let mut client = Client::new();
+        client.set_retry_policy(retry_policy());
+        let mut response = client.send(request).await?;
+        let body = response.body().bytes().await?;
+        Result::<_, Box<dyn Error>>::Ok((response.status_code(), body))
+    };
+
+    let (bodies, response) = futures_lite::future::zip(server, client).await;
+    let (status, body) = response?;
+    assert_eq!(status, StatusCode::Ok);
+    assert_eq!(body, b"ok");
+    for request in bodies? {
+        assert_eq!(request.body, b"data");
+    }
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn gives_up_after_max_attempts() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8100").await?;
+    let server = async {
+        serve(&listener, &[UNAVAILABLE; 3]).await?;
+        std::io::Result::Ok(())
+    };
+
+    let client = async {
+        let request = Request::new(Method::GET, "http://127.0.0.1:8100/".parse()?);
+        let mut client = Client::new();
+        client.set_retry_policy(retry_policy());
+        Result::<_, Box<dyn Error>>::Ok(client.send(request).await?.status_code())
+    };
+
+    let (served, status) = futures_lite::future::zip(server, client).await;
+    served?;
+    assert_eq!(status?, StatusCode::ServiceUnavailable);
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn retries_connection_refused() -> Result<(), Box<dyn Error>> {
+    // Nothing is listening yet when the first attempt is made.
+    let server = async {
+        wstd::task::sleep(Duration::from_millis(10)).await;
+        let listener = TcpListener::bind("127.0.0.1:8101").await?;
+        serve_one(
+            &listener,
+            "HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+        )
+        .await?;
+        std::io::Result::Ok(())
+    };
+
+    let client = async {
+        let request = Request::new(Method::GET, "http://127.0.0.1:8101/".parse()?);
+        let mut client = Client::new();
+        client.set_retry_policy(
+            RetryPolicy::new(5)
+                .with_backoff(Duration::from_millis(100), Duration::from_millis(100)),
+        );
+        Result::<_, Box<dyn Error>>::Ok(client.send(request).await?.status_code())
+    };
+
+    let (served, status) = futures_lite::future::zip(server, client).await;
+    served?;
+    assert_eq!(status?, StatusCode::Ok);
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn post_is_not_retried() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8102").await?;
+    let server = serve_one(&listener, UNAVAILABLE);
+
+    let client = async {
+        let request =
+            Request::new(Method::POST, "http://127.0.0.1:8102/".parse()?).set_body("data");
+        let mut client = Client::new();
+        client.set_retry_policy(retry_policy());
+        Result::<_, Box<dyn Error>>::Ok(client.send(request).await?.status_code())
+    };
+
+    let (served, status) = futures_lite::future::zip(server, client).await;
+    assert_eq!(served?.body, b"data");
+    assert_eq!(status?, StatusCode::ServiceUnavailable);
+
+    Ok(())
+}
+
+/// Reads `parts` one after the other, waiting before each one but the first.
+struct Slow {
+    parts: Vec<&'static [u8]>,
+    read: usize,
+}
+
+impl AsyncRead for Slow {
+    async fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let Some(part) = self.parts.get(self.read) else {
+            return Ok(0);
+        };
+        if self.read > 0 {
+            wstd::task::sleep(Duration::from_millis(100)).await;
+        }
+        self.read += 1;
+        buf[..part.len()].copy_from_slice(part);
+        Ok(part.len())
+    }
+}
+
+#[wstd::test]
+async fn retries_early_response() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8123").await?;
+    let server = async {
+        // Respond before the whole body was sent.
+        let mut stream = accept(&listener).await?;
+        read_head(&mut stream).await?;
+        stream.write_all(UNAVAILABLE.as_bytes()).await?;
+        stream.flush().await?;
+        drop(stream);
+        serve_one(
+            &listener,
+            "HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+        )
+        .await
+    };
+
+    let client = async {
+        let body = StreamingBody::from_reader(
+            Slow {
+                parts: vec![b"one,", b"two,", b"three"],
+                read: 0,
+            },
+            Some(13),
+        );
+        let request = Request::new(Method::PUT, "http://127.0.0.1:8123/".parse()?).set_body(body);
+        let mut client = Client::new();
+        client.set_retry_policy(retry_policy());
+        Result::<_, Box<dyn Error>>::Ok(client.send(request).await?.status_code())
+    };
+
+    let (served, status) = futures_lite::future::zip(server, client).await;
+    assert_eq!(status?, StatusCode::Ok);
+    assert_eq!(served?.body, b"one,two,three");
+
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use std::error::Error;
+use wstd::http::sse::EventStream;
+use wstd::http::{Client, Method, Request};
+use wstd::iter::AsyncIterator;
+use wstd::net::TcpListener;
+use wstd::time::Duration;
+
+mod common;
+use common::{serve, Trickle};
+
+#[wstd::test]
+async fn parse_events() -> Result<(), Box<dyn Error>> {
+    let body = "\u{feff}: a comment\r\n\
+        data: first\r\n\
+        data:  second line\r\n\
+        \r\n\
+        event: update\rid: 7\rdata\r\r\
+        retry: 250\n\
+        retry: soon\n\
+        id: bad\0id\n\
+        data:{\"n\":1}\n\
+        unknown: field\n\
+        \n\
+        event: ignored\n\
+        \n\
+        data: incomplete";
+    for step in [1, 2, 3, 1024] {
+        let mut events = EventStream::new(Trickle::new(body, step));
+        let mut parsed = Vec::new();
+        while let Some(event) = events.next().await {
+            let event = event?;
+            parsed.push((
+                event.event().to_owned(),
+                event.data().to_owned(),
+                event.id().map(str::to_owned),
+            ));
+        }
+        assert_eq!(
+            parsed,
+            [
+                ("message".to_owned(), "first\n second line".to_owned(), None),
+                ("update".to_owned(), "".to_owned(), Some("7".to_owned())),
+                (
+                    "message".to_owned(),
+                    "{\"n\":1}".to_owned(),
+                    Some("7".to_owned())
+                ),
+            ],
+            "step {step}"
+        );
+        assert_eq!(events.retry(), Duration::from_millis(250));
+        assert_eq!(events.last_event_id(), Some("7"));
+    }
+    Ok(())
+}
+
+#[wstd::test]
+async fn reconnect() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8113").await?;
+    let server = serve(
+        &listener,
+        &[
+            "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n\
+             retry: 10\nid: 1\ndata: one\n\nid: 2\ndata: two\n\ndata: lost",
+            "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream; charset=utf-8\r\n\
+             connection: close\r\n\r\nid: 3\ndata: three\n\n",
+            "HTTP/1.1 204 No Content\r\nconnection: close\r\n\r\n",
+        ],
+    );
+
+    let client = async {
+        let request = Request::new(Method::GET, "http://127.0.0.1:8113/events".parse()?);
+        let mut events = EventStream::connect(Client::new(), request).await?;
+        let mut data = Vec::new();
+        while let Some(event) = events.next().await {
+            data.push(event?.into_data());
+        }
+        assert_eq!(events.retry(), Duration::from_millis(10));
+        Result::<_, Box<dyn Error>>::Ok(data)
+    };
+
+    let (requests, data) = futures_lite::future::zip(server, client).await;
+    assert_eq!(data?, ["one", "two", "three"]);
+    let requests = requests?;
+    assert_eq!(requests[0].header("accept"), Some("text/event-stream"));
+    assert_eq!(requests[0].header("last-event-id"), None);
+    assert_eq!(requests[1].header("last-event-id"), Some("2"));
+    assert_eq!(requests[2].header("last-event-id"), Some("3"));
+
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use std::error::Error;
+use wstd::http::body::{Body, StreamingBody};
+use wstd::http::{Client, Method, Request, StatusCode};
+use wstd::io::{AsyncRead, Cursor};
+use wstd::iter::AsyncIterator;
+use wstd::net::TcpListener;
+
+mod common;
+use common::{serve_one, OK};
+
+#[wstd::test]
+async fn from_reader() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8090").await?;
+    let data: Vec<u8> = (0..100_000u32).map(|i| i as u8).collect();
+
+    let body = StreamingBody::from_reader(Cursor::new(data.clone()), Some(data.len() as u64));
+    let request = Request::new(Method::PUT, "http://127.0.0.1:8090/".parse()?).set_body(body);
+    let (request, response) =
+        futures_lite::future::zip(serve_one(&listener, OK), Client::new().send(request)).await;
+    assert_eq!(response?.status_code(), StatusCode::Ok);
+
+    let request = request?;
+    assert_eq!(request.header("content-length"), Some("100000"));
+    assert_eq!(request.body, data);
+
+    Ok(())
+}
+
+/// Produces `count` chunks of `size` bytes each.
+struct Generate {
+    count: usize,
+    size: usize,
+}
+
+impl AsyncIterator for Generate {
+    type Item = std::io::Result<Vec<u8>>;
+
+    async fn next(&mut self) -> Option<Self::Item> {
+        if self.count == 0 {
+            return None;
+        }
+        self.count -= 1;
+        Some(Ok(vec![b'a' + self.count as u8; self.size]))
+    }
+}
+
+#[wstd::test]
+async fn from_iter() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8091").await?;
+
+    let body = StreamingBody::from_iter(Generate {
+        count: 3,
+        size: 5000,
+    });
+    let request = Request::new(Method::POST, "http://127.0.0.1:8091/".parse()?).set_body(body);
+    let (request, response) =
+        futures_lite::future::zip(serve_one(&listener, OK), Client::new().send(request)).await;
+    assert_eq!(response?.status_code(), StatusCode::Ok);
+
+    let request = request?;
+    assert_eq!(request.header("transfer-encoding"), Some("chunked"));
+    let mut expected = vec![b'c'; 5000];
+    expected.extend([b'b'; 5000]);
+    expected.extend([b'a'; 5000]);
+    assert_eq!(request.body, expected);
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn len_is_declared_length() -> Result<(), Box<dyn Error>> {
+    let mut body = StreamingBody::from_reader(Cursor::new(b"0123456789".to_vec()), Some(10));
+    assert_eq!(body.len(), Some(10));
+    body.read_to_end(&mut Vec::new()).await?;
+    assert_eq!(body.len(), Some(10));
+
+    Ok(())
+}
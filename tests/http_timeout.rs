@@ -0,0 +1,117 @@
+use std::error::Error;
+use wstd::http::error::ErrorVariant;
+use wstd::http::{Client, Method, Request};
+use wstd::io::AsyncWrite;
+use wstd::net::TcpListener;
+use wstd::time::Duration;
+
+mod common;
+use common::{accept, read_head};
+
+/// Respond to one connection, sending `head` after `delay`, and then `body`,
+/// and keep the connection open for another `hold`.
+async fn serve(
+    listener: &TcpListener,
+    delay: Duration,
+    head: &str,
+    body: &str,
+    hold: Duration,
+) -> std::io::Result<()> {
+    let mut stream = accept(listener).await?;
+    read_head(&mut stream).await?;
+    wstd::task::sleep(delay).await;
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body.as_bytes()).await?;
+    stream.flush().await?;
+    wstd::task::sleep(hold).await;
+    Ok(())
+}
+
+#[wstd::test]
+async fn total_timeout_cancels_body() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8119").await?;
+    // Only half of the body is sent before the timeout.
+    let server = serve(
+        &listener,
+        Duration::from_millis(0),
+        "HTTP/1.1 200 OK\r\ncontent-length: 10\r\n\r\n",
+        "01234",
+        Duration::from_millis(1000),
+    );
+
+    let client = async {
+        let mut client = Client::new();
+        client.set_timeout(Duration::from_millis(200));
+        let request = Request::new(Method::GET, "http://127.0.0.1:8119/".parse()?);
+        let mut response = client.send(request).await?;
+        let err = response.body().bytes().await.unwrap_err();
+        Result::<_, Box<dyn Error>>::Ok(err)
+    };
+
+    let (served, err) = futures_lite::future::zip(server, client).await;
+    served?;
+    let err = err?;
+    assert!(matches!(err.variant(), ErrorVariant::Timeout), "{err:?}");
+    assert!(err.is_timeout());
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn total_timeout_cancels_send() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8120").await?;
+    let server = serve(
+        &listener,
+        Duration::from_millis(1000),
+        "HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\n",
+        "ok",
+        Duration::from_millis(0),
+    );
+
+    let client = async {
+        // The request overrides the client's longer timeout.
+        let mut client = Client::new();
+        client.set_timeout(Duration::from_secs(10));
+        let mut request = Request::new(Method::GET, "http://127.0.0.1:8120/".parse()?);
+        request.set_timeout(Duration::from_millis(200));
+        let err = client.send(request).await.unwrap_err();
+        Result::<_, Box<dyn Error>>::Ok(err)
+    };
+
+    let (served, err) = futures_lite::future::zip(server, client).await;
+    // The client may have closed the connection before the response.
+    drop(served);
+    let err = err?;
+    assert!(matches!(err.variant(), ErrorVariant::Timeout), "{err:?}");
+
+    Ok(())
+}
+
+#[wstd::test]
+async fn request_overrides_client_timeout() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8121").await?;
+    let server = serve(
+        &listener,
+        Duration::from_millis(200),
+        "HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\n",
+        "ok",
+        Duration::from_millis(0),
+    );
+
+    let client = async {
+        let mut client = Client::new();
+        client.set_timeout(Duration::from_millis(50));
+        client.set_first_byte_timeout(Duration::from_millis(50));
+        let mut request = Request::new(Method::GET, "http://127.0.0.1:8121/".parse()?);
+        request.set_timeout(Duration::from_secs(10));
+        request.set_first_byte_timeout(Duration::from_secs(10));
+        let mut response = client.send(request).await?;
+        Result::<_, Box<dyn Error>>::Ok(response.body().text().await?)
+    };
+
+    let (served, body) = futures_lite::future::zip(server, client).await;
+    served?;
+    assert_eq!(body?, "ok");
+
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use std::error::Error;
+use wstd::http::{Client, Method, Request};
+use wstd::io::AsyncRead;
+use wstd::net::TcpListener;
+
+mod common;
+use common::accept;
+
+/// A stand-in server which reads part of the request, then closes the
+/// connection while the client is still uploading the body.
+async fn close_mid_upload(listener: TcpListener) -> std::io::Result<()> {
+    let mut stream = accept(&listener).await?;
+    let mut buf = [0; 4096];
+    let mut read = 0;
+    while read < 64 * 1024 {
+        match stream.read(&mut buf).await? {
+            0 => break,
+            n => read += n,
+        }
+    }
+    Ok(())
+}
+
+#[wstd::test]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:8083").await?;
+    let server = close_mid_upload(listener);
+
+    let body = vec![b'x'; 16 * 1024 * 1024];
+    let request =
+        Request::new(Method::POST, "http://127.0.0.1:8083/upload".parse()?).set_body(body);
+    let client = async { Client::new().send(request).await };
+
+    let (served, sent) = futures_lite::future::zip(server, client).await;
+    served?;
+    let err = sent.expect_err("the upload should fail when the server hangs up");
+    let context = format!("{err:?}");
+    let written = context
+        .split("writing body failed after ")
+        .nth(1)
+        .and_then(|rest| rest.split(' ').next())
+        .and_then(|n| n.parse::<u64>().ok());
+    assert!(written.is_some_and(|n| n > 0), "{context}");
+    assert!(context.contains("sending request body"), "{context}");
+
+    Ok(())
+}
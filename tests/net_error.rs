@@ -0,0 +1,18 @@
+use std::error::Error;
+use std::io::ErrorKind;
+use wstd::net::{self, TcpListener, WasiSocketErrorCode};
+
+#[wstd::test]
+async fn address_in_use() -> Result<(), Box<dyn Error>> {
+    let _listener = TcpListener::bind("127.0.0.1:8082").await?;
+
+    let err = TcpListener::bind("127.0.0.1:8082")
+        .await
+        .expect_err("binding the same address twice should fail");
+    assert_eq!(err.kind(), ErrorKind::AddrInUse);
+
+    let source = net::Error::from_io_error(&err).ok_or("io error wraps a net::Error")?;
+    assert_eq!(source.wasi_code(), Some(WasiSocketErrorCode::AddressInUse));
+
+    Ok(())
+}
@@ -1,9 +1,25 @@
 use std::error::Error;
 use wstd::task::sleep;
-use wstd::time::Duration;
+use wstd::time::{Duration, Timer};
 
 #[wstd::test]
 async fn just_sleep() -> Result<(), Box<dyn Error>> {
     sleep(Duration::from_secs(1)).await;
     Ok(())
 }
+
+#[wstd::test]
+async fn timer_is_send_and_sync() -> Result<(), Box<dyn Error>> {
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+    let timer = Timer::after(Duration::from_millis(10));
+    assert_send_sync(&timer);
+    // The timer wins the race, even though it is polled many times before.
+    let first = futures_lite::future::or(async { Some(timer.await) }, async {
+        sleep(Duration::from_secs(1)).await;
+        None
+    })
+    .await;
+    assert!(first.is_some());
+    Ok(())
+}